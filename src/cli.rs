@@ -1,5 +1,18 @@
-use clap::{ArgGroup, Parser, Subcommand};
 use super::commands as cmd;
+use std::{path::PathBuf, time::SystemTime};
+
+use crate::utils::{
+    config::ObjectFormat,
+    date,
+    diff::DiffAlgorithm,
+    graph::GraphStyle,
+    line_range::{FileRange, LineSpec},
+    pretty::Pretty,
+    rev_list::Order,
+    similarity,
+    word_diff::WordDiffMode,
+};
+use clap::{ArgGroup, Parser, Subcommand};
 /// Rust实现的简易版本的Git，用于学习Rust语言
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -10,9 +23,14 @@ struct Cli {
 }
 /// @see <a href="https://juejin.cn/post/7242623208825110586">Rust Clap库学习 - 掘金</a>
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)] // 只在启动时解析一次，不必Box
 enum Command {
     /// 初始化仓库
-    Init,
+    Init {
+        /// object储存格式：mit(JSON) | git(与Git兼容的loose object)
+        #[clap(long, value_enum, default_value = "mit")]
+        object_format: ObjectFormat,
+    },
     /// 添加文件到暂存区
     /// @see <a href="https://juejin.cn/post/7053831273277554696">git add .，git add -A，git add -u，git add * 的区别与联系</a>
     Add {
@@ -49,14 +67,234 @@ enum Command {
     },
     /// 查看当前状态
     Status,
+    /// 显示工作区、暂存区、commit之间的差异
+    Diff {
+        /// 比较暂存区与HEAD（或指定的commit）
+        #[clap(long, action, visible_alias = "staged")]
+        cached: bool,
+
+        /// 要比较的commit（最多两个）：一个时与工作区（或暂存区）比较，两个时比较两个commit
+        #[clap(num_args = 0..=2)]
+        revs: Vec<String>,
+
+        /// 差分算法
+        #[clap(long, value_enum, default_value = "myers")]
+        diff_algorithm: DiffAlgorithm,
+
+        /// 按单词比较修改的行，默认格式为plain
+        #[clap(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "plain")]
+        word_diff: Option<WordDiffMode>,
+
+        /// 单词的正则表达式（默认为连续的非空白字符，`.`则按字符比较），隐含--word-diff
+        #[clap(long)]
+        word_diff_regex: Option<String>,
+        /// 每个文件的增删行数 & 柱状图，以及汇总
+        #[clap(long, action)]
+        stat: bool,
+
+        /// 机器可读的增删行数：`新增\t删除\t路径`
+        #[clap(long, action)]
+        numstat: bool,
+
+        /// 只显示文件名和修改类型(A/M/D/R/C)
+        #[clap(long, action)]
+        name_status: bool,
+
+        /// 检测重命名，可指定相似度阈值，如：`-M`、`-M50%`、`-M90%`
+        #[clap(
+            short = 'M',
+            long = "find-renames",
+            num_args = 0..=1,
+            default_missing_value = "50%",
+            value_parser = similarity::parse_threshold
+        )]
+        find_renames: Option<usize>,
+
+        /// 检测复制（隐含-M），来源为修改或删除的文件，如：`-C`、`-C75%`
+        #[clap(
+            short = 'C',
+            long = "find-copies",
+            num_args = 0..=1,
+            default_missing_value = "50%",
+            value_parser = similarity::parse_threshold
+        )]
+        find_copies: Option<usize>,
+    },
     /// log 现实提交历史
     #[clap(group = ArgGroup::new("sub").required(false))]
     Log {
+        /// 版本范围，如：`main`、`A..B`、`A...B`、`^X`，默认为HEAD
+        revs: Vec<String>,
+
         #[clap(short = 'A', long)]
         all: bool,
 
-        #[clap(short, long)]
+        /// 最多显示的commit数量
+        #[clap(short, long, visible_alias = "max-count")]
         number: Option<usize>,
+
+        /// 跳过前n个符合条件的commit
+        #[clap(long, default_value_t = 0)]
+        skip: usize,
+
+        /// 只显示作者（`name <email>`）匹配正则的commit
+        #[clap(long)]
+        author: Option<String>,
+
+        /// 只显示提交者匹配正则的commit
+        #[clap(long)]
+        committer: Option<String>,
+
+        /// 只显示提交信息匹配正则的commit，可以指定多个（匹配任意一个即可）
+        #[clap(long)]
+        grep: Vec<String>,
+
+        /// `--author`、`--committer`、`--grep`、`-S`、`-G`忽略大小写
+        #[clap(short = 'i', long, action)]
+        regexp_ignore_case: bool,
+
+        /// 只显示该时间之后的commit，如：`2024-01-31`、`2 weeks ago`、`yesterday`
+        #[clap(long, visible_alias = "after", value_parser = date::parse)]
+        since: Option<SystemTime>,
+
+        /// 只显示该时间之前的commit
+        #[clap(long, visible_alias = "before", value_parser = date::parse)]
+        until: Option<SystemTime>,
+
+        /// 只显示该字符串出现次数发生变化（新增或删除）的commit，如：`-S"fn main"`
+        #[clap(short = 'S', conflicts_with = "pickaxe_grep")]
+        pickaxe: Option<String>,
+
+        /// 只显示新增或删除的行匹配该正则的commit，如：`-G"^fn \w+"`
+        #[clap(short = 'G')]
+        pickaxe_grep: Option<String>,
+
+        /// 将`-S`的参数视为正则
+        #[clap(long, action, requires = "pickaxe")]
+        pickaxe_regex: bool,
+
+        /// 跟踪文件中一段行的修改：`-L <start>,<end>:<file>`、`-L :<funcname>:<file>`，可以有多个
+        #[clap(short = 'L', value_parser = FileRange::parse, conflicts_with_all = ["follow", "paths"])]
+        line_ranges: Vec<FileRange>,
+
+        /// 只显示修改了这些文件或目录的commit（在`--`之后），如：`mit log -- src/`
+        #[clap(last = true)]
+        paths: Vec<String>,
+        /// 子commit一定在parent之前，同一条分支上的commit连续输出
+        #[clap(long, action, conflicts_with = "date_order")]
+        topo_order: bool,
+
+        /// 子commit一定在parent之前，其余按提交时间排序
+        #[clap(long, action)]
+        date_order: bool,
+
+        /// 从旧到新输出
+        #[clap(long, action)]
+        reverse: bool,
+
+        /// 在左侧绘制分支线：`--graph`、`--graph=unicode`
+        #[clap(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "ascii")]
+        #[clap(conflicts_with = "reverse")]
+        graph: Option<GraphStyle>,
+
+        /// 每个commit一行：缩写hash、ref和标题，即`--pretty=oneline`并缩写hash
+        #[clap(long, action)]
+        oneline: bool,
+
+        /// 输出格式：oneline | short | medium（默认）| full | fuller | json | format:<string>
+        #[clap(long, value_parser = Pretty::parse, num_args = 0..=1, require_equals = true)]
+        #[clap(default_missing_value = "medium", conflicts_with_all = ["oneline", "format"])]
+        pretty: Option<Pretty>,
+
+        /// 同`--pretty`，如：`--format="%h %an %s"`、`--format=json`
+        #[clap(long, value_parser = Pretty::parse, conflicts_with = "oneline")]
+        format: Option<Pretty>,
+        /// 每个commit修改的文件 & 增删行数
+        #[clap(long, action)]
+        stat: bool,
+
+        /// 每个commit的增删行数（机器可读）
+        #[clap(long, action)]
+        numstat: bool,
+
+        /// 每个commit修改的文件名和修改类型(A/M/D/R)
+        #[clap(long, action)]
+        name_status: bool,
+
+        /// 差分算法（用于补丁、-L、-S、-G）
+        #[clap(long, value_enum, default_value = "myers")]
+        diff_algorithm: DiffAlgorithm,
+
+        /// 只显示修改了该文件的commit，并跟踪文件的重命名
+        #[clap(long)]
+        follow: Option<String>,
+    },
+    /// 显示文件每一行最后一次修改所在的commit、作者、时间和行号
+    Blame {
+        /// 文件路径
+        file: String,
+        /// 从该版本开始查找，默认为HEAD
+        rev: Option<String>,
+
+        /// 只显示这些行：`-L <start>,<end>`、`-L :<funcname>`，可以有多个
+        #[clap(short = 'L', value_parser = LineSpec::parse)]
+        ranges: Vec<LineSpec>,
+
+        /// 比较时忽略空白字符
+        #[clap(short = 'w', action)]
+        ignore_whitespace: bool,
+
+        /// 忽略该commit（如批量格式化）的修改，修改的行按位置归属于更早的commit，可以有多个
+        #[clap(long = "ignore-rev")]
+        ignore_revs: Vec<String>,
+    },
+    /// 按顺序列出版本范围内的commit，每行一个hash
+    RevList {
+        /// 版本范围，如：`HEAD`、`A..B`、`A...B`、`^X`，可以有多个
+        #[clap(required = true)]
+        revs: Vec<String>,
+        /// 子commit一定在parent之前，同一条分支上的commit连续输出
+        #[clap(long, action, conflicts_with = "date_order")]
+        topo_order: bool,
+
+        /// 子commit一定在parent之前，其余按提交时间排序
+        #[clap(long, action)]
+        date_order: bool,
+
+        /// 从旧到新输出
+        #[clap(long, action)]
+        reverse: bool,
+    },
+    /// 显示object：commit的信息和修改、tree的文件列表、blob的内容；支持`<rev>:<path>`
+    Show {
+        /// 要显示的object，默认为HEAD，如：`HEAD`、`master:src/main.rs`、`<hash>`
+        objects: Vec<String>,
+
+        /// commit修改的文件 & 增删行数
+        #[clap(long, action)]
+        stat: bool,
+
+        /// commit的增删行数（机器可读）
+        #[clap(long, action)]
+        numstat: bool,
+
+        /// commit修改的文件名和修改类型(A/M/D/R)
+        #[clap(long, action)]
+        name_status: bool,
+
+        /// 检测重命名，可指定相似度阈值
+        #[clap(
+            short = 'M',
+            long = "find-renames",
+            num_args = 0..=1,
+            default_missing_value = "50%",
+            value_parser = similarity::parse_threshold
+        )]
+        find_renames: Option<usize>,
+
+        /// 差分算法
+        #[clap(long, value_enum, default_value = "myers")]
+        diff_algorithm: DiffAlgorithm,
     },
     /// branch
     Branch {
@@ -72,10 +310,14 @@ enum Command {
         #[clap(short, long, action, group = "sub", default_value = "true")]
         list: bool,
 
-        /// 删除制定分支，不能删除当前所在分支
-        #[clap(short = 'D', long, group = "sub")]
+        /// 删除制定分支，不能删除当前所在分支；分支必须已经完全合并到HEAD
+        #[clap(short = 'd', long, group = "sub")]
         delete: Option<String>,
 
+        /// 强制删除分支，即使没有完全合并
+        #[clap(short = 'D', group = "sub")]
+        force_delete: Option<String>,
+
         /// 显示当前分支
         #[clap(long, action, group = "sub")]
         show_current: bool,
@@ -112,19 +354,86 @@ enum Command {
         /// staged
         #[clap(long, short = 'S', action)]
         staged: bool,
+
+        /// 对于未解决冲突的文件，恢复为ours的版本(stage 2)
+        #[clap(long, action, conflicts_with_all = ["source", "staged", "theirs"])]
+        ours: bool,
+
+        /// 对于未解决冲突的文件，恢复为theirs的版本(stage 3)
+        #[clap(long, action, conflicts_with_all = ["source", "staged"])]
+        theirs: bool,
     },
     /// merge
     Merge {
         /// 要合并的分支
-        #[clap(required = true)]
-        branch: String,
+        #[clap(required_unless_present_any = ["continue_merge", "abort"])]
+        branch: Option<String>,
+
+        /// 解决冲突后，完成合并提交
+        #[clap(long = "continue", action, conflicts_with_all = ["branch", "abort"])]
+        continue_merge: bool,
+
+        /// 放弃合并，恢复到合并前的状态
+        #[clap(long, action, conflicts_with = "branch")]
+        abort: bool,
+
+        /// 行级合并使用的差分算法
+        #[clap(long, value_enum, default_value = "myers")]
+        diff_algorithm: DiffAlgorithm,
+    },
+    /// 查找两个commit的最近公共祖先
+    MergeBase {
+        /// 两个commit（分支名或commit hash）
+        #[clap(num_args = 2, required = true)]
+        commits: Vec<String>,
+
+        /// 输出所有的最近公共祖先，而不仅是一个
+        #[clap(long, action, conflicts_with = "is_ancestor")]
+        all: bool,
+
+        /// 检查第一个commit是否是第二个的祖先，结果通过退出码表示：是则为0，否则为1
+        #[clap(long, action)]
+        is_ancestor: bool,
+    },
+    /// 整理仓库：将object打包为packfile，并丢弃过期的不可达object
+    Gc {
+        /// 丢弃早于该时间的不可达object，如：now、never、2.weeks.ago、2024-01-01
+        #[clap(long)]
+        prune: Option<String>,
+    },
+    /// 将所有object打包为一个packfile，相似的object以delta储存
+    Repack,
+    /// 检查仓库完整性，发现损坏时以非0退出码退出
+    Fsck {
+        /// 列出所有不可达的object，而不仅是dangling object
+        #[clap(long, action)]
+        unreachable: bool,
+    },
+    /// 删除不可达的loose object
+    Prune {
+        /// 只列出将被删除的object，不实际删除
+        #[clap(long, short = 'n', action)]
+        dry_run: bool,
+
+        /// 只删除早于该时间的object，如：now、never、2.weeks.ago、2024-01-01
+        #[clap(long)]
+        expire: Option<String>,
     },
 }
+/// `--topo-order`、`--date-order`
+fn walk_order(topo_order: bool, date_order: bool) -> Order {
+    match (topo_order, date_order) {
+        (true, _) => Order::Topo,
+        (_, true) => Order::Date,
+        _ => Order::Default,
+    }
+}
+
 pub fn handle_command() {
     let cli = Cli::parse();
     match cli.command {
-        Command::Init => {
-            cmd::init().expect("初始化失败");
+        Command::Init { object_format } => {
+            cmd::init(object_format).expect("初始化失败");
         }
         Command::Add { files, all, update } => {
             cmd::add(files, all, update);
@@ -138,16 +447,146 @@ pub fn handle_command() {
         Command::Status => {
             cmd::status();
         }
-        Command::Log { all, number } => {
-            cmd::log(all, number);
+        Command::Diff {
+            cached,
+            revs,
+            diff_algorithm,
+            word_diff,
+            word_diff_regex,
+            stat,
+            numstat,
+            name_status,
+            find_renames,
+            find_copies,
+        } => {
+            let word_regex = match word_diff_regex.as_deref().map(regex::Regex::new) {
+                Some(Err(err)) => {
+                    println!("fatal: invalid regular expression: {}", err);
+                    return;
+                }
+                regex => regex.map(Result::unwrap),
+            };
+            let word_diff = word_diff.or(word_regex.as_ref().map(|_| WordDiffMode::Plain));
+            let options = cmd::diff::DiffOptions {
+                algorithm: diff_algorithm,
+                word_diff,
+                word_regex,
+                stat,
+                numstat,
+                name_status,
+                find_renames,
+                find_copies,
+            };
+            cmd::diff(cached, revs, options);
         }
-        Command::Branch { list, delete, new_branch, commit_hash, show_current } => {
-            cmd::branch(new_branch, commit_hash, list, delete, show_current);
+        Command::Log {
+            revs,
+            all,
+            number,
+            skip,
+            author,
+            committer,
+            grep,
+            regexp_ignore_case,
+            since,
+            until,
+            pickaxe,
+            pickaxe_grep,
+            pickaxe_regex,
+            line_ranges,
+            paths,
+            topo_order,
+            date_order,
+            reverse,
+            graph,
+            oneline,
+            pretty,
+            format,
+            stat,
+            numstat,
+            name_status,
+            diff_algorithm,
+            follow,
+        } => {
+            let diff = cmd::diff::DiffOptions {
+                stat,
+                numstat,
+                name_status,
+                algorithm: diff_algorithm,
+                ..Default::default()
+            };
+            let options = cmd::log::LogOptions {
+                all,
+                number,
+                revs,
+                order: walk_order(topo_order, date_order),
+                reverse,
+                follow: follow.map(PathBuf::from),
+                diff,
+                graph,
+                pretty: match oneline {
+                    true => Pretty::Oneline { abbrev: true },
+                    false => pretty.or(format).unwrap_or_default(),
+                },
+                paths: paths.into_iter().map(PathBuf::from).collect(),
+                author,
+                committer,
+                grep,
+                ignore_case: regexp_ignore_case,
+                since,
+                until,
+                skip,
+                pickaxe,
+                pickaxe_grep,
+                pickaxe_regex,
+                line_ranges,
+            };
+            cmd::log(options);
+        }
+        Command::Blame { file, rev, ranges, ignore_whitespace, ignore_revs } => {
+            let options = cmd::blame::BlameOptions { ranges, ignore_whitespace, ignore_revs };
+            cmd::blame(file, rev, options);
+        }
+        Command::RevList { revs, topo_order, date_order, reverse } => {
+            cmd::rev_list(revs, walk_order(topo_order, date_order), reverse);
+        }
+        Command::Show {
+            objects,
+            stat,
+            numstat,
+            name_status,
+            find_renames,
+            diff_algorithm,
+        } => {
+            let options = cmd::diff::DiffOptions {
+                stat,
+                numstat,
+                name_status,
+                find_renames,
+                algorithm: diff_algorithm,
+                ..Default::default()
+            };
+            cmd::show(objects, options);
+        }
+        Command::Branch {
+            list,
+            delete,
+            force_delete,
+            new_branch,
+            commit_hash,
+            show_current,
+        } => {
+            let force = force_delete.is_some();
+            cmd::branch(new_branch, commit_hash, list, delete.or(force_delete), force, show_current);
         }
         Command::Switch { branch, create, detach } => {
             cmd::switch(branch, create, detach);
         }
-        Command::Restore { path, source, mut worktree, staged } => {
+        Command::Restore { path, source, mut worktree, staged, ours, theirs } => {
+            if ours || theirs {
+                cmd::restore_stage(path, theirs);
+                return;
+            }
             // 未指定stage和worktree时，默认操作worktree
             // 指定 --staged 将仅还原index
             if !staged {
@@ -160,8 +599,27 @@ pub fn handle_command() {
             */
             cmd::restore(path, source, worktree, staged);
         }
-        Command::Merge { branch } => {
-            cmd::merge(branch);
+        Command::Merge { branch, continue_merge, abort, diff_algorithm } => {
+            cmd::merge(branch, continue_merge, abort, diff_algorithm);
+        }
+        Command::MergeBase { commits, all, is_ancestor } => {
+            if !cmd::merge_base(&commits[0], &commits[1], all, is_ancestor) {
+                std::process::exit(1);
+            }
+        }
+        Command::Gc { prune } => {
+            cmd::gc(prune);
+        }
+        Command::Repack => {
+            cmd::repack();
+        }
+        Command::Fsck { unreachable } => {
+            if !cmd::fsck(unreachable) {
+                std::process::exit(1);
+            }
+        }
+        Command::Prune { dry_run, expire } => {
+            cmd::prune(expire, dry_run);
         }
     }
 }
@@ -33,6 +33,12 @@ pub fn add(raw_paths: Vec<String>, all: bool, mut update: bool) {
     }
 
     let index = Index::get_instance();
+    // 未解决冲突的文件：add即标记为已解决（--update同样适用）
+    for file in index.get_conflicted_files() {
+        if file.include_in(&paths) && !files.iter().any(|f| f.to_absolute() == file) {
+            files.push(file.to_relative());
+        }
+    }
     for file in &files {
         add_a_file(file, index);
     }
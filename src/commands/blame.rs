@@ -0,0 +1,224 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    commands::diff,
+    models::{Commit, Hash},
+    utils::{
+        diff::{edit_script, split_lines, DiffAlgorithm, Edit},
+        line_range::{self, LineSpec},
+        rev_list::{self, Order, RevSet},
+        revision, PathExt,
+    },
+};
+
+/// blame的选项
+#[derive(Debug, Clone, Default)]
+pub struct BlameOptions {
+    pub ranges: Vec<LineSpec>,    // `-L`：只显示这些行，默认为整个文件
+    pub ignore_whitespace: bool,  // `-w`：比较时忽略空白字符
+    pub ignore_revs: Vec<String>, // `--ignore-rev`：忽略这些commit的修改（如批量格式化）
+}
+
+/// 每个文件中待查找的行：(最终版本中的行号, 当前版本中的行号)，从0开始
+type PendingLines = BTreeMap<PathBuf, Vec<(usize, usize)>>;
+
+/** 显示文件（rev中的版本，默认为HEAD）每一行最后一次修改所在的commit、作者、时间和行号
+ */
+pub fn blame(file: String, rev: Option<String>, options: BlameOptions) {
+    let path = PathBuf::from(file).to_absolute().to_relative_workdir();
+    let lines = match annotate(&path, rev.as_deref().unwrap_or("HEAD"), &options) {
+        Ok(lines) => lines,
+        Err(msg) => {
+            println!("{}", msg);
+            return;
+        }
+    };
+    let mut commits = HashMap::new();
+    for (_, hash, _) in &lines {
+        commits.entry(hash.clone()).or_insert_with(|| Commit::load(hash));
+    }
+    let author_width = commits
+        .values()
+        .map(|commit| commit.get_author().chars().count())
+        .max()
+        .unwrap_or(0);
+    let number_width = lines.last().map_or(1, |(number, _, _)| (number + 1).to_string().len());
+    for (number, hash, text) in &lines {
+        let commit = &commits[hash];
+        println!(
+            "{} ({:<author_width$} {} {:>number_width$}) {}",
+            &hash[..7],
+            commit.get_author(),
+            commit.get_date(),
+            number + 1,
+            text
+        );
+    }
+}
+
+/** 查找每一行的来源：从rev开始按拓扑顺序（子commit在前）处理commit，
+在parent中没有修改的行（见[line_map]）交给parent继续查找，其余的行归属于该commit；merge中优先交给靠前的parent
+<br>被忽略的commit中修改的行，按位置对应到第一个parent中被替换的行，没有对应的行时仍归属于该commit
+<br>返回(行号, commit, 行的内容)，按行号排序
+ */
+fn annotate(path: &Path, rev: &str, options: &BlameOptions) -> Result<Vec<(usize, Hash, String)>, String> {
+    let head = revision::resolve_commit(rev).ok_or_else(|| revision::unknown_revision(rev))?;
+    let mut ignored = HashSet::new();
+    for rev in &options.ignore_revs {
+        ignored.insert(revision::resolve_commit(rev).ok_or_else(|| revision::unknown_revision(rev))?);
+    }
+    let Some(version) = diff::commit_file(&head, path) else {
+        return Err(format!("fatal: {}中没有文件{:?}", rev, path));
+    };
+    let lines = split_lines(&version.content);
+    let numbers = match options.ranges.is_empty() {
+        true => (0..lines.len()).collect::<Vec<_>>(),
+        false => {
+            let ranges = options
+                .ranges
+                .iter()
+                .map(|spec| spec.resolve(&lines))
+                .collect::<Result<_, _>>()?;
+            line_range::merge(ranges).into_iter().flatten().collect()
+        }
+    };
+
+    let mut blamed = Vec::new();
+    let mut pending = HashMap::<Hash, PendingLines>::new();
+    pending
+        .entry(head.clone())
+        .or_default()
+        .insert(path.to_path_buf(), numbers.iter().map(|&i| (i, i)).collect());
+    let set = RevSet { include: vec![head], ..Default::default() };
+    for hash in rev_list::walk(&set, Order::Topo) {
+        if pending.is_empty() {
+            break;
+        }
+        let Some(files) = pending.remove(&hash) else {
+            continue;
+        };
+        let parents = Commit::load(&hash).get_parent_hash();
+        for (path, mut remaining) in files {
+            let content = diff::commit_file(&hash, &path).expect("查找的文件不存在").content;
+            for (i, parent) in parents.iter().enumerate() {
+                let Some((old_path, old)) = diff::parent_file(parent, &hash, &path) else {
+                    continue; // 新增的文件
+                };
+                let map =
+                    line_map(&old.content, &content, options.ignore_whitespace, i == 0 && ignored.contains(&hash));
+                let (passed, rest): (Vec<_>, Vec<_>) =
+                    remaining.into_iter().partition(|(_, line)| map.contains_key(line));
+                remaining = rest;
+                if !passed.is_empty() {
+                    let lines = pending.entry(parent.clone()).or_default().entry(old_path).or_default();
+                    lines.extend(passed.into_iter().map(|(number, line)| (number, map[&line])));
+                }
+            }
+            blamed.extend(remaining.into_iter().map(|(number, _)| (number, hash.clone())));
+        }
+    }
+    blamed.sort();
+    let text = |number: usize| {
+        let text = String::from_utf8_lossy(lines[number]);
+        text.strip_suffix('\n').unwrap_or(&text).to_string()
+    };
+    Ok(blamed.into_iter().map(|(number, hash)| (number, hash, text(number))).collect())
+}
+
+/** 新版本中的行 -> 旧版本中相同的行；ignore_whitespace时比较去掉所有空白字符后的内容
+<br>replaced时，被替换的行（连续的删除之后紧跟新增）按位置一一对应
+ */
+fn line_map(old: &[u8], new: &[u8], ignore_whitespace: bool, replaced: bool) -> HashMap<usize, usize> {
+    let key = |line: &[u8]| match ignore_whitespace {
+        true => line.iter().filter(|b| !b.is_ascii_whitespace()).copied().collect(),
+        false => line.to_vec(),
+    };
+    let old_lines = split_lines(old).into_iter().map(key).collect::<Vec<_>>();
+    let new_lines = split_lines(new).into_iter().map(key).collect::<Vec<_>>();
+    let matches = DiffAlgorithm::default().matches(&old_lines, &new_lines);
+    let mut map = matches.iter().map(|&(i, j)| (j, i)).collect::<HashMap<_, _>>();
+    if replaced {
+        // 编辑脚本中每处修改都是先删除后新增
+        let (mut deleted, mut inserted) = (Vec::new(), 0);
+        for edit in edit_script(&matches, old_lines.len(), new_lines.len()) {
+            match edit {
+                Edit::Equal(..) => (deleted, inserted) = (Vec::new(), 0),
+                Edit::Delete(i) => deleted.push(i),
+                Edit::Insert(j) => {
+                    if let Some(&i) = deleted.get(inserted) {
+                        map.insert(j, i);
+                    }
+                    inserted += 1;
+                }
+            }
+        }
+    }
+    map
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{commands as cmd, models::head, utils::test};
+
+    fn commit(content: &str, message: &str) -> Hash {
+        test::ensure_file(Path::new("a.txt"), Some(content));
+        cmd::add(vec![], true, false);
+        cmd::commit(message.into(), false);
+        head::current_head_commit()
+    }
+
+    fn blame(options: &BlameOptions) -> Vec<Hash> {
+        let lines = annotate(Path::new("a.txt"), "HEAD", options).unwrap();
+        lines.into_iter().map(|(_, hash, _)| hash).collect()
+    }
+
+    #[test]
+    fn test_blame() {
+        test::setup_with_empty_workdir();
+        let first = commit("a\nb\nc\n", "first");
+        let second = commit("a\nB\nc\nd\n", "second");
+        let reformat = commit("a\n  B\nc\nd\n", "reformat");
+        let lines = annotate(Path::new("a.txt"), "HEAD", &BlameOptions::default()).unwrap();
+        let text = lines.iter().map(|(_, _, text)| text.as_str()).collect::<Vec<_>>();
+        assert_eq!(text, ["a", "  B", "c", "d"]);
+        assert_eq!(blame(&BlameOptions::default()), [&first, &reformat, &first, &second].map(Hash::clone));
+        assert_eq!(annotate(Path::new("a.txt"), "HEAD~1", &BlameOptions::default()).unwrap().len(), 4);
+
+        // 忽略空白字符 & 忽略格式化的commit
+        let ignore_whitespace = BlameOptions { ignore_whitespace: true, ..Default::default() };
+        assert_eq!(blame(&ignore_whitespace), [&first, &second, &first, &second].map(Hash::clone));
+        let ignore_revs = BlameOptions { ignore_revs: vec![reformat.clone()], ..Default::default() };
+        assert_eq!(blame(&ignore_revs), [&first, &second, &first, &second].map(Hash::clone));
+
+        let ranges = vec![LineSpec::parse("2,3").unwrap(), LineSpec::parse("/d/").unwrap()];
+        let lines = annotate(Path::new("a.txt"), "HEAD", &BlameOptions { ranges, ..Default::default() }).unwrap();
+        let numbers = lines.iter().map(|(number, _, _)| *number).collect::<Vec<_>>();
+        assert_eq!(numbers, [1, 2, 3]);
+
+        assert!(annotate(Path::new("missing.txt"), "HEAD", &BlameOptions::default()).is_err());
+        assert!(annotate(Path::new("a.txt"), "no_such_rev", &BlameOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_blame_rename_merge() {
+        test::setup_with_empty_workdir();
+        let base = commit("1\n2\n3\n", "base");
+        cmd::switch(None, Some("feature".into()), false);
+        let feature = commit("1\n2\nthree\n", "feature");
+        cmd::switch(Some("master".into()), None, false);
+        let master = commit("one\n2\n3\n", "master");
+        cmd::merge(Some("feature".into()), false, false, DiffAlgorithm::Myers);
+        assert_eq!(blame(&BlameOptions::default()), [&master, &base, &feature].map(Hash::clone));
+
+        std::fs::rename("a.txt", "b.txt").unwrap();
+        cmd::add(vec![], true, false);
+        cmd::commit("rename".into(), false);
+        let lines = annotate(Path::new("b.txt"), "HEAD", &BlameOptions::default()).unwrap();
+        let commits = lines.into_iter().map(|(_, hash, _)| hash).collect::<Vec<_>>();
+        assert_eq!(commits, [&master, &base, &feature].map(Hash::clone));
+    }
+}
@@ -2,7 +2,7 @@ use colored::Colorize;
 
 use crate::{
     models::*,
-    utils::{store, util},
+    utils::{merge_base::CommitGraph, revision},
 };
 
 // branch error
@@ -12,28 +12,16 @@ enum BranchErr {
 
     BranchNoExist,
     BranchCheckedOut,
+    BranchNotMerged,
 }
-// 从分支名、commit hash中搜索commit
-fn search_hash(commit_hash: Hash) -> Option<Hash> {
-    // 分支名
-    if head::list_local_branches().contains(&commit_hash) {
-        let commit_hash = head::get_branch_head(&commit_hash);
-        return Some(commit_hash);
-    }
-    // commit hash
-    let store = store::Store::new();
-    store.search(&commit_hash)
-}
-
 fn create_branch(branch_name: String, _base_commit: Hash) -> Result<(), BranchErr> {
     // 找到正确的base_commit_hash
-    let base_commit = search_hash(_base_commit.clone());
-    if base_commit.is_none() || util::check_object_type(base_commit.clone().unwrap()) != util::ObjectType::Commit {
-        println!("fatal: 非法的 commit: '{}'", _base_commit);
+    let Some(base_commit) = revision::resolve_commit(&_base_commit) else {
+        println!("{}", revision::unknown_revision(&_base_commit));
         return Err(BranchErr::InvalidObject);
-    }
+    };
 
-    let base_commit = Commit::load(&base_commit.unwrap());
+    let base_commit = Commit::load(&base_commit);
 
     let exist_branches = head::list_local_branches();
     if exist_branches.contains(&branch_name) {
@@ -41,11 +29,13 @@ fn create_branch(branch_name: String, _base_commit: Hash) -> Result<(), BranchEr
         return Err(BranchErr::BranchExist);
     }
 
-    head::update_branch(&branch_name, &base_commit.get_hash());
+    let message = format!("branch: Created from {}", _base_commit);
+    head::update_branch(&branch_name, &base_commit.get_hash(), &message);
     Ok(())
 }
 
-fn delete_branch(branch_name: String) -> Result<(), BranchErr> {
+/// 删除分支；force为false时，分支必须已经完全合并到HEAD
+fn delete_branch(branch_name: String, force: bool) -> Result<(), BranchErr> {
     let branches = head::list_local_branches();
     if !branches.contains(&branch_name) {
         println!("error: 分支 '{}' 不存在", branch_name);
@@ -62,6 +52,15 @@ fn delete_branch(branch_name: String) -> Result<(), BranchErr> {
         return Err(BranchErr::BranchCheckedOut);
     }
 
+    // 分支的commit不是HEAD的祖先时，删除分支会导致这些commit不可达
+    let branch_commit = head::get_branch_head(&branch_name);
+    let current_commit = head::current_head_commit();
+    if !force && (current_commit.is_empty() || !CommitGraph::new().is_ancestor(&branch_commit, &current_commit)) {
+        println!("error: 分支 '{}' 没有完全合并", branch_name);
+        println!("如果确定要删除它，请运行 'mit branch -D {}'", branch_name);
+        return Err(BranchErr::BranchNotMerged);
+    }
+
     head::delete_branch(&branch_name); // 删除refs/heads/branch_name，不删除任何commit
     Ok(())
 }
@@ -100,13 +99,14 @@ pub fn branch(
     commit_hash: Option<Hash>,
     list: bool,
     delete: Option<String>,
+    force: bool,
     show_current: bool,
 ) {
-    if new_branch.is_some() {
-        let basic_commit = commit_hash.unwrap_or_else(head::current_head_commit); // 默认使用当前commit
-        let _ = create_branch(new_branch.unwrap(), basic_commit);
-    } else if delete.is_some() {
-        let _ = delete_branch(delete.unwrap());
+    if let Some(new_branch) = new_branch {
+        let basic_commit = commit_hash.unwrap_or_else(|| "HEAD".to_string()); // 默认使用当前commit
+        let _ = create_branch(new_branch, basic_commit);
+    } else if let Some(delete) = delete {
+        let _ = delete_branch(delete, force);
     } else if show_current {
         show_current_branch();
     } else if list {
@@ -161,7 +161,7 @@ mod test {
         test::setup_with_clean_mit();
 
         // no commit: invalid object
-        let result = delete_branch("test_branch".to_string());
+        let result = delete_branch("test_branch".to_string(), false);
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), BranchErr::BranchNoExist));
         assert!(head::list_local_branches().is_empty());
@@ -177,8 +177,25 @@ mod test {
         assert!(head::get_branch_head(&new_branch) == commit_hash, "new branch head error");
 
         // branch exist
-        let result = delete_branch(new_branch.clone());
+        let result = delete_branch(new_branch.clone(), false);
         assert!(result.is_ok());
         assert!(!head::list_local_branches().contains(&new_branch), "new branch not in list");
     }
+
+    #[test]
+    fn test_delete_unmerged_branch() {
+        test::setup_with_clean_mit();
+        commands::commit::commit("test commit 1".to_string(), true);
+        commands::switch::switch(None, Some("feature".to_string()), false);
+        commands::commit::commit("feature commit".to_string(), true);
+        commands::switch::switch(Some("master".to_string()), None, false);
+
+        let result = delete_branch("feature".to_string(), false);
+        assert!(matches!(result.unwrap_err(), BranchErr::BranchNotMerged));
+        assert!(head::list_local_branches().contains(&"feature".to_string()));
+
+        let result = delete_branch("feature".to_string(), true);
+        assert!(result.is_ok());
+        assert!(!head::list_local_branches().contains(&"feature".to_string()));
+    }
 }
@@ -1,25 +1,42 @@
 use crate::models::*;
 
-use super::status;
+use super::{merge, status};
 
+/** 提交暂存区
+<br>暂存区存在未解决的冲突时无法提交；合并中断(MERGE_HEAD存在)时，提交的commit会同时记录MERGE_HEAD作为第二个parent
+ */
 pub fn commit(message: String, allow_empty: bool) {
     let index = Index::get_instance();
-    if !allow_empty && status::changes_to_be_committed().is_empty() {
+    let merge_head = head::merge_head();
+    let unmerged = merge::unmerged_paths();
+    if !unmerged.is_empty() {
+        println!("fatal: 存在未解决的冲突，无法提交：");
+        for path in unmerged {
+            println!("\t{}", path.display());
+        }
+        return;
+    }
+    if merge_head.is_none() && !allow_empty && status::changes_to_be_committed().is_empty() {
         panic!("工作区没有任何改动，不需要提交");
     }
 
     let current_head = head::current_head();
     let current_commit_hash = head::current_head_commit();
 
-    let mut commit = {
-        if current_commit_hash.is_empty() {
-            Commit::new(index, vec![], message.clone())
-        } else {
-            Commit::new(index, vec![current_commit_hash.clone()], message.clone())
-        }
+    let mut parents = Vec::new();
+    if !current_commit_hash.is_empty() {
+        parents.push(current_commit_hash.clone());
+    }
+    parents.extend(merge_head); // 合并提交
+    let kind = match parents.len() {
+        0 => " (initial)",
+        1 => "",
+        _ => " (merge)",
     };
+    let mut commit = Commit::new(index, parents, message.clone());
     let commit_hash = commit.save();
-    head::update_head_commit(&commit_hash);
+    head::update_head_commit(&commit_hash, &format!("commit{}: {}", kind, message));
+    head::clear_merge_state();
 
     match current_head {
         head::Head::Branch(branch_name) => {
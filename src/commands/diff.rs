@@ -0,0 +1,873 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use colored::Colorize;
+use regex::Regex;
+
+use crate::{
+    commands::{merge, status},
+    models::{head, Blob, Commit, Hash, Index},
+    utils::{
+        diff::{self, DiffAlgorithm},
+        revision, similarity, util,
+        word_diff::{self, WordDiffMode},
+        PathExt,
+    },
+};
+
+const CONTEXT_LINES: usize = 3;
+const FILE_MODE: &str = "100644"; // 暂不区分文件模式
+const NULL_HASH: &str = "0000000";
+const STAT_GRAPH_WIDTH: usize = 40; // --stat中+/-柱状图的最大宽度
+
+/// patch的生成选项
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    pub algorithm: DiffAlgorithm,
+    pub word_diff: Option<WordDiffMode>, // 按单词比较修改的行
+    pub word_regex: Option<Regex>,       // 单词的正则，默认为连续的非空白字符
+    pub stat: bool,                      // 每个文件的增删行数 & 柱状图
+    pub numstat: bool,                   // 机器可读的增删行数
+    pub name_status: bool,               // 文件名 & 修改类型(A/M/D/R/C)
+    pub find_renames: Option<usize>,     // 重命名检测的相似度阈值(%)，None表示不检测
+    pub find_copies: Option<usize>,      // 复制检测的相似度阈值(%)，来源为修改或删除的文件
+}
+
+impl DiffOptions {
+    /// 是否只输出摘要（不输出patch）
+    pub fn summary_only(&self) -> bool {
+        self.stat || self.numstat || self.name_status
+    }
+}
+
+/// 文件在diff某一侧的版本
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub hash: Hash,
+    pub content: Vec<u8>,
+}
+
+impl Version {
+    fn from_blob(hash: &Hash) -> Version {
+        Version { hash: hash.clone(), content: Blob::load(hash).get_content() }
+    }
+
+    /// 工作区中的文件，hash为内容对应的blob hash（不写入object）
+    fn from_worktree(path: &Path) -> Version {
+        let content = util::read_workfile(&path.to_absolute_workdir());
+        Version { hash: Blob::dry_new(content.clone()).get_hash(), content }
+    }
+}
+
+/// 一个文件的差异：相对路径(to workdir)，新增的文件没有old，删除的文件没有new
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub old: Option<Version>,
+    pub new: Option<Version>,
+    pub rename: Option<Rename>, // 重命名或复制的来源，old为来源的内容
+}
+
+/// 重命名 & 复制的来源
+#[derive(Debug, Clone)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub similarity: usize,
+    pub copy: bool,
+}
+
+impl FileDiff {
+    /// 输出用的路径，统一使用`/`分隔
+    fn display_path(&self) -> String {
+        self.path.to_string_lossy().replace('\\', "/")
+    }
+
+    /// 重命名 & 复制的来源路径，否则为自身
+    fn display_old_path(&self) -> String {
+        match &self.rename {
+            Some(rename) => rename.from.to_string_lossy().replace('\\', "/"),
+            None => self.display_path(),
+        }
+    }
+
+    /// --stat & --numstat中的文件名，重命名显示为`old => new`
+    fn display_name(&self) -> String {
+        match &self.rename {
+            Some(_) => format!("{} => {}", self.display_old_path(), self.display_path()),
+            None => self.display_path(),
+        }
+    }
+
+    /// 两侧的内容，不存在的一侧为空
+    pub fn contents(&self) -> (&[u8], &[u8]) {
+        fn content(version: &Option<Version>) -> &[u8] {
+            version.as_ref().map(|v| v.content.as_slice()).unwrap_or_default()
+        }
+        (content(&self.old), content(&self.new))
+    }
+
+    fn is_binary(&self) -> bool {
+        let (old, new) = self.contents();
+        diff::is_binary(old) || diff::is_binary(new)
+    }
+
+    /// 修改类型：A(新增) | D(删除) | M(修改) | R(重命名) | C(复制)
+    pub fn status(&self) -> char {
+        match (&self.old, &self.new, &self.rename) {
+            (_, _, Some(rename)) if rename.copy => 'C',
+            (_, _, Some(_)) => 'R',
+            (None, _, _) => 'A',
+            (_, None, _) => 'D',
+            _ => 'M',
+        }
+    }
+
+    /// 新增 & 删除的行数，二进制文件返回None
+    pub fn line_counts(&self, algorithm: DiffAlgorithm) -> Option<(usize, usize)> {
+        if self.is_binary() {
+            return None;
+        }
+        let (old, new) = self.contents();
+        let (old_lines, new_lines) = (diff::split_lines(old), diff::split_lines(new));
+        let matched = algorithm.matches(&old_lines, &new_lines).len();
+        Some((new_lines.len() - matched, old_lines.len() - matched))
+    }
+
+    /// 删除 & 新增的行（保留换行符），二进制文件没有行
+    pub fn changed_lines(&self, algorithm: DiffAlgorithm) -> (Vec<&[u8]>, Vec<&[u8]>) {
+        if self.is_binary() {
+            return (Vec::new(), Vec::new());
+        }
+        let (old, new) = self.contents();
+        let (old_lines, new_lines) = (diff::split_lines(old), diff::split_lines(new));
+        let matches = algorithm.matches(&old_lines, &new_lines);
+        let (mut deleted, mut added) = (Vec::new(), Vec::new());
+        for edit in diff::edit_script(&matches, old_lines.len(), new_lines.len()) {
+            match edit {
+                diff::Edit::Delete(i) => deleted.push(old_lines[i]),
+                diff::Edit::Insert(j) => added.push(new_lines[j]),
+                diff::Edit::Equal(..) => {}
+            }
+        }
+        (deleted, added)
+    }
+}
+
+/// commit中的所有文件：相对路径(to workdir) -> blob hash
+pub fn commit_files(commit: &Hash) -> HashMap<PathBuf, Hash> {
+    Commit::load(commit).get_tree().get_recursive_blobs().into_iter().collect()
+}
+
+/// commit中的一个文件，不存在或为目录时返回None
+pub fn commit_file(commit: &Hash, path: &Path) -> Option<Version> {
+    let entry = Commit::load(commit).get_tree().find(path)?;
+    (entry.filemode.0 == "blob").then(|| Version::from_blob(&entry.object_hash))
+}
+
+/// commit中的文件在parent中的版本：路径相同的文件，不存在时检测重命名，都没有时（新增的文件）返回None
+pub fn parent_file(parent: &Hash, commit: &Hash, path: &Path) -> Option<(PathBuf, Version)> {
+    if let Some(version) = commit_file(parent, path) {
+        return Some((path.to_path_buf(), version));
+    }
+    let files = detect_renames(diff_commits(parent, commit), Some(similarity::DEFAULT_THRESHOLD), None);
+    match files.into_iter().find(|file| file.path == path) {
+        Some(FileDiff { rename: Some(rename), old: Some(old), .. }) => Some((rename.from, old)),
+        _ => None,
+    }
+}
+
+/// 暂存区中的所有文件(stage 0)：相对路径(to workdir) -> blob hash
+pub fn index_files() -> HashMap<PathBuf, Hash> {
+    let index = Index::get_instance();
+    index
+        .get_tracked_entries()
+        .into_iter()
+        .map(|(path, data)| (path.to_relative_workdir(), data.hash))
+        .collect()
+}
+
+/// 比较两组文件，new_version用于读取新版本的内容（可能来自工作区）
+fn diff_files<F>(old: &HashMap<PathBuf, Hash>, new: &HashMap<PathBuf, Hash>, new_version: F) -> Vec<FileDiff>
+where
+    F: Fn(&PathBuf, &Hash) -> Version,
+{
+    let mut paths = old.keys().chain(new.keys()).cloned().collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .filter(|path| old.get(path) != new.get(path))
+        .map(|path| FileDiff {
+            old: old.get(&path).map(Version::from_blob),
+            new: new.get(&path).map(|hash| new_version(&path, hash)),
+            path,
+            rename: None,
+        })
+        .collect()
+}
+
+/// 工作区与暂存区的差异（不包括未跟踪和未解决冲突的文件）
+pub fn diff_worktree() -> Vec<FileDiff> {
+    let index = Index::get_instance();
+    let changes = status::changes_to_be_staged().exclude(&merge::unmerged_paths());
+    let mut paths = changes
+        .modified
+        .iter()
+        .chain(changes.deleted.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let old = index
+                .get_hash(&path.to_absolute_workdir())
+                .map(|hash| Version::from_blob(&hash));
+            let new = path.to_absolute_workdir().exists().then(|| Version::from_worktree(&path));
+            FileDiff { path, old, new, rename: None }
+        })
+        .collect()
+}
+
+/// 暂存区与HEAD的差异
+pub fn diff_cached() -> Vec<FileDiff> {
+    let changes = status::changes_to_be_committed();
+    let head_commit = head::current_head_commit();
+    let old = if head_commit.is_empty() {
+        HashMap::new()
+    } else {
+        commit_files(&head_commit)
+    };
+    let new = index_files();
+    let mut paths = [changes.new, changes.modified, changes.deleted].concat();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| FileDiff {
+            old: old.get(&path).map(Version::from_blob),
+            new: new.get(&path).map(Version::from_blob),
+            path,
+            rename: None,
+        })
+        .collect()
+}
+
+/// commit与暂存区(cached)或工作区中已跟踪的文件的差异
+pub fn diff_commit(commit: &Hash, cached: bool) -> Vec<FileDiff> {
+    let old = commit_files(commit);
+    let mut new = index_files();
+    if cached {
+        return diff_files(&old, &new, |_, hash| Version::from_blob(hash));
+    }
+    new.retain(|path, _| path.to_absolute_workdir().exists());
+    for (path, hash) in new.iter_mut() {
+        *hash = Version::from_worktree(path).hash;
+    }
+    diff_files(&old, &new, |path, _| Version::from_worktree(path))
+}
+
+/// 两个commit之间的差异
+pub fn diff_commits(old: &Hash, new: &Hash) -> Vec<FileDiff> {
+    diff_files(&commit_files(old), &commit_files(new), |_, hash| Version::from_blob(hash))
+}
+
+/// root commit引入的所有文件
+pub fn diff_root(commit: &Hash) -> Vec<FileDiff> {
+    diff_files(&HashMap::new(), &commit_files(commit), |_, hash| Version::from_blob(hash))
+}
+
+/** 重命名 & 复制检测：将相似的(删除, 新增)合并为重命名；剩余的新增文件再从修改 & 删除的文件中寻找复制来源
+<br>结果按路径排序
+ */
+pub fn detect_renames(files: Vec<FileDiff>, find_renames: Option<usize>, find_copies: Option<usize>) -> Vec<FileDiff> {
+    let Some(rename_threshold) = find_renames.or(find_copies) else {
+        return files;
+    };
+    let content = |file: &FileDiff, old: bool| {
+        let version = if old { &file.old } else { &file.new };
+        version.as_ref().map(|v| v.content.clone()).unwrap_or_default()
+    };
+    let deleted = (0..files.len()).filter(|&i| files[i].new.is_none()).collect::<Vec<_>>();
+    let mut added = (0..files.len()).filter(|&i| files[i].old.is_none()).collect::<Vec<_>>();
+    let sources = |indexes: &[usize]| indexes.iter().map(|&i| content(&files[i], true)).collect::<Vec<_>>();
+    let targets = |indexes: &[usize]| indexes.iter().map(|&i| content(&files[i], false)).collect::<Vec<_>>();
+
+    // (target下标, source下标, 相似度, 是否为复制)
+    let mut pairs = Vec::new();
+    for (i, j, score) in similarity::find_matches(&sources(&deleted), &targets(&added), rename_threshold, true) {
+        pairs.push((added[j], deleted[i], score, false));
+    }
+    if let Some(copy_threshold) = find_copies {
+        added.retain(|j| !pairs.iter().any(|pair| pair.0 == *j));
+        let copy_sources = (0..files.len()).filter(|&i| files[i].old.is_some()).collect::<Vec<_>>();
+        for (i, j, score) in similarity::find_matches(&sources(&copy_sources), &targets(&added), copy_threshold, false)
+        {
+            pairs.push((added[j], copy_sources[i], score, true));
+        }
+    }
+
+    let mut files = files;
+    for &(target, source, similarity, copy) in &pairs {
+        files[target].old = files[source].old.clone();
+        files[target].rename = Some(Rename { from: files[source].path.clone(), similarity, copy });
+    }
+    // 被重命名的文件不再显示为删除
+    let renamed = pairs.iter().filter(|pair| !pair.3).map(|pair| pair.1).collect::<Vec<_>>();
+    let mut result = files
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !renamed.contains(i))
+        .map(|(_, file)| file)
+        .collect::<Vec<_>>();
+    result.sort_by(|a, b| a.path.cmp(&b.path));
+    result
+}
+
+/// hunk头部的行范围：`start,len`，len为1时省略；没有行时start为前一行
+pub fn hunk_range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/** hunk标题中的函数名，与Git默认的规则相同：旧文件中hunk之前最近的以字母、`_`或`$`开头的行
+<br>最多80字节，去掉结尾的空白
+ */
+fn hunk_funcname(old_lines: &[&[u8]], start: usize) -> Option<String> {
+    const MAX_LEN: usize = 80;
+    let line = old_lines[..start]
+        .iter()
+        .rev()
+        .find(|line| line.first().is_some_and(|&c| c.is_ascii_alphabetic() || c == b'_' || c == b'$'))?;
+    let line = &line[..line.len().min(MAX_LEN)];
+    Some(String::from_utf8_lossy(line).trim_end().to_string())
+}
+
+/** 生成一个文件的unified diff（不带颜色），与`git diff`的格式相同
+ */
+pub fn format_patch(file: &FileDiff, options: &DiffOptions) -> Vec<String> {
+    let (old_path, path) = (file.display_old_path(), file.display_path());
+    let mut lines = vec![format!("diff --git a/{} b/{}", old_path, path)];
+    let abbrev = |version: &Option<Version>| match version {
+        Some(version) => version.hash[..7].to_string(),
+        None => NULL_HASH.to_string(),
+    };
+    match (&file.old, &file.new, &file.rename) {
+        (_, _, Some(rename)) => {
+            let kind = if rename.copy { "copy" } else { "rename" };
+            lines.push(format!("similarity index {}%", rename.similarity));
+            lines.push(format!("{} from {}", kind, old_path));
+            lines.push(format!("{} to {}", kind, path));
+        }
+        (None, _, _) => lines.push(format!("new file mode {}", FILE_MODE)),
+        (_, None, _) => lines.push(format!("deleted file mode {}", FILE_MODE)),
+        _ => {}
+    }
+    let unchanged = file.old.as_ref().map(|v| &v.hash) == file.new.as_ref().map(|v| &v.hash);
+    if unchanged {
+        return lines; // 内容相同的重命名 & 复制
+    }
+    let mode = if file.old.is_some() && file.new.is_some() {
+        format!(" {}", FILE_MODE)
+    } else {
+        String::new()
+    };
+    lines.push(format!("index {}..{}{}", abbrev(&file.old), abbrev(&file.new), mode));
+
+    let old_name = file.old.as_ref().map_or("/dev/null".to_string(), |_| format!("a/{}", old_path));
+    let new_name = file.new.as_ref().map_or("/dev/null".to_string(), |_| format!("b/{}", path));
+    let (old_content, new_content) = file.contents();
+    if file.is_binary() {
+        lines.push(format!("Binary files {} and {} differ", old_name, new_name));
+        return lines;
+    }
+    let (old_lines, new_lines) = (diff::split_lines(old_content), diff::split_lines(new_content));
+    let script =
+        diff::edit_script(&options.algorithm.matches(&old_lines, &new_lines), old_lines.len(), new_lines.len());
+    let hunks = diff::hunks(&script, CONTEXT_LINES);
+    if hunks.is_empty() {
+        return lines;
+    }
+    lines.push(format!("--- {}", old_name));
+    lines.push(format!("+++ {}", new_name));
+
+    for hunk in hunks {
+        let funcname = hunk_funcname(&old_lines, hunk.old_start).map_or(String::new(), |name| format!(" {}", name));
+        lines.push(format!(
+            "@@ -{} +{} @@{}",
+            hunk_range(hunk.old_start, hunk.old_len),
+            hunk_range(hunk.new_start, hunk.new_len),
+            funcname
+        ));
+        if let Some(mode) = options.word_diff {
+            lines.extend(format_word_hunk(&hunk, &old_lines, &new_lines, mode, options));
+            continue;
+        }
+        lines.extend(format_edits(&hunk.edits, &old_lines, &new_lines));
+    }
+    lines
+}
+
+/// hunk的正文：保留的行以空格开头，删除的行以`-`开头，新增的行以`+`开头
+pub fn format_edits(edits: &[diff::Edit], old_lines: &[&[u8]], new_lines: &[&[u8]]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for edit in edits {
+        let (prefix, line) = match *edit {
+            diff::Edit::Equal(i, _) => (' ', old_lines[i]),
+            diff::Edit::Delete(i) => ('-', old_lines[i]),
+            diff::Edit::Insert(j) => ('+', new_lines[j]),
+        };
+        let text = String::from_utf8_lossy(line);
+        lines.push(format!("{}{}", prefix, text.strip_suffix('\n').unwrap_or(&text)));
+        if !line.ends_with(b"\n") {
+            lines.push("\\ No newline at end of file".to_string());
+        }
+    }
+    lines
+}
+
+/// 单词级diff：将hunk中old、new两侧的行分别拼接后按单词比较
+fn format_word_hunk(
+    hunk: &diff::Hunk,
+    old_lines: &[&[u8]],
+    new_lines: &[&[u8]],
+    mode: WordDiffMode,
+    options: &DiffOptions,
+) -> Vec<String> {
+    let join = |lines: &[&[u8]]| String::from_utf8_lossy(&lines.concat()).to_string();
+    let old = join(&old_lines[hunk.old_start..hunk.old_start + hunk.old_len]);
+    let new = join(&new_lines[hunk.new_start..hunk.new_start + hunk.new_len]);
+    let default_regex = Regex::new(word_diff::DEFAULT_WORD_REGEX).unwrap();
+    let regex = options.word_regex.as_ref().unwrap_or(&default_regex);
+    word_diff::format(&word_diff::word_diff(&old, &new, regex, options.algorithm), mode)
+}
+
+/// 为patch加上颜色：文件头加粗，hunk头青色，删除红色，新增绿色；单词级diff的正文已经着色
+pub fn color_patch(lines: &[String], word_diff: bool) -> Vec<String> {
+    let mut in_header = true;
+    let mut colored = Vec::new();
+    for line in lines {
+        let line = if let Some(range) = line.strip_prefix("@@") {
+            in_header = false;
+            // 函数名不着色
+            match range.find("@@") {
+                Some(end) => {
+                    let (header, funcname) = line.split_at(end + 4);
+                    format!("{}{}", header.cyan(), funcname)
+                }
+                None => line.cyan().to_string(),
+            }
+        } else if in_header {
+            line.bold().to_string()
+        } else if word_diff {
+            line.clone()
+        } else if line.starts_with('+') {
+            line.green().to_string()
+        } else if line.starts_with('-') {
+            line.red().to_string()
+        } else {
+            line.clone()
+        };
+        colored.push(line);
+    }
+    colored
+}
+
+/// `--numstat`：`新增行数\t删除行数\t路径`，二进制文件的行数为`-`
+pub fn format_numstat(files: &[FileDiff], algorithm: DiffAlgorithm) -> Vec<String> {
+    files
+        .iter()
+        .map(|file| match file.line_counts(algorithm) {
+            Some((added, deleted)) => format!("{}\t{}\t{}", added, deleted, file.display_name()),
+            None => format!("-\t-\t{}", file.display_name()),
+        })
+        .collect()
+}
+
+/// `--name-status`：`修改类型\t路径`
+pub fn format_name_status(files: &[FileDiff]) -> Vec<String> {
+    files
+        .iter()
+        .map(|file| match &file.rename {
+            // 重命名 & 复制：R100\told\tnew
+            Some(rename) => format!(
+                "{}{:03}\t{}\t{}",
+                file.status(),
+                rename.similarity,
+                file.display_old_path(),
+                file.display_path()
+            ),
+            None => format!("{}\t{}", file.status(), file.display_path()),
+        })
+        .collect()
+}
+
+/// 将数量按比例缩放到宽度内，非0的数量至少为1（与Git相同）
+fn scale_linear(count: usize, width: usize, max_change: usize) -> usize {
+    if count == 0 {
+        0
+    } else {
+        1 + count * (width - 1) / max_change
+    }
+}
+
+/** `--stat`：每个文件一行` 路径 | 行数 +++--`，最后一行为汇总
+<br>修改最多的文件超过[STAT_GRAPH_WIDTH]时，按比例缩短柱状图
+ */
+pub fn format_stat(files: &[FileDiff], algorithm: DiffAlgorithm) -> Vec<String> {
+    if files.is_empty() {
+        return Vec::new();
+    }
+    let counts = files.iter().map(|file| file.line_counts(algorithm)).collect::<Vec<_>>();
+    let name_width = files.iter().map(|file| file.display_name().chars().count()).max().unwrap_or(0);
+    let max_change = counts.iter().flatten().map(|(a, d)| a + d).max().unwrap_or(0);
+    let count_width = max_change.to_string().len();
+    let (mut insertions, mut deletions) = (0, 0);
+
+    let mut lines = Vec::new();
+    for (file, count) in files.iter().zip(counts) {
+        let name = format!("{:<width$}", file.display_name(), width = name_width);
+        let Some((mut added, mut deleted)) = count else {
+            let (old, new) = file.contents();
+            lines.push(format!(" {} | Bin {} -> {} bytes", name, old.len(), new.len()));
+            continue;
+        };
+        (insertions, deletions) = (insertions + added, deletions + deleted);
+        let total = added + deleted;
+        if max_change > STAT_GRAPH_WIDTH {
+            let mut scaled = scale_linear(total, STAT_GRAPH_WIDTH, max_change);
+            if scaled < 2 && added > 0 && deleted > 0 {
+                scaled = 2;
+            }
+            if added < deleted {
+                added = scale_linear(added, STAT_GRAPH_WIDTH, max_change);
+                deleted = scaled - added;
+            } else {
+                deleted = scale_linear(deleted, STAT_GRAPH_WIDTH, max_change);
+                added = scaled - deleted;
+            }
+        }
+        let bar = format!("{}{}", "+".repeat(added), "-".repeat(deleted));
+        let line = format!(" {} | {:>width$} {}", name, total, bar, width = count_width);
+        lines.push(line.trim_end().to_string());
+    }
+
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    let mut summary = format!(" {} changed", plural(files.len(), "file"));
+    if insertions > 0 || deletions == 0 {
+        summary += &format!(", {}(+)", plural(insertions, "insertion"));
+    }
+    if deletions > 0 || insertions == 0 {
+        summary += &format!(", {}(-)", plural(deletions, "deletion"));
+    }
+    lines.push(summary);
+    lines
+}
+
+/// --stat的柱状图着色：新增绿色，删除红色
+fn color_stat(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .map(|line| match line.rsplit_once(' ') {
+            Some((prefix, bar))
+                if line.contains(" | ") && !bar.is_empty() && bar.chars().all(|c| c == '+' || c == '-') =>
+            {
+                let added = bar.chars().filter(|&c| c == '+').count();
+                format!("{} {}{}", prefix, bar[..added].green(), bar[added..].red())
+            }
+            _ => line,
+        })
+        .collect()
+}
+
+/// 摘要（--stat、--numstat、--name-status）的所有输出行，按选项依次拼接
+pub fn summary_lines(files: &[FileDiff], options: &DiffOptions) -> Vec<String> {
+    let mut lines = Vec::new();
+    if options.stat {
+        lines.extend(color_stat(format_stat(files, options.algorithm)));
+    }
+    if options.numstat {
+        lines.extend(format_numstat(files, options.algorithm));
+    }
+    if options.name_status {
+        lines.extend(format_name_status(files));
+    }
+    lines
+}
+
+/// 按选项输出差异：摘要（--stat、--numstat、--name-status）或patch
+pub fn print_diff(files: &[FileDiff], options: &DiffOptions) {
+    if !options.summary_only() {
+        for file in files {
+            let lines = color_patch(&format_patch(file, options), options.word_diff.is_some());
+            lines.iter().for_each(|line| println!("{}", line));
+        }
+        return;
+    }
+    summary_lines(files, options).iter().for_each(|line| println!("{}", line));
+}
+
+/** 显示差异：
+ * <br>无参数：工作区 vs 暂存区；`--cached`：暂存区 vs HEAD
+ * <br>`<commit>`：commit vs 工作区（`--cached`时为暂存区）；`<commit> <commit>`：两个commit之间
+ */
+pub fn diff(cached: bool, revs: Vec<String>, options: DiffOptions) {
+    let mut commits = Vec::new();
+    for rev in &revs {
+        match revision::resolve_commit(rev) {
+            Some(commit) => commits.push(commit),
+            None => {
+                println!("{}", revision::unknown_revision(rev));
+                return;
+            }
+        }
+    }
+    let files = match commits.as_slice() {
+        [] if cached => diff_cached(),
+        [] => diff_worktree(),
+        [commit] => diff_commit(commit, cached),
+        [old, new] => diff_commits(old, new),
+        _ => {
+            println!("fatal: 最多只能指定两个commit");
+            return;
+        }
+    };
+    let files = detect_renames(files, options.find_renames, options.find_copies);
+    print_diff(&files, &options);
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+    use crate::{commands as cmd, utils::test};
+
+    fn patches(files: Vec<FileDiff>) -> Vec<String> {
+        files
+            .iter()
+            .flat_map(|file| format_patch(file, &DiffOptions::default()))
+            .collect()
+    }
+
+    /// 内容为content的版本（计算hash需要仓库）
+    fn version(content: &str) -> Version {
+        Version {
+            hash: Blob::dry_new(content.as_bytes().to_vec()).get_hash(),
+            content: content.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_format_patch() {
+        test::setup_with_clean_mit();
+        let file = FileDiff {
+            path: PathBuf::from("dir/a.txt"),
+            old: Some(version("1\n2\n3\n4\n5\n6\n7\n8\n9\n")),
+            new: Some(version("1\n2\nthree\n4\n5\n6\n7\n8\n9")),
+            rename: None,
+        };
+        let lines = format_patch(&file, &DiffOptions::default());
+        assert_eq!(lines[0], "diff --git a/dir/a.txt b/dir/a.txt");
+        assert!(lines[1].starts_with("index ") && lines[1].ends_with(" 100644"));
+        assert_eq!(
+            lines[2..],
+            [
+                "--- a/dir/a.txt",
+                "+++ b/dir/a.txt",
+                "@@ -1,9 +1,9 @@",
+                " 1",
+                " 2",
+                "-3",
+                "+three",
+                " 4",
+                " 5",
+                " 6",
+                " 7",
+                " 8",
+                "-9",
+                "+9",
+                "\\ No newline at end of file",
+            ]
+        );
+
+        // 单词级diff：最后一行只有换行符不同，单词相同
+        let options = DiffOptions { word_diff: Some(WordDiffMode::Plain), ..Default::default() };
+        assert_eq!(
+            format_patch(&file, &options)[4..],
+            ["@@ -1,9 +1,9 @@", "1", "2", "[-3-]{+three+}", "4", "5", "6", "7", "8", "9"]
+        );
+        let options = DiffOptions { word_regex: Regex::new(".").ok(), ..options };
+        assert_eq!(format_patch(&file, &options)[7], "[-3-]{+three+}");
+
+        let file = FileDiff {
+            path: PathBuf::from("new.txt"),
+            old: None,
+            new: Some(version("a\n")),
+            rename: None,
+        };
+        let lines = format_patch(&file, &DiffOptions::default());
+        assert_eq!(lines[1], "new file mode 100644");
+        assert!(lines[2].starts_with("index 0000000.."));
+        assert_eq!(lines[3..], ["--- /dev/null", "+++ b/new.txt", "@@ -0,0 +1 @@", "+a"]);
+
+        // hunk标题中的函数名
+        let old = "fn first() {   \n    1\n    2\n}\n\n$var\n    a\n    b\n    c\n    d\n    e\n    f\n";
+        let file = FileDiff {
+            path: PathBuf::from("a.rs"),
+            old: Some(version(old)),
+            new: Some(version(&old.replace("2", "two").replace("    f", "    F"))),
+            rename: None,
+        };
+        let headers = format_patch(&file, &DiffOptions::default());
+        let headers = headers.iter().filter(|line| line.starts_with("@@")).collect::<Vec<_>>();
+        assert_eq!(headers, ["@@ -1,6 +1,6 @@", "@@ -9,4 +9,4 @@ $var"]);
+        let long = format!("_{}\n", "x".repeat(100));
+        let file = FileDiff {
+            path: PathBuf::from("long.txt"),
+            old: Some(version(&format!("{}1\n2\n3\n4\n", long))),
+            new: Some(version(&format!("{}1\n2\n3\nfour\n", long))),
+            rename: None,
+        };
+        assert_eq!(format_patch(&file, &DiffOptions::default())[4], format!("@@ -2,4 +2,4 @@ _{}", "x".repeat(79)));
+
+        let file = FileDiff {
+            path: PathBuf::from("bin"),
+            old: Some(version("a\0")),
+            new: None,
+            rename: None,
+        };
+        assert_eq!(
+            format_patch(&file, &DiffOptions::default()).last().unwrap(),
+            "Binary files a/bin and /dev/null differ"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        test::setup_with_clean_mit();
+        let many = "x\n".repeat(100);
+        let files = vec![
+            FileDiff {
+                path: PathBuf::from("a.txt"),
+                old: Some(version("1\n2\n3\n")),
+                new: Some(version("1\ntwo\n3\n4\n")),
+                rename: None,
+            },
+            FileDiff {
+                path: PathBuf::from("bin"),
+                old: None,
+                new: Some(version("\0\0\0")),
+                rename: None,
+            },
+            FileDiff {
+                path: PathBuf::from("dir/long.txt"),
+                old: Some(version(&many)),
+                new: None,
+                rename: None,
+            },
+        ];
+        assert_eq!(format_numstat(&files, DiffAlgorithm::Myers), ["2\t1\ta.txt", "-\t-\tbin", "0\t100\tdir/long.txt"]);
+        assert_eq!(format_name_status(&files), ["M\ta.txt", "A\tbin", "D\tdir/long.txt"]);
+        assert_eq!(
+            format_stat(&files, DiffAlgorithm::Myers),
+            [
+                " a.txt        |   3 +-", // 按比例缩放
+                " bin          | Bin 0 -> 3 bytes",
+                &format!(" dir/long.txt | 100 {}", "-".repeat(40)),
+                " 3 files changed, 2 insertions(+), 101 deletions(-)",
+            ]
+        );
+        assert_eq!(
+            format_stat(&files[..1], DiffAlgorithm::Myers)[1],
+            " 1 file changed, 2 insertions(+), 1 deletion(-)"
+        );
+    }
+
+    #[test]
+    fn test_detect_renames() {
+        test::setup_with_clean_mit();
+        let file = |path: &str, old: Option<&str>, new: Option<&str>| FileDiff {
+            path: PathBuf::from(path),
+            old: old.map(version),
+            new: new.map(version),
+            rename: None,
+        };
+        let files = vec![
+            file("a.txt", Some("1\n2\n3\n4\n"), None),
+            file("b.txt", Some("x\ny\n"), Some("x\ny\nz\n")),
+            file("c.txt", None, Some("1\n2\n3\nfour\n")),
+            file("d.txt", None, Some("x\ny\nz\n")),
+        ];
+        assert_eq!(detect_renames(files.clone(), None, None).len(), 4);
+
+        let renamed = detect_renames(files.clone(), Some(50), None);
+        assert_eq!(format_name_status(&renamed), ["M\tb.txt", "R054\ta.txt\tc.txt", "A\td.txt"]);
+        assert_eq!(format_numstat(&renamed, DiffAlgorithm::Myers)[1], "1\t1\ta.txt => c.txt");
+        let lines = format_patch(&renamed[1], &DiffOptions::default());
+        assert_eq!(
+            lines[..6],
+            [
+                "diff --git a/a.txt b/c.txt",
+                "similarity index 54%",
+                "rename from a.txt",
+                "rename to c.txt",
+                lines[4].as_str(), // index行
+                "--- a/a.txt",
+            ]
+        );
+        assert!(detect_renames(files.clone(), Some(80), None).iter().all(|f| f.rename.is_none()));
+
+        // 复制：来源为修改的文件，-C隐含-M
+        let copied = detect_renames(files, None, Some(50));
+        assert_eq!(format_name_status(&copied), ["M\tb.txt", "R054\ta.txt\tc.txt", "C066\tb.txt\td.txt"]);
+
+        // 内容相同的重命名没有hunk
+        let same = detect_renames(vec![file("a", Some("1\n"), None), file("b", None, Some("1\n"))], Some(50), None);
+        assert_eq!(format_patch(&same[0], &DiffOptions::default()).len(), 4);
+    }
+
+    #[test]
+    fn test_diff() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("a\nb\nc\n"));
+        test::ensure_file(Path::new("b.txt"), Some("b\n"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        let first = head::current_head_commit();
+
+        // 工作区修改
+        test::ensure_file(Path::new("a.txt"), Some("a\nB\nc\n"));
+        fs::remove_file("b.txt").unwrap();
+        test::ensure_file(Path::new("untracked.txt"), Some("u\n"));
+        let lines = patches(diff_worktree());
+        assert!(lines.contains(&"-b".to_string()) && lines.contains(&"+B".to_string()));
+        assert!(lines.contains(&"deleted file mode 100644".to_string()));
+        assert!(!lines.iter().any(|line| line.contains("untracked.txt")));
+        assert!(diff_cached().is_empty());
+
+        // 暂存后，差异在暂存区与HEAD之间
+        cmd::add(vec!["a.txt".to_string(), "b.txt".to_string()], false, true);
+        assert!(diff_worktree().is_empty());
+        let files = diff_cached();
+        assert_eq!(files.iter().map(|f| f.path.clone()).collect::<Vec<_>>(), ["a.txt", "b.txt"].map(PathBuf::from));
+
+        cmd::commit("second".to_string(), false);
+        let second = head::current_head_commit();
+        let files = diff_commits(&first, &second);
+        assert_eq!(files.len(), 2);
+        assert!(files[1].new.is_none());
+        assert!(diff_commits(&second, &second).is_empty());
+
+        // commit vs 工作区中已跟踪的文件
+        test::ensure_file(Path::new("a.txt"), Some("a\n"));
+        let files = diff_commit(&second, false);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].new.as_ref().unwrap().content, b"a\n");
+        assert!(diff_commit(&second, true).is_empty());
+    }
+}
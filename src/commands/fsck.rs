@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+
+use colored::Colorize;
+
+use crate::{
+    models::{head, Commit, Hash, Index, ObjectType, Tree},
+    utils::{reachable, store::Store, util, PathExt},
+};
+
+/// fsck的检查结果
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    pub errors: Vec<String>,
+    pub notices: Vec<String>,              // 不影响检查结果的提示，如HEAD指向还没有commit的分支
+    pub dangling: Vec<(ObjectType, Hash)>, // 不可达，且没有被其他object引用
+    pub unreachable: Vec<(ObjectType, Hash)>,
+}
+
+impl FsckReport {
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// 检查from引用的to是否存在，且类型正确
+fn check_link(store: &Store, report: &mut FsckReport, from: (ObjectType, &Hash), to: (ObjectType, &Hash)) {
+    match store.object_type(to.1) {
+        ObjectType::Invalid => {
+            report
+                .errors
+                .push(format!("broken link from {:>6} {}\n              to {:>6} {}", from.0, from.1, to.0, to.1));
+            report.errors.push(format!("missing {} {}", to.0, to.1));
+        }
+        actual if actual != to.0 => {
+            report
+                .errors
+                .push(format!("error: {} {}: {} is a {}, not a {}", from.0, from.1, to.1, actual, to.0));
+        }
+        _ => {}
+    }
+}
+
+/// 重新计算每个object的hash，并检查commit & tree中的引用，返回被引用的object
+fn check_objects(store: &Store, report: &mut FsckReport) -> HashSet<Hash> {
+    let mut referenced = HashSet::new();
+    for hash in store.list_objects() {
+        if !is_valid_hash(&hash) {
+            report.errors.push(format!("error: invalid object name in objects/: {}", hash));
+            continue;
+        }
+        if !store.verify(&hash) {
+            report.errors.push(format!("error: {}: object corrupt or hash mismatch", hash));
+            continue;
+        }
+        match store.object_type(&hash) {
+            ObjectType::Commit => match Commit::try_load(&hash) {
+                Some(commit) => {
+                    let tree = commit.get_tree_hash();
+                    check_link(store, report, (ObjectType::Commit, &hash), (ObjectType::Tree, &tree));
+                    referenced.insert(tree);
+                    for parent in commit.get_parent_hash() {
+                        check_link(store, report, (ObjectType::Commit, &hash), (ObjectType::Commit, &parent));
+                        referenced.insert(parent);
+                    }
+                }
+                None => report.errors.push(format!("error: {}: invalid commit", hash)),
+            },
+            ObjectType::Tree => match Tree::try_load(&hash) {
+                Some(tree) => {
+                    for entry in tree.entries {
+                        let entry_type = ObjectType::parse(&entry.filemode.0);
+                        check_link(store, report, (ObjectType::Tree, &hash), (entry_type, &entry.object_hash));
+                        referenced.insert(entry.object_hash);
+                    }
+                }
+                None => report.errors.push(format!("error: {}: invalid tree", hash)),
+            },
+            _ => {}
+        }
+    }
+    referenced
+}
+
+/// 检查HEAD & refs/heads/* 是否指向存在的commit
+fn check_refs(store: &Store, report: &mut FsckReport) {
+    for branch in head::list_local_branches() {
+        let hash = head::get_branch_head(&branch).trim().to_string();
+        if store.object_type(&hash) != ObjectType::Commit {
+            report
+                .errors
+                .push(format!("error: refs/heads/{}: invalid sha1 pointer {}", branch, hash));
+        }
+    }
+    match head::current_head() {
+        head::Head::Branch(branch) => {
+            if !head::list_local_branches().contains(&branch) {
+                report
+                    .notices
+                    .push(format!("notice: HEAD points to an unborn branch ({})", branch));
+            }
+        }
+        head::Head::Detached(hash) => {
+            if store.object_type(&hash) != ObjectType::Commit {
+                report.errors.push(format!("error: HEAD: invalid sha1 pointer {}", hash));
+            }
+        }
+    }
+}
+
+/// 检查index中的每个文件（包括冲突文件的各个版本）是否指向存在的blob
+fn check_index(store: &Store, report: &mut FsckReport) {
+    let index = Index::get_instance();
+    let mut entries = index.get_tracked_entries().into_iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut conflicts = index.get_conflicts().into_iter().collect::<Vec<_>>();
+    conflicts.sort_by(|a, b| a.0.cmp(&b.0));
+    for (path, conflict) in conflicts {
+        for stage in 1..=3 {
+            match conflict.stage(stage) {
+                Some(hash) if store.object_type(&hash) != ObjectType::Blob => {
+                    let path = path.to_relative_workdir();
+                    report.errors.push(format!(
+                        "error: index entry '{}' (stage {}) points to missing blob {}",
+                        path.display(),
+                        stage,
+                        hash
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+    for (path, meta) in entries {
+        if store.object_type(&meta.hash) != ObjectType::Blob {
+            let path = path.to_relative_workdir();
+            report
+                .errors
+                .push(format!("error: index entry '{}' points to missing blob {}", path.display(), meta.hash));
+        }
+    }
+}
+
+/// 检查仓库的完整性
+pub fn check() -> FsckReport {
+    let mut report = FsckReport::default();
+    let store = Store::new();
+    if !util::get_storage_path().unwrap().join("HEAD").is_file() {
+        report.errors.push("error: HEAD is missing".to_string());
+        return report;
+    }
+
+    let referenced = check_objects(&store, &mut report);
+    check_refs(&store, &mut report);
+    check_index(&store, &mut report);
+
+    let reachable = reachable::reachable_objects();
+    for hash in store.list_objects() {
+        if !reachable.contains(&hash) {
+            let obj_type = store.object_type(&hash);
+            if !referenced.contains(&hash) {
+                report.dangling.push((obj_type, hash.clone()));
+            }
+            report.unreachable.push((obj_type, hash));
+        }
+    }
+    report
+}
+
+/** 检查仓库完整性：object内容与hash是否一致、引用是否存在、ref & index是否有效
+<br>默认列出dangling object，`--unreachable`则列出所有不可达的object
+<br>返回仓库是否完好，用于设置退出码
+ */
+pub fn fsck(unreachable: bool) -> bool {
+    util::check_repo_exist();
+    let report = check();
+    for notice in &report.notices {
+        println!("{}", notice);
+    }
+    for error in &report.errors {
+        println!("{}", error.red());
+    }
+    let (label, objects) = if unreachable {
+        ("unreachable", &report.unreachable)
+    } else {
+        ("dangling", &report.dangling)
+    };
+    for (obj_type, hash) in objects {
+        println!("{} {} {}", label, obj_type, hash);
+    }
+    report.is_ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::{commands as cmd, models::{reflog, Blob}, utils::test};
+
+    fn object_path(hash: &str) -> std::path::PathBuf {
+        util::get_storage_path().unwrap().join("objects").join(hash)
+    }
+
+    #[test]
+    fn test_fsck_clean() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("a"));
+        test::ensure_file(Path::new("dir/b.txt"), Some("b"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+
+        let report = check();
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert!(report.unreachable.is_empty());
+
+        // 打包后同样可以检查
+        cmd::gc(None);
+        let report = check();
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert!(report.unreachable.is_empty());
+    }
+
+    #[test]
+    fn test_fsck_unborn() {
+        test::setup_with_empty_workdir();
+        let report = check();
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!(report.notices, ["notice: HEAD points to an unborn branch (master)"]);
+
+        cmd::commit("first".to_string(), true);
+        assert!(check().notices.is_empty());
+    }
+
+    #[test]
+    fn test_fsck_corrupt_and_missing() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("a"));
+        test::ensure_file(Path::new("b.txt"), Some("b"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        let blob_a = Blob::dry_new("a".into()).get_hash();
+        let blob_b = Blob::dry_new("b".into()).get_hash();
+
+        fs::write(object_path(&blob_a), "garbage").unwrap(); // 损坏
+        fs::remove_file(object_path(&blob_b)).unwrap(); // 丢失
+        let report = check();
+        assert!(!report.is_ok());
+        assert!(report.errors.iter().any(|e| e.contains(&blob_a) && e.contains("corrupt")));
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| e.starts_with("missing blob") && e.contains(&blob_b)));
+        assert!(report.errors.iter().any(|e| e.contains("index entry 'b.txt'")));
+    }
+
+    #[test]
+    fn test_fsck_refs() {
+        test::setup_with_empty_workdir();
+        cmd::commit("first".to_string(), true);
+        head::update_branch(&"broken".to_string(), &"1234567890".to_string(), "branch: Created from HEAD");
+        let report = check();
+        assert!(report.errors.iter().any(|e| e.contains("refs/heads/broken")));
+    }
+
+    #[test]
+    fn test_fsck_dangling() {
+        test::setup_with_empty_workdir();
+        cmd::commit("first".to_string(), true);
+        let first = head::current_head_commit();
+        cmd::branch(Some("tmp".to_string()), None, false, None, false, false);
+        cmd::switch(Some("tmp".to_string()), None, false);
+        cmd::commit("second".to_string(), true);
+        let second = head::current_head_commit();
+        cmd::switch(Some("master".to_string()), None, false);
+        cmd::branch(None, None, false, Some("tmp".to_string()), true, false);
+        assert!(check().dangling.is_empty()); // HEAD的reflog中仍有记录
+        reflog::remove(reflog::HEAD);
+
+        let report = check();
+        assert!(report.is_ok(), "{:?}", report.errors);
+        assert_eq!(report.dangling, vec![(ObjectType::Commit, second.clone())]);
+        assert!(report.unreachable.contains(&(ObjectType::Commit, second)));
+        assert!(!report.unreachable.iter().any(|(_, hash)| *hash == first));
+    }
+}
@@ -0,0 +1,157 @@
+use colored::Colorize;
+
+use std::collections::HashSet;
+
+use crate::{
+    commands::prune,
+    models::Hash,
+    utils::{store::Store, util},
+};
+
+fn repack_excluding(exclude: &HashSet<Hash>, loose: &HashSet<Hash>) {
+    let (objects, deltas) = Store::new().repack(exclude, loose);
+    if objects == 0 {
+        println!("Nothing new to pack.");
+        return;
+    }
+    println!("Packed {} objects ({} deltas)", objects.to_string().green(), deltas.to_string().green());
+}
+
+/// 将所有object打包为一个packfile，相似的object以delta储存，并删除已打包的loose object
+pub fn repack() {
+    util::check_repo_exist();
+    repack_excluding(&HashSet::new(), &HashSet::new());
+}
+
+/** 整理仓库：打包object，减少文件数量 & 磁盘占用
+<br>同时丢弃超过宽限期（默认2周）的不可达object，`--prune=now`立即丢弃，`--prune=never`全部保留（打包）
+<br>宽限期内的不可达object不打包，保留为loose object并保持原来的修改时间，以便过期后被丢弃
+ */
+pub fn gc(prune: Option<String>) {
+    util::check_repo_exist();
+    let expire = match prune::parse_expire(&prune.unwrap_or(prune::DEFAULT_EXPIRE.to_string())) {
+        Ok(expire) => expire,
+        Err(err) => {
+            println!("fatal: {}", err);
+            return;
+        }
+    };
+    let store = Store::new();
+    let expired = prune::expired_objects(&store, expire);
+    if !expired.is_empty() {
+        println!("Pruned {} unreachable objects", expired.len().to_string().red());
+    }
+    let loose = match expire {
+        Some(_) => &prune::unreachable_objects(&store) - &expired,
+        None => HashSet::new(),
+    };
+    repack_excluding(&expired, &loose);
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        fs,
+        path::Path,
+        time::{Duration, SystemTime},
+    };
+
+    use crate::{
+        commands as cmd,
+        models::{head, Blob, Commit},
+        utils::{store::Store, test, util},
+    };
+
+    #[test]
+    fn test_gc() {
+        test::setup_with_empty_workdir();
+        let content = "a line of text\n".repeat(100);
+        test::ensure_file(Path::new("a.txt"), Some(&content));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        test::ensure_file(Path::new("a.txt"), Some(&(content.clone() + "more\n")));
+        cmd::add(vec![], true, false);
+        cmd::commit("second".to_string(), false);
+
+        super::gc(None);
+        let store = Store::new();
+        assert!(store.list_loose().is_empty());
+        assert_eq!(store.list_objects().len(), 6); // 2 commit + 2 tree + 2 blob
+
+        // 打包后对上层透明
+        let commit = Commit::load(&head::current_head_commit());
+        assert_eq!(commit.get_message(), "second");
+        assert!(cmd::status::changes_to_be_staged().is_empty());
+        assert!(cmd::status::changes_to_be_committed().is_empty());
+        cmd::restore(vec![".".to_string()], Some(commit.get_parent_hash()[0].clone()), true, true);
+        assert_eq!(std::fs::read_to_string("a.txt").unwrap(), content);
+    }
+
+    #[test]
+    fn test_gc_prune() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("a"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        test::ensure_file(Path::new("a.txt"), Some("unreachable"));
+        cmd::add(vec![], true, false);
+        test::ensure_file(Path::new("a.txt"), Some("staged"));
+        cmd::add(vec![], true, false);
+
+        super::gc(None); // 宽限期内，保留
+        assert_eq!(Store::new().list_objects().len(), 5); // commit + tree + 3 blob
+
+        super::gc(Some("now".to_string()));
+        let store = Store::new();
+        assert_eq!(store.list_objects().len(), 4);
+        assert!(!store.contains(&Blob::dry_new("unreachable".into()).get_hash()));
+        assert!(store.contains(&Blob::dry_new("staged".into()).get_hash()));
+    }
+
+    #[test]
+    fn test_gc_prune_expired() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("unreachable"));
+        cmd::add(vec![], true, false);
+        test::ensure_file(Path::new("a.txt"), Some("a"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        let unreachable = Blob::dry_new("unreachable".into()).get_hash();
+
+        super::gc(None); // 宽限期内，保留为loose object
+        let store = Store::new();
+        assert_eq!(store.list_loose(), [&unreachable].map(String::clone));
+        assert_eq!(store.list_objects().len(), 4);
+
+        // 超过宽限期后，下一次gc丢弃它
+        let old = SystemTime::now() - Duration::from_secs(15 * 24 * 60 * 60);
+        let path = util::get_storage_path().unwrap().join("objects").join(&unreachable);
+        fs::File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+        super::gc(None);
+        let store = Store::new();
+        assert!(!store.contains(&unreachable));
+        assert!(store.list_loose().is_empty());
+        assert_eq!(store.list_objects().len(), 3);
+    }
+
+    #[test]
+    fn test_gc_unpack_unreachable() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("unreachable"));
+        cmd::add(vec![], true, false);
+        super::gc(Some("never".to_string())); // 全部打包
+        test::ensure_file(Path::new("a.txt"), Some("a"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        let unreachable = Blob::dry_new("unreachable".into()).get_hash();
+        let store = Store::new();
+        assert!(!store.list_loose().contains(&unreachable));
+        let packed = store.mtime(&unreachable).unwrap();
+
+        // 已打包的不可达object写出为loose object，修改时间取原pack的修改时间
+        super::gc(None);
+        let store = Store::new();
+        assert_eq!(store.list_loose(), [&unreachable].map(String::clone));
+        assert_eq!(store.mtime(&unreachable), Some(packed));
+    }
+}
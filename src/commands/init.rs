@@ -1,12 +1,16 @@
-use crate::utils::util::ROOT_DIR;
+use crate::utils::{
+    config::{self, ObjectFormat},
+    util::ROOT_DIR,
+};
 use std::{env, fs, io};
 
 /**
-初始化mit仓库 创建.mit/objects .mit/refs/heads .mit/HEAD
+初始化mit仓库 创建.mit/objects .mit/refs/heads .mit/HEAD .mit/config
 <br>并设置 .mit 为隐藏文件夹
 <br>无法重复初始化
+<br>object_format 决定仓库的object储存格式，记录在.mit/config中
 */
-pub fn init() -> io::Result<()> {
+pub fn init(object_format: ObjectFormat) -> io::Result<()> {
     let dir = env::current_dir()?;
     let mit_dir = dir.join(ROOT_DIR);
     if mit_dir.exists() {
@@ -20,6 +24,7 @@ pub fn init() -> io::Result<()> {
         fs::create_dir_all(dir)?;
     }
     fs::write(mit_dir.join("HEAD"), "ref: refs/heads/master\n")?;
+    config::write_config(&mit_dir, object_format)?;
 
     set_dir_hidden(mit_dir.to_str().unwrap())?; // 设置目录隐藏 (跨平台)
     println!("Initialized empty mit repository in {}", dir.display());
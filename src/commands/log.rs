@@ -1,83 +1,677 @@
-use crate::models::{head, Commit};
-use colored::Colorize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use regex::{bytes, Regex, RegexBuilder};
+
+use crate::{
+    commands::diff::{self, DiffOptions, FileDiff, Version},
+    models::{head, Commit, Hash},
+    utils::{
+        diff::{edit_script, split_lines, DiffAlgorithm, Edit},
+        graph::{Graph, GraphStyle},
+        line_range::{self, FileRange},
+        path_ext::PathExt,
+        pretty::{self, Pretty},
+        rev_list::{self, Order, RevSet},
+        similarity,
+    },
+};
 
 const DEFAULT_LOG_NUMBER: usize = 10;
 
-pub fn log(all: bool, number: Option<usize>) {
-    println!("log all: {:?}, number: {:?}", all, number);
-    let _ = __log(all, number);
+/// log的选项
+#[derive(Debug, Clone, Default)]
+pub struct LogOptions {
+    pub all: bool,                    // 显示所有commit，不限制数量
+    pub number: Option<usize>,        // 最多显示的commit数量，默认为DEFAULT_LOG_NUMBER
+    pub revs: Vec<String>,            // 版本范围，如`A..B`、`^X`，默认为HEAD
+    pub order: Order,                 // `--topo-order`、`--date-order`
+    pub reverse: bool,                // 从旧到新输出（在限制数量之后）
+    pub follow: Option<PathBuf>,      // 只显示修改了该文件的commit，遇到重命名时继续跟踪原文件名
+    pub diff: DiffOptions,            // `--stat`、`--numstat`、`--name-status`，显示每个commit相对于第一个parent的修改
+    pub graph: Option<GraphStyle>,    // 在左侧绘制分支线
+    pub pretty: Pretty,               // 每个commit的输出格式
+    pub paths: Vec<PathBuf>,          // `-- <path>...`：只显示修改了这些路径的commit，并按路径简化历史
+    pub author: Option<String>,       // 作者（`name <email>`）匹配该正则
+    pub committer: Option<String>,    // 提交者匹配该正则
+    pub grep: Vec<String>,            // 提交信息匹配任意一个正则
+    pub ignore_case: bool,            // `--author`、`--committer`、`--grep`、`-S`、`-G`忽略大小写
+    pub since: Option<SystemTime>,    // 只显示该时间之后（包括）的commit
+    pub until: Option<SystemTime>,    // 只显示该时间之前（包括）的commit
+    pub skip: usize,                  // 跳过前n个符合条件的commit
+    pub pickaxe: Option<String>,      // `-S`：只显示该字符串出现次数发生变化的commit
+    pub pickaxe_grep: Option<String>, // `-G`：只显示新增或删除的行匹配该正则的commit
+    pub pickaxe_regex: bool,          // `-S`的参数为正则
+    pub line_ranges: Vec<FileRange>,  // `-L`：只显示修改了这些行的commit，并只输出这些行的修改
+}
+
+pub fn log(mut options: LogOptions) {
+    options.follow = options.follow.map(|path| path.to_absolute().to_relative_workdir());
+    options.paths = options
+        .paths
+        .iter()
+        .map(|path| path.to_absolute().to_relative_workdir())
+        .collect();
+    for range in &mut options.line_ranges {
+        range.path = range.path.to_absolute().to_relative_workdir();
+    }
+    let _ = __log(&options);
+}
+
+/// 按作者、提交者、提交信息、时间筛选commit
+struct CommitFilter {
+    author: Option<Regex>,
+    committer: Option<Regex>,
+    grep: Vec<Regex>,
+    since: Option<SystemTime>,
+    until: Option<SystemTime>,
+}
+
+impl CommitFilter {
+    fn new(options: &LogOptions) -> Result<CommitFilter, String> {
+        let regex = |pattern: &String| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(options.ignore_case)
+                .build()
+                .map_err(|_| format!("fatal: 无效的正则表达式: '{}'", pattern))
+        };
+        Ok(CommitFilter {
+            author: options.author.as_ref().map(regex).transpose()?,
+            committer: options.committer.as_ref().map(regex).transpose()?,
+            grep: options.grep.iter().map(regex).collect::<Result<_, _>>()?,
+            since: options.since,
+            until: options.until,
+        })
+    }
+
+    fn matches(&self, commit: &Commit) -> bool {
+        let person = |regex: &Option<Regex>, name: String| {
+            regex
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(&format!("{} <{}>", name, Commit::email(&name))))
+        };
+        let message = commit.get_message();
+        let time = commit.get_time();
+        person(&self.author, commit.get_author())
+            && person(&self.committer, commit.get_committer())
+            && (self.grep.is_empty() || self.grep.iter().any(|regex| regex.is_match(&message)))
+            && self.since.is_none_or(|since| time >= since)
+            && self.until.is_none_or(|until| time <= until)
+    }
+}
+
+/// `-S`、`-G`：按修改的内容筛选commit，比较commit与第一个parent中每个文件的内容（与Git相同，merge commit不参与）
+enum Pickaxe {
+    Count(bytes::Regex), // `-S`：出现次数发生变化，即新增或删除了该字符串
+    Grep(bytes::Regex),  // `-G`：新增或删除的行匹配
 }
 
-fn __log(all: bool, number: Option<usize>) -> usize {
-    let mut log_count = 0usize;
-
-    let head = head::current_head();
-    let mut branch_name: Option<String> = None;
-    let mut head_commit = match head {
-        head::Head::Branch(_branch_name) => {
-            let commit = head::get_branch_head(&_branch_name);
-            branch_name = Some(_branch_name.clone());
-            if commit.is_empty() {
-                println!("当前分支{:?}没有任何提交", _branch_name);
-                return 0;
+impl Pickaxe {
+    fn new(options: &LogOptions) -> Result<Option<Pickaxe>, String> {
+        let regex = |pattern: &str| {
+            bytes::RegexBuilder::new(pattern)
+                .case_insensitive(options.ignore_case)
+                .build()
+                .map_err(|_| format!("fatal: 无效的正则表达式: '{}'", pattern))
+        };
+        Ok(match (&options.pickaxe, &options.pickaxe_grep) {
+            (Some(string), _) if options.pickaxe_regex => Some(Pickaxe::Count(regex(string)?)),
+            (Some(string), _) => Some(Pickaxe::Count(regex(&regex::escape(string))?)),
+            (None, Some(pattern)) => Some(Pickaxe::Grep(regex(pattern)?)),
+            (None, None) => None,
+        })
+    }
+
+    /// paths非空时只检查其中的文件
+    fn matches(&self, files: &[FileDiff], paths: &[PathBuf], algorithm: DiffAlgorithm) -> bool {
+        let in_paths = |file: &FileDiff| {
+            paths.is_empty()
+                || paths
+                    .iter()
+                    .any(|path| path.as_os_str().is_empty() || file.path.starts_with(path))
+        };
+        files.iter().filter(|file| in_paths(file)).any(|file| match self {
+            Pickaxe::Count(regex) => {
+                let (old, new) = file.contents();
+                regex.find_iter(old).count() != regex.find_iter(new).count()
             }
-            commit
+            Pickaxe::Grep(regex) => {
+                let (deleted, added) = file.changed_lines(algorithm);
+                // 行尾的换行符不参与匹配
+                deleted
+                    .iter()
+                    .chain(&added)
+                    .any(|line| regex.is_match(line.strip_suffix(b"\n").unwrap_or(line)))
+            }
+        })
+    }
+}
+
+/** `-L`：跟踪文件中的行范围，与Git相同，commit需按拓扑顺序处理（子commit在前）
+<br>范围在出发的commit中确定，之后每个commit中的范围映射到parent中对应的行（见[line_range::map_back]），并跟踪文件的重命名；
+范围内的行都是新增的时不再继续跟踪
+ */
+struct LineLog {
+    pending: HashMap<Hash, FileRanges>, // 每个commit中要检查的行范围
+    algorithm: DiffAlgorithm,
+}
+
+/// 每个文件中的行范围，按路径排序
+type FileRanges = BTreeMap<PathBuf, Vec<Range<usize>>>;
+
+/// 一个文件中的行范围在parent中的对应关系
+struct RangeMapping {
+    path: PathBuf,
+    old_path: PathBuf,
+    old: Option<Version>, // 不存在时范围内的行都是新增的
+    new: Version,
+    script: Vec<Edit>,
+    ranges: Vec<(Range<usize>, Range<usize>, Range<usize>)>, // (范围, parent中的范围, script中属于该范围的部分)
+}
+
+impl RangeMapping {
+    fn is_changed(&self) -> bool {
+        self.ranges
+            .iter()
+            .any(|(_, _, edits)| line_range::is_changed(&self.script, edits))
+    }
+
+    /// 只包含被修改的范围的patch，每个范围一个hunk，包括范围内所有的行
+    fn patch(&self) -> Vec<String> {
+        if !self.is_changed() {
+            return Vec::new();
         }
-        head::Head::Detached(commit_hash) => commit_hash,
-    };
+        let display = |path: &Path| path.to_string_lossy().replace('\\', "/");
+        let (old_path, path) = (display(&self.old_path), display(&self.path));
+        let mut lines = vec![format!("diff --git a/{} b/{}", old_path, path)];
+        lines.push(match self.old {
+            Some(_) => format!("--- a/{}", old_path),
+            None => "--- /dev/null".to_string(),
+        });
+        lines.push(format!("+++ b/{}", path));
+        let old_content = self.old.as_ref().map(|old| old.content.as_slice()).unwrap_or_default();
+        let (old_lines, new_lines) = (split_lines(old_content), split_lines(&self.new.content));
+        for (range, old_range, edits) in &self.ranges {
+            if !line_range::is_changed(&self.script, edits) {
+                continue;
+            }
+            lines.push(format!(
+                "@@ -{} +{} @@",
+                diff::hunk_range(old_range.start, old_range.len()),
+                diff::hunk_range(range.start, range.len())
+            ));
+            lines.extend(diff::format_edits(&self.script[edits.clone()], &old_lines, &new_lines));
+        }
+        lines
+    }
+}
+
+impl LineLog {
+    /// 在出发的commit（tips）中确定行范围
+    fn new(options: &LogOptions, tips: &[Hash]) -> Result<Option<LineLog>, String> {
+        if options.line_ranges.is_empty() {
+            return Ok(None);
+        }
+        let mut line_log = LineLog { pending: HashMap::new(), algorithm: options.diff.algorithm };
+        for tip in tips {
+            for FileRange { spec, path } in &options.line_ranges {
+                let Some(version) = diff::commit_file(tip, path) else {
+                    return Err(format!("fatal: {}中没有文件{:?}", &tip[..7], path));
+                };
+                let range = spec.resolve(&split_lines(&version.content))?;
+                line_log.add(tip, path, vec![range]);
+            }
+        }
+        Ok(Some(line_log))
+    }
+
+    fn add(&mut self, commit: &Hash, path: &Path, ranges: Vec<Range<usize>>) {
+        let existing = self
+            .pending
+            .entry(commit.clone())
+            .or_default()
+            .entry(path.to_path_buf())
+            .or_default();
+        existing.extend(ranges);
+        *existing = line_range::merge(std::mem::take(existing));
+    }
 
-    let mut number = match number {
-        Some(number) => number,
+    /** 处理一个commit：范围被修改时返回相对于第一个parent的patch，否则返回None
+    <br>与Git相同，merge中范围与某个parent相同时，只沿该parent继续跟踪，并且不输出
+     */
+    fn next(&mut self, hash: &Hash) -> Option<Vec<String>> {
+        let files = self.pending.remove(hash)?;
+        let parents = Commit::load(hash).get_parent_hash();
+        let mut mappings = Vec::new(); // 每个parent中每个文件的对应关系，root commit相对于空文件
+        for parent in parents.iter().map(Some).chain(parents.is_empty().then_some(None)) {
+            let mapping = files.iter().map(|(path, ranges)| self.map_file(hash, parent, path, ranges));
+            mappings.push(mapping.collect::<Vec<_>>());
+        }
+        let is_changed = |mapping: &[RangeMapping]| mapping.iter().any(RangeMapping::is_changed);
+        let same_parent = mappings.iter().position(|mapping| !is_changed(mapping));
+        if let (true, Some(i)) = (parents.len() > 1, same_parent) {
+            self.follow(&parents[i], &mappings[i]);
+            return None;
+        }
+        for (parent, mapping) in parents.iter().zip(&mappings) {
+            self.follow(parent, mapping);
+        }
+        let first = &mappings[0];
+        is_changed(first).then(|| first.iter().flat_map(RangeMapping::patch).collect())
+    }
+
+    /// 继续在parent中跟踪映射后的范围
+    fn follow(&mut self, parent: &Hash, mapping: &[RangeMapping]) {
+        for file in mapping {
+            let ranges = file
+                .ranges
+                .iter()
+                .map(|(_, old_range, _)| old_range.clone())
+                .collect::<Vec<_>>();
+            let ranges = line_range::merge(ranges);
+            if !ranges.is_empty() {
+                self.add(parent, &file.old_path, ranges);
+            }
+        }
+    }
+
+    /// 文件中的行范围在parent中的对应关系
+    fn map_file(&self, hash: &Hash, parent: Option<&Hash>, path: &Path, ranges: &[Range<usize>]) -> RangeMapping {
+        let new = diff::commit_file(hash, path).expect("跟踪的文件不存在");
+        let (old_path, old) = match parent.and_then(|parent| diff::parent_file(parent, hash, path)) {
+            Some((old_path, old)) => (old_path, Some(old)),
+            None => (path.to_path_buf(), None),
+        };
+        let old_content = old.as_ref().map(|old| old.content.as_slice()).unwrap_or_default();
+        let (old_lines, new_lines) = (split_lines(old_content), split_lines(&new.content));
+        let matches = self.algorithm.matches(&old_lines, &new_lines);
+        let script = edit_script(&matches, old_lines.len(), new_lines.len());
+        let ranges = ranges
+            .iter()
+            .map(|range| {
+                let (old_range, edits) = line_range::map_back(&script, range);
+                (range.clone(), old_range, edits)
+            })
+            .collect();
+        RangeMapping { path: path.to_path_buf(), old_path, old, new, script, ranges }
+    }
+}
+
+/// commit相对于第一个parent的修改（root commit相对于空树）
+fn commit_changes(commit: &Hash, parents: &[Hash]) -> Vec<FileDiff> {
+    match parents.first() {
+        Some(parent) => diff::diff_commits(parent, commit),
+        None => diff::diff_root(commit),
+    }
+}
+
+fn __log(options: &LogOptions) -> usize {
+    if let head::Head::Branch(branch_name) = head::current_head() {
+        if options.revs.is_empty() && head::get_branch_head(&branch_name).is_empty() {
+            println!("当前分支{:?}没有任何提交", branch_name);
+            return 0;
+        }
+    }
+    let revs = if options.revs.is_empty() {
+        vec!["HEAD".to_string()]
+    } else {
+        options.revs.clone()
+    };
+    let parsed = RevSet::parse(&revs).and_then(|set| {
+        let line_log = LineLog::new(options, &set.include)?;
+        Ok((set, CommitFilter::new(options)?, Pickaxe::new(options)?, line_log))
+    });
+    let (mut set, filter, pickaxe, mut line_log) = match parsed {
+        Ok(result) => result,
+        Err(msg) => {
+            println!("{}", msg);
+            return 0;
+        }
+    };
+    set.paths = options.paths.clone();
+    if options.graph.is_some() && options.pretty == Pretty::Json {
+        println!("fatal: --graph与--format=json不能同时使用");
+        return 0;
+    }
+    let number = match options.number {
+        _ if options.all => usize::MAX,
+        Some(number) => number.max(1),
         None => DEFAULT_LOG_NUMBER,
     };
 
-    let mut first = true;
-    loop {
-        log_count += 1;
-        let commit = Commit::load(&head_commit);
-        if first {
-            // TODO: (HEAD -> ttt, ad2)
-            first = false;
-            print!("{}{}{}{}", "commit ".yellow(), commit.get_hash().yellow(), "(".yellow(), "HEAD".blue());
-            if let Some(ref branch_name) = branch_name {
-                print!("{}", format!(" -> {}", branch_name).blue());
+    // 先筛选 & 限制数量，再按需反转
+    let mut follow = options.follow.clone();
+    let mut commits = Vec::new();
+    let mut skipped = 0;
+    // 与Git相同，--graph默认使用拓扑顺序，使同一条分支上的commit连续；-L需要先处理子commit
+    let order = match options.order {
+        Order::Default if options.graph.is_some() || line_log.is_some() => Order::Topo,
+        order => order,
+    };
+    for hash in rev_list::walk(&set, order) {
+        if commits.len() >= number {
+            break;
+        }
+        // 每个commit都要处理，才能继续跟踪范围
+        let patch = match &mut line_log {
+            Some(line_log) => match line_log.next(&hash) {
+                Some(patch) => Some(patch),
+                None => continue,
+            },
+            None => None,
+        };
+        let commit = Commit::load(&hash);
+        if !filter.matches(&commit) {
+            continue;
+        }
+        let mut changes = None;
+        if let Some(path) = follow.clone() {
+            let files = commit_changes(&hash, &commit.get_parent_hash());
+            let files = diff::detect_renames(files, Some(similarity::DEFAULT_THRESHOLD), None);
+            let Some(file) = files.into_iter().find(|file| file.path == path) else {
+                continue; // 未修改该文件
+            };
+            if let Some(rename) = &file.rename {
+                follow = Some(rename.from.clone()); // 更早的commit中使用原文件名
             }
-            println!("{}", ")".yellow());
-        } else {
-            println!("{}{}{}{}{}", "commit ".yellow(), head_commit.yellow(), "(".yellow(), "HEAD".blue(), ")".yellow());
-        }
-        println!("Author: {}", commit.get_author());
-        println!("Date:   {}", commit.get_date());
-        println!();
-        println!("    {}", commit.get_message());
-        println!();
-
-        if all == false {
-            if number > 1 {
-                number -= 1;
-            } else {
-                break;
+            changes = Some(vec![file]);
+        }
+        if let Some(pickaxe) = &pickaxe {
+            let parents = commit.get_parent_hash();
+            let files = commit_changes(&hash, &parents);
+            if parents.len() > 1 || !pickaxe.matches(&files, &options.paths, options.diff.algorithm) {
+                continue;
             }
         }
-        if commit.get_parent_hash().is_empty() {
-            break;
+        if skipped < options.skip {
+            skipped += 1;
+            continue;
         }
-        head_commit = commit.get_parent_hash().first().unwrap().clone();
+        // 与Git相同，merge commit默认不显示修改
+        let parents = commit.get_parent_hash();
+        let details = match patch {
+            Some(patch) => Some(diff::color_patch(&patch, false)),
+            None if options.diff.summary_only() && parents.len() <= 1 => {
+                let files = changes.unwrap_or_else(|| commit_changes(&hash, &parents));
+                Some(diff::summary_lines(&files, &options.diff))
+            }
+            None => None,
+        };
+        commits.push((commit, details));
+    }
+    if options.reverse {
+        commits.reverse();
     }
-    log_count
+
+    let decorations = pretty::decorations();
+    let shown = commits.iter().map(|(commit, _)| commit.get_hash()).collect::<Vec<_>>();
+    let rewritten = match options.graph {
+        Some(_) => rev_list::rewrite_parents(&shown),
+        None => HashMap::new(),
+    };
+    let mut graph = options.graph.map(Graph::new);
+    for (i, (commit, details)) in commits.iter().enumerate() {
+        let hash = commit.get_hash();
+        let mut lines = options.pretty.format(commit, &decorations);
+        if let Some(details) = details {
+            lines.extend(details.iter().cloned());
+            if options.pretty.is_multiline() {
+                lines.push(String::new());
+            }
+        }
+        if let Some(graph) = &mut graph {
+            // 连接到最近的会输出的祖先，没有时分支线在此结束
+            lines = graph.render(&hash, &rewritten[&hash], &lines);
+        }
+        // `format:`的最后一个commit之后不换行；有diff摘要时仍然换行（与Git相同）
+        let last = match options.pretty.is_separator() && details.is_none() && i + 1 == commits.len() {
+            true => lines.pop(),
+            false => None,
+        };
+        lines.iter().for_each(|line| println!("{}", line));
+        if let Some(last) = last {
+            print!("{}", last);
+        }
+    }
+    commits.len()
 }
 
 #[cfg(test)]
 mod test {
     use super::super::super::commands;
-    use crate::utils::test;
+    use super::LogOptions;
+    use crate::models::head;
+    use crate::utils::diff::DiffAlgorithm;
+    use crate::utils::{date, graph::GraphStyle, line_range, pretty, rev_list::Order, test, util};
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime},
+    };
+
+    fn log_count(all: bool, number: Option<usize>, follow: Option<&str>) -> usize {
+        let follow = follow.map(PathBuf::from);
+        super::__log(&LogOptions { all, number, follow, ..Default::default() })
+    }
     #[test]
     fn test_log() {
         test::setup_with_clean_mit();
-        assert_eq!(super::__log(false, None), 0);
+        assert_eq!(log_count(false, None, None), 0);
         commands::commit::commit("test commit 2".into(), true);
-        assert_eq!(super::__log(false, Some(1)), 1);
+        assert_eq!(log_count(false, Some(1), None), 1);
         commands::commit::commit("test commit 3".into(), true);
-        assert_eq!(super::__log(false, None), 2);
+        assert_eq!(log_count(false, None, None), 2);
+    }
+
+    #[test]
+    fn test_log_merge() {
+        test::setup_with_empty_workdir();
+        commands::commit("base".into(), true);
+        commands::switch(None, Some("feature".into()), false);
+        commands::commit("feature".into(), true);
+        commands::switch(Some("master".into()), None, false);
+        commands::commit("master".into(), true);
+        commands::merge(Some("feature".into()), false, false, DiffAlgorithm::Myers);
+        // merge commit、master、feature、base，base只输出一次
+        assert_eq!(log_count(true, None, None), 4);
+    }
+
+    #[test]
+    fn test_log_follow() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("old.txt"), Some("1\n2\n3\n4\n"));
+        commands::add(vec![], true, false);
+        commands::commit("add old".into(), false);
+        test::ensure_file(Path::new("other.txt"), Some("other\n"));
+        commands::add(vec![], true, false);
+        commands::commit("add other".into(), false);
+        fs::rename("old.txt", "new.txt").unwrap();
+        test::ensure_file(Path::new("new.txt"), Some("1\n2\n3\nfour\n"));
+        commands::add(vec![], true, false);
+        commands::commit("rename".into(), false);
+
+        // rename、add old，跳过add other
+        assert_eq!(log_count(true, None, Some("new.txt")), 2);
+        assert_eq!(log_count(true, None, Some("other.txt")), 1);
+        assert_eq!(log_count(false, Some(1), Some("new.txt")), 1);
+    }
+
+    #[test]
+    fn test_log_range() {
+        test::setup_with_empty_workdir();
+        commands::commit("base".into(), true);
+        commands::switch(None, Some("feature".into()), false);
+        commands::commit("feature".into(), true);
+        commands::switch(Some("master".into()), None, false);
+        commands::commit("master".into(), true);
+        commands::merge(Some("feature".into()), false, false, DiffAlgorithm::Myers);
+
+        let count = |revs: &[&str], number: Option<usize>| {
+            let revs = revs.iter().map(|rev| rev.to_string()).collect();
+            let options = LogOptions {
+                revs,
+                number,
+                order: Order::Topo,
+                reverse: true,
+                ..Default::default()
+            };
+            super::__log(&options)
+        };
+        assert_eq!(count(&["feature..master"], None), 2);
+        assert_eq!(count(&["master", "^feature"], Some(1)), 1);
+        assert_eq!(count(&["feature"], None), 2);
+        assert_eq!(count(&["no_such_branch"], None), 0);
+    }
+
+    #[test]
+    fn test_log_graph() {
+        test::setup_with_empty_workdir();
+        commands::commit("base".into(), true);
+        let base = head::current_head_commit();
+        commands::switch(None, Some("feature".into()), false);
+        commands::commit("feature".into(), true);
+        let feature = head::current_head_commit();
+        commands::switch(Some("master".into()), None, false);
+        commands::commit("master".into(), true);
+        commands::merge(Some("feature".into()), false, false, DiffAlgorithm::Myers);
+        let merge = head::current_head_commit();
+        let tag = util::get_storage_path().unwrap().join("refs/tags/v1");
+        fs::create_dir_all(tag.parent().unwrap()).unwrap();
+        fs::write(tag, &base).unwrap();
+
+        let decorations = pretty::decorations();
+        assert_eq!(decorations[&merge], ["HEAD -> master"]);
+        assert_eq!(decorations[&feature], ["feature"]);
+        assert_eq!(decorations[&base], ["tag: v1"]);
+        commands::switch(Some(feature.clone()), None, true);
+        assert_eq!(pretty::decorations()[&feature], ["HEAD", "feature"]);
+
+        let graph = Some(GraphStyle::Unicode);
+        let options = LogOptions { revs: vec!["master".into()], graph, ..Default::default() };
+        assert_eq!(super::__log(&options), 4);
+        let options = LogOptions {
+            revs: vec!["feature..master".into()],
+            graph,
+            ..Default::default()
+        };
+        assert_eq!(super::__log(&options), 2);
+    }
+
+    #[test]
+    fn test_log_filter() {
+        test::setup_with_empty_workdir();
+        commands::commit("fix: first bug".into(), true);
+        commands::commit("feat: new command".into(), true);
+        commands::commit("Fix: second bug".into(), true);
+        let count = |options: LogOptions| super::__log(&options);
+
+        let grep = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        assert_eq!(count(LogOptions { grep: grep(&["^fix"]), ..Default::default() }), 1);
+        assert_eq!(
+            count(LogOptions {
+                grep: grep(&["^fix"]),
+                ignore_case: true,
+                ..Default::default()
+            }),
+            2
+        );
+        assert_eq!(count(LogOptions { grep: grep(&["^fix", "^feat"]), ..Default::default() }), 2);
+        assert_eq!(count(LogOptions { grep: grep(&["("]), ..Default::default() }), 0); // 无效的正则
+        let skip = LogOptions { grep: grep(&["bug"]), skip: 1, ..Default::default() };
+        assert_eq!(count(skip), 1);
+        assert_eq!(count(LogOptions { skip: 1, number: Some(1), ..Default::default() }), 1);
+        assert_eq!(count(LogOptions { skip: 5, ..Default::default() }), 0);
+
+        assert_eq!(count(LogOptions { author: Some("^mit <mit@mit>$".into()), ..Default::default() }), 3);
+        assert_eq!(count(LogOptions { author: Some("someone".into()), ..Default::default() }), 0);
+        assert_eq!(count(LogOptions { committer: Some("mit-author".into()), ..Default::default() }), 3);
+
+        let since = date::parse("1 hour ago").ok();
+        assert_eq!(count(LogOptions { since, ..Default::default() }), 3);
+        let until = date::parse("yesterday").ok();
+        assert_eq!(count(LogOptions { until, ..Default::default() }), 0);
+        assert_eq!(
+            count(LogOptions {
+                since: Some(SystemTime::now() + Duration::from_secs(60)),
+                ..Default::default()
+            }),
+            0
+        );
+    }
+
+    #[test]
+    fn test_log_pickaxe() {
+        test::setup_with_empty_workdir();
+        let commit = |file: &str, content: &str, message: &str| {
+            test::ensure_file(Path::new(file), Some(content));
+            commands::add(vec![], true, false);
+            commands::commit(message.into(), false);
+        };
+        commit("a.txt", "fn main() {}\n", "add main");
+        commit("a.txt", "fn main() {}\nlet x = 1;\n", "add x");
+        commit("b.txt", "other\n", "add b");
+        commit("a.txt", "fn start() {}\nlet x = 1;\n", "rename main");
+        let count = |options: LogOptions| super::__log(&options);
+        let s = |string: &str| Some(string.to_string());
+
+        // -S：出现次数发生变化
+        assert_eq!(count(LogOptions { pickaxe: s("fn main"), ..Default::default() }), 2);
+        assert_eq!(count(LogOptions { pickaxe: s("let x"), ..Default::default() }), 1);
+        assert_eq!(count(LogOptions { pickaxe: s("fn main("), ..Default::default() }), 2); // 默认不是正则
+        let regex = LogOptions {
+            pickaxe: s(r"fn \w+\("),
+            pickaxe_regex: true,
+            ..Default::default()
+        };
+        assert_eq!(count(regex), 1); // rename main中出现次数不变
+        let ignore_case = LogOptions {
+            pickaxe: s("FN MAIN"),
+            ignore_case: true,
+            ..Default::default()
+        };
+        assert_eq!(count(ignore_case), 2);
+        let paths = vec![PathBuf::from("b.txt")];
+        assert_eq!(count(LogOptions { pickaxe: s("fn main"), paths, ..Default::default() }), 0);
+
+        // -G：新增或删除的行匹配
+        assert_eq!(count(LogOptions { pickaxe_grep: s("^fn"), ..Default::default() }), 2);
+        assert_eq!(count(LogOptions { pickaxe_grep: s(r"x = \d"), ..Default::default() }), 1);
+        assert_eq!(count(LogOptions { pickaxe_grep: s("^other$"), ..Default::default() }), 1);
+        let invalid = LogOptions { pickaxe_grep: s("("), ..Default::default() };
+        assert_eq!(count(invalid), 0); // 无效的正则
+    }
+
+    #[test]
+    fn test_log_line_range() {
+        test::setup_with_empty_workdir();
+        let commit = |file: &str, content: &str, message: &str| {
+            test::ensure_file(Path::new(file), Some(content));
+            commands::add(vec![], true, false);
+            commands::commit(message.into(), false);
+        };
+        commit("a.rs", "use x;\n\nfn main() {\n    let a = 1;\n}\n\nfn other() {}\n", "init");
+        commit("a.rs", "use x;\n\nfn other() {\n    2\n}\n\nfn main() {\n    let a = 1;\n}\n", "edit other");
+        commit("a.rs", "use x;\n\nfn other() {\n    2\n}\n\nfn main() {\n    let a = 2;\n}\n", "edit main");
+        fs::remove_file("a.rs").unwrap();
+        commit("b.rs", "use y;\n\nfn other() {\n    2\n}\n\nfn main() {\n    let a = 2;\n}\n", "rename");
+
+        let count = |ranges: &[&str]| {
+            let line_ranges = ranges
+                .iter()
+                .map(|range| line_range::FileRange::parse(range).unwrap())
+                .collect();
+            super::__log(&LogOptions { line_ranges, ..Default::default() })
+        };
+        // 跟踪到重命名之前的a.rs，main被移动但没有修改的commit不输出
+        assert_eq!(count(&[":fn main:b.rs"]), 2); // edit main、init
+        assert_eq!(count(&["1,1:b.rs"]), 2); // rename、init
+        assert_eq!(count(&["3,5:b.rs"]), 1); // edit other中整个函数都是新增的，不再继续跟踪
+        assert_eq!(count(&["1,1:b.rs", "8,8:b.rs"]), 3);
+        assert_eq!(count(&["20,30:b.rs"]), 0); // 超出文件的行数
+        assert_eq!(count(&["1,2:missing.rs"]), 0);
     }
 }
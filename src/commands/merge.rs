@@ -1,27 +1,96 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use colored::Colorize;
+
 use crate::{
     commands::{self, status::*},
-    models::{head, Commit, Hash},
-    utils::{store, util},
+    models::{head, Blob, Commit, ConflictEntry, Hash, Index},
+    utils::{
+        diff::{self, DiffAlgorithm},
+        diff3,
+        merge_base::CommitGraph,
+        revision, util, PathExt,
+    },
 };
 
 enum MergeErr {
     NoFastForward,
     NoClean,
+    Conflict,
 }
 
 fn check_ff(current: &Hash, target: Hash) -> Result<bool, MergeErr> {
-    let target_commit = Commit::load(&target);
-    // 检查current是否是target的祖先
-    if *current == target_commit.get_hash() {
+    // 检查current是否是target的祖先，当前分支还没有commit时同样可以fast forward
+    if current.is_empty() || CommitGraph::new().is_ancestor(current, &target) {
         return Ok(true);
     }
-    for parent in target_commit.get_parent_hash() {
-        let result = check_ff(current, parent);
-        if result.is_ok() {
-            return result;
+    Err(MergeErr::NoFastForward)
+}
+
+/// commit中的所有文件：相对路径(to workdir) -> blob hash，commit为空时返回空
+fn commit_blobs(commit: &Option<Hash>) -> HashMap<PathBuf, Hash> {
+    match commit {
+        Some(commit) => Commit::load(commit).get_tree().get_recursive_blobs().into_iter().collect(),
+        None => HashMap::new(),
+    }
+}
+
+/// 文件级三方合并的结果（相对路径）
+#[derive(Default)]
+struct TreeMerge {
+    index: Vec<(PathBuf, Hash)>,                      // 合并成功的文件，写入暂存区(stage 0)
+    worktree: Vec<(PathBuf, Hash)>,                   // 写入工作区的文件，冲突文件包含冲突标记
+    conflicts: Vec<(PathBuf, String, ConflictEntry)>, // 冲突的文件 & 冲突类型 & 各个版本(stage 1~3)
+}
+
+/** 对base、ours、theirs三棵树进行文件级三方合并；双方都修改的文本文件再进行行级合并
+ * <br>只有一方修改（或双方修改相同）的文件直接采用修改后的版本
+ */
+fn merge_trees(
+    base: &Option<Hash>,
+    ours: &Hash,
+    theirs: &Hash,
+    labels: (&str, &str),
+    algorithm: DiffAlgorithm,
+) -> TreeMerge {
+    let base = commit_blobs(base);
+    let ours = commit_blobs(&Some(ours.clone()));
+    let theirs = commit_blobs(&Some(theirs.clone()));
+    let mut paths = base.keys().chain(ours.keys()).chain(theirs.keys()).collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    let mut result = TreeMerge::default();
+    for path in paths {
+        let (b, o, t) = (base.get(path), ours.get(path), theirs.get(path));
+        let stages = ConflictEntry { base: b.cloned(), ours: o.cloned(), theirs: t.cloned() };
+        let (worktree, conflict) = match (o, t) {
+            _ if o == t || t == b => (o.cloned(), None),
+            _ if o == b => (t.cloned(), None),
+            (Some(o), Some(t)) => {
+                let load = |hash: &Hash| Blob::load(hash).get_content();
+                let (o_content, t_content) = (load(o), load(t));
+                let b_content = b.map(load).unwrap_or_default();
+                if diff::is_binary(&o_content) || diff::is_binary(&t_content) || diff::is_binary(&b_content) {
+                    (Some(o.clone()), Some("binary")) // 二进制文件无法按行合并，工作区保留ours
+                } else {
+                    let merged = diff3::merge(&b_content, &o_content, &t_content, labels, algorithm);
+                    let kind = if b.is_some() { "content" } else { "add/add" };
+                    (Some(Blob::new(merged.content).get_hash()), (merged.conflicts > 0).then_some(kind))
+                }
+            }
+            // 一方修改，另一方删除：工作区保留修改后的版本供用户选择
+            _ => (o.or(t).cloned(), Some("modify/delete")),
+        };
+        match conflict {
+            Some(kind) => result.conflicts.push((path.clone(), kind.to_string(), stages)),
+            None => result.index.extend(worktree.clone().map(|hash| (path.clone(), hash))),
+        }
+        if let Some(hash) = worktree {
+            result.worktree.push((path.clone(), hash));
         }
     }
-    Err(MergeErr::NoFastForward)
+    result
 }
 
 /** commit 以fast forward到形式合并到当前分支 */
@@ -44,7 +113,8 @@ fn merge_ff(commit_hash: String) -> Result<(), MergeErr> {
     let head = head::current_head();
     match head {
         head::Head::Branch(branch) => {
-            head::update_branch(&branch, &commit_hash.clone());
+            let message = format!("merge {}: Fast-forward", &commit_hash[..7]);
+            head::update_branch(&branch, &commit_hash.clone(), &message);
             commands::restore::restore(vec![], Some(commit_hash.clone()), true, true)
         }
         head::Head::Detached(_) => {
@@ -55,35 +125,188 @@ fn merge_ff(commit_hash: String) -> Result<(), MergeErr> {
     Ok(())
 }
 
-/** merge，暂时只支持fast forward */
-pub fn merge(branch: String) {
-    let merge_commit = {
+/** 三方合并：以最近公共祖先为base，合并target到当前分支
+<br>没有冲突时自动创建一个有两个parent的merge commit；有冲突时在工作区写入冲突标记，等待用户解决
+ */
+fn merge_three_way(target: &Hash, label: &str, message: String, algorithm: DiffAlgorithm) -> Result<(), MergeErr> {
+    let current = head::current_head_commit();
+    let base = CommitGraph::new().merge_base(&current, target);
+    let result = merge_trees(&base, &current, target, ("HEAD", label), algorithm);
+
+    // 未跟踪的文件不能被覆盖
+    let index = Index::get_instance();
+    let overwritten = result
+        .worktree
+        .iter()
+        .map(|(path, _)| path.to_absolute_workdir())
+        .filter(|path| path.exists() && !index.tracked(path))
+        .collect::<Vec<_>>();
+    if !overwritten.is_empty() {
+        println!("fatal: 以下未跟踪的文件将会被合并覆盖：");
+        for path in overwritten {
+            println!("\t{}", path.to_relative_workdir().display());
+        }
+        return Err(MergeErr::NoClean);
+    }
+
+    head::write_special(head::ORIG_HEAD, &current);
+    commands::restore::restore_worktree(None, &result.worktree);
+    commands::restore::restore_index(None, &result.index);
+
+    if !result.conflicts.is_empty() {
+        // 记录合并状态，等待用户解决冲突后 `mit commit` 或 `mit merge --continue`
+        let mut merge_msg = format!("{}\n\n# Conflicts:\n", message);
+        for (path, kind, stages) in &result.conflicts {
+            index.add_conflict(&path.to_absolute_workdir(), stages.clone());
+            let msg = format!("CONFLICT ({}): Merge conflict in {}", kind, path.display());
+            println!("{}", msg.red());
+            merge_msg += &format!("#\t{}\n", path.display());
+        }
+        index.save();
+        head::write_special(head::MERGE_HEAD, target);
+        head::write_special(head::MERGE_MSG, &merge_msg);
+        println!("Automatic merge failed; fix conflicts and then commit the result.");
+        return Err(MergeErr::Conflict);
+    }
+
+    let mut commit = Commit::new(index, vec![current, target.clone()], message);
+    let commit_hash = commit.save();
+    head::update_head_commit(&commit_hash, &format!("merge {}: Merge made by the 'three-way' strategy.", label));
+    println!("Merge made by the 'three-way' strategy. [{}]", &commit_hash[..7]);
+    Ok(())
+}
+
+/// 尚未解决的冲突文件（相对路径 to workdir）：暂存区中存在stage 1~3的文件，通过`mit add`或`mit rm`解决
+pub fn unmerged_paths() -> Vec<PathBuf> {
+    let index = Index::get_instance();
+    let mut paths = index
+        .get_conflicted_files()
+        .iter()
+        .map(|path| path.to_relative_workdir())
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+/// 合并的提交信息：MERGE_MSG去掉注释行
+pub fn merge_message() -> String {
+    let merge_msg = head::read_special(head::MERGE_MSG).unwrap_or_default();
+    let lines = merge_msg.lines().filter(|line| !line.starts_with('#')).collect::<Vec<_>>();
+    lines.join("\n").trim().to_string()
+}
+
+/// 解决冲突后，使用MERGE_MSG完成合并提交
+fn merge_continue() {
+    if head::merge_head().is_none() {
+        println!("fatal: There is no merge in progress (MERGE_HEAD missing).");
+        return;
+    }
+    let unmerged = unmerged_paths();
+    if !unmerged.is_empty() {
+        println!("fatal: 存在未解决的冲突，请解决后使用 \"mit add <file>...\" 标记");
+        for path in unmerged {
+            println!("{}", format!("\t{}", path.display()).red());
+        }
+        return;
+    }
+    commands::commit(merge_message(), true);
+}
+
+/// 放弃合并，恢复到合并之前的状态(ORIG_HEAD)
+fn merge_abort() {
+    if head::merge_head().is_none() {
+        println!("fatal: There is no merge to abort (MERGE_HEAD missing).");
+        return;
+    }
+    let orig_head = head::orig_head().unwrap_or_else(head::current_head_commit);
+    let blobs = Commit::load(&orig_head).get_tree().get_recursive_blobs();
+    commands::restore::restore_worktree(None, &blobs);
+    commands::restore::restore_index(None, &blobs);
+    let index = Index::get_instance();
+    index.clear_conflicts();
+    index.save();
+    head::clear_merge_state();
+}
+
+/** merge，优先使用fast forward，无法fast forward时进行三方合并
+<br>合并因冲突中断后，使用`--continue`在解决冲突后提交，或使用`--abort`放弃合并
+<br>algorithm：行级合并使用的差分算法
+ */
+pub fn merge(branch: Option<String>, continue_merge: bool, abort: bool, algorithm: DiffAlgorithm) {
+    util::check_repo_exist();
+    if continue_merge {
+        merge_continue();
+        return;
+    } else if abort {
+        merge_abort();
+        return;
+    }
+    if head::merge_head().is_some() {
+        println!("fatal: You have not concluded your merge (MERGE_HEAD exists).");
+        println!("Please, commit your changes before you merge.");
+        return;
+    }
+    let branch = revision::expand_previous_branch(&branch.expect("没有指定要合并的分支"));
+    let (merge_commit, message) = {
         if head::list_local_branches().contains(&branch) {
             // Branch Name, e.g. master
-            head::get_branch_head(&branch)
+            (head::get_branch_head(&branch), format!("Merge branch '{}'", branch))
         } else {
-            // Commit Hash, e.g. a1b2c3d4
-            let store = store::Store::new();
-            let commit = store.search(&branch);
-            if commit.is_none() || !util::is_typeof_commit(commit.clone().unwrap()) {
-                println!("fatal: 非法的 commit hash: '{}'", branch);
-                return;
+            // 其他版本表达式，e.g. a1b2c3d4、HEAD~1、@{-1}
+            match revision::resolve_commit(&branch) {
+                Some(commit) => (commit, format!("Merge commit '{}'", branch)),
+                None => {
+                    println!("{}", revision::unknown_revision(&branch));
+                    return;
+                }
             }
-            commit.unwrap()
         }
     };
-    // 暂时只支持fast forward
-    let _ = merge_ff(merge_commit);
+    let current_commit = head::current_head_commit();
+    if !current_commit.is_empty() && check_ff(&merge_commit, current_commit.clone()).is_ok() {
+        println!("Already up to date.");
+        return;
+    }
+    match merge_ff(merge_commit.clone()) {
+        Ok(_) if !current_commit.is_empty() => head::write_special(head::ORIG_HEAD, &current_commit),
+        Err(MergeErr::NoFastForward) => {
+            let _ = merge_three_way(&merge_commit, &branch, message, algorithm);
+        }
+        _ => {}
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::{fs, path::Path};
+
     use super::*;
     use crate::{
-        commands::{commit, switch::switch},
+        commands::{self as cmd, commit, switch::switch},
         utils::test,
     };
 
+    /// 在master上提交base，然后分别在master & feature上提交修改
+    fn setup_diverged(ours: &[(&str, &str)], theirs: &[(&str, &str)]) {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("1\n2\n3\n4\n5\n"));
+        test::ensure_file(Path::new("b.txt"), Some("b\n"));
+        cmd::add(vec![], true, false);
+        commit::commit("base".to_string(), false);
+        switch(None, Some("feature".to_string()), false);
+        for (path, content) in theirs {
+            test::ensure_file(Path::new(path), Some(content));
+        }
+        cmd::add(vec![], true, false);
+        commit::commit("theirs".to_string(), false);
+        switch(Some("master".to_string()), None, false);
+        for (path, content) in ours {
+            test::ensure_file(Path::new(path), Some(content));
+        }
+        cmd::add(vec![], true, false);
+        commit::commit("ours".to_string(), false);
+    }
+
     #[test]
     fn test_check_ff() {
         test::setup_with_empty_workdir();
@@ -120,4 +343,193 @@ mod test {
         assert!(matches!(result.unwrap_err(), MergeErr::NoFastForward));
         print!("success detect no fast forward");
     }
+
+    #[test]
+    fn test_merge_base() {
+        setup_diverged(&[("c.txt", "c")], &[("d.txt", "d")]);
+        let ours = head::current_head_commit();
+        let theirs = head::get_branch_head(&"feature".to_string());
+        let base = Commit::load(&ours).get_parent_hash()[0].clone();
+        let mut graph = CommitGraph::new();
+        assert_eq!(graph.merge_base(&ours, &theirs), Some(base.clone()));
+        assert_eq!(graph.merge_base(&ours, &base), Some(base.clone()));
+        assert_eq!(graph.merge_base(&ours, &ours), Some(ours));
+    }
+
+    #[test]
+    fn test_merge_clean() {
+        setup_diverged(
+            &[("a.txt", "1\nours\n3\n4\n5\n"), ("c.txt", "c\n")],
+            &[("a.txt", "1\n2\n3\n4\ntheirs\n"), ("d.txt", "d\n")],
+        );
+        let ours = head::current_head_commit();
+        let theirs = head::get_branch_head(&"feature".to_string());
+        fs::remove_file("b.txt").unwrap();
+        cmd::add(vec![], true, false);
+        commit::commit("delete b".to_string(), false);
+        let ours_after_delete = head::current_head_commit();
+        assert_ne!(ours, ours_after_delete);
+
+        merge(Some("feature".to_string()), false, false, DiffAlgorithm::Myers);
+        let commit = Commit::load(&head::current_head_commit());
+        assert_eq!(commit.get_parent_hash(), vec![ours_after_delete, theirs]);
+        assert_eq!(commit.get_message(), "Merge branch 'feature'");
+        assert_eq!(fs::read_to_string("a.txt").unwrap(), "1\nours\n3\n4\ntheirs\n");
+        assert!(Path::new("c.txt").exists() && Path::new("d.txt").exists());
+        assert!(!Path::new("b.txt").exists());
+        assert!(changes_to_be_committed().is_empty());
+        assert!(changes_to_be_staged().is_empty());
+
+        // 再次合并
+        merge(Some("feature".to_string()), false, false, DiffAlgorithm::Myers);
+        assert_eq!(head::current_head_commit(), commit.get_hash());
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        setup_diverged(&[("a.txt", "1\nours\n3\n4\n5\n")], &[("a.txt", "1\ntheirs\n3\n4\n5\n"), ("d.txt", "d\n")]);
+        let ours = head::current_head_commit();
+        let result = merge_three_way(
+            &head::get_branch_head(&"feature".to_string()),
+            "feature",
+            "merge".to_string(),
+            DiffAlgorithm::Myers,
+        );
+        assert!(matches!(result, Err(MergeErr::Conflict)));
+        assert_eq!(head::current_head_commit(), ours); // 没有提交
+        assert_eq!(
+            fs::read_to_string("a.txt").unwrap(),
+            "1\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\n3\n4\n5\n"
+        );
+        assert_eq!(fs::read_to_string("d.txt").unwrap(), "d\n"); // 无冲突的文件已合并
+        let index = Index::get_instance();
+        assert!(index.contains(Path::new("d.txt")));
+        assert!(!index.contains(Path::new("a.txt"))); // 冲突文件没有stage 0
+        let conflict = index.get_conflict(Path::new("a.txt")).unwrap();
+        let hash = |content: &str| Some(Blob::dry_new(content.into()).get_hash());
+        assert_eq!(conflict.stage(1), hash("1\n2\n3\n4\n5\n"));
+        assert_eq!(conflict.stage(2), hash("1\nours\n3\n4\n5\n"));
+        assert_eq!(conflict.stage(3), hash("1\ntheirs\n3\n4\n5\n"));
+    }
+
+    #[test]
+    fn test_merge_untracked_overwritten() {
+        setup_diverged(&[("c.txt", "c\n")], &[("d.txt", "d\n")]);
+        test::ensure_file(Path::new("d.txt"), Some("untracked"));
+        let ours = head::current_head_commit();
+        let result = merge_three_way(
+            &head::get_branch_head(&"feature".to_string()),
+            "feature",
+            "merge".to_string(),
+            DiffAlgorithm::Myers,
+        );
+        assert!(matches!(result, Err(MergeErr::NoClean)));
+        assert_eq!(head::current_head_commit(), ours);
+        assert_eq!(fs::read_to_string("d.txt").unwrap(), "untracked");
+    }
+
+    #[test]
+    fn test_merge_continue() {
+        setup_diverged(&[("a.txt", "1\nours\n3\n4\n5\n")], &[("a.txt", "1\ntheirs\n3\n4\n5\n"), ("d.txt", "d\n")]);
+        let ours = head::current_head_commit();
+        let theirs = head::get_branch_head(&"feature".to_string());
+        merge(Some("feature".to_string()), false, false, DiffAlgorithm::Myers);
+        assert_eq!(head::merge_head(), Some(theirs.clone()));
+        assert_eq!(head::orig_head(), Some(ours.clone()));
+        assert_eq!(unmerged_paths(), vec![PathBuf::from("a.txt")]);
+        assert_eq!(merge_message(), "Merge branch 'feature'");
+
+        // 存在冲突时无法提交 & 无法开始新的合并
+        commit::commit("merge".to_string(), false);
+        merge(None, true, false, DiffAlgorithm::Myers);
+        merge(Some("feature".to_string()), false, false, DiffAlgorithm::Myers);
+        assert_eq!(head::current_head_commit(), ours);
+
+        test::ensure_file(Path::new("a.txt"), Some("1\nresolved\n3\n4\n5\n"));
+        cmd::add(vec!["a.txt".to_string()], false, false);
+        assert!(unmerged_paths().is_empty());
+        merge(None, true, false, DiffAlgorithm::Myers);
+        let commit = Commit::load(&head::current_head_commit());
+        assert_eq!(commit.get_parent_hash(), vec![ours, theirs]);
+        assert_eq!(commit.get_message(), "Merge branch 'feature'");
+        assert!(head::merge_head().is_none());
+        assert!(changes_to_be_staged().is_empty());
+    }
+
+    #[test]
+    fn test_commit_during_merge() {
+        setup_diverged(&[("a.txt", "ours\n")], &[("a.txt", "theirs\n")]);
+        let theirs = head::get_branch_head(&"feature".to_string());
+        merge(Some("feature".to_string()), false, false, DiffAlgorithm::Myers);
+        test::ensure_file(Path::new("a.txt"), Some("ours\n")); // 采用ours，暂存区没有变化
+        cmd::add(vec![], true, false);
+        commit::commit("my merge".to_string(), false);
+        let commit = Commit::load(&head::current_head_commit());
+        assert_eq!(commit.get_parent_hash()[1], theirs);
+        assert_eq!(commit.get_message(), "my merge");
+        assert!(head::merge_head().is_none());
+    }
+
+    #[test]
+    fn test_merge_abort() {
+        setup_diverged(&[("a.txt", "1\nours\n3\n4\n5\n")], &[("a.txt", "1\ntheirs\n3\n4\n5\n"), ("d.txt", "d\n")]);
+        let ours = head::current_head_commit();
+        merge(Some("feature".to_string()), false, false, DiffAlgorithm::Myers);
+        assert!(head::merge_head().is_some());
+        test::ensure_file(Path::new("untracked.txt"), Some("keep"));
+
+        merge(None, false, true, DiffAlgorithm::Myers);
+        assert!(head::merge_head().is_none());
+        assert_eq!(head::current_head_commit(), ours);
+        assert_eq!(fs::read_to_string("a.txt").unwrap(), "1\nours\n3\n4\n5\n");
+        assert!(!Path::new("d.txt").exists());
+        assert!(Path::new("untracked.txt").exists());
+        assert!(changes_to_be_committed().is_empty());
+    }
+
+    #[test]
+    fn test_restore_ours_theirs() {
+        setup_diverged(&[("a.txt", "ours\n")], &[("a.txt", "theirs\n")]);
+        merge(Some("feature".to_string()), false, false, DiffAlgorithm::Myers);
+        cmd::restore(vec!["a.txt".to_string()], None, true, false); // 冲突文件无法从暂存区恢复
+        assert!(fs::read_to_string("a.txt").unwrap().contains("<<<<<<<"));
+
+        cmd::restore_stage(vec![".".to_string()], true);
+        assert_eq!(fs::read_to_string("a.txt").unwrap(), "theirs\n");
+        cmd::restore_stage(vec!["a.txt".to_string()], false);
+        assert_eq!(fs::read_to_string("a.txt").unwrap(), "ours\n");
+        assert_eq!(unmerged_paths(), vec![PathBuf::from("a.txt")]); // 仍未解决
+
+        cmd::add(vec!["a.txt".to_string()], false, true); // --update 同样可以解决冲突
+        assert!(unmerged_paths().is_empty());
+        assert!(!Index::get_instance().has_conflicts());
+    }
+
+    #[test]
+    fn test_modify_delete_conflict() {
+        setup_diverged(&[("b.txt", "modified\n")], &[("d.txt", "d\n")]);
+        switch(Some("feature".to_string()), None, false);
+        cmd::rm(vec!["b.txt".to_string()], false, false).unwrap();
+        commit::commit("delete b".to_string(), false);
+        switch(Some("master".to_string()), None, false);
+
+        merge(Some("feature".to_string()), false, false, DiffAlgorithm::Myers);
+        let conflict = Index::get_instance().get_conflict(Path::new("b.txt")).unwrap();
+        assert!(conflict.base.is_some() && conflict.ours.is_some() && conflict.theirs.is_none());
+        assert_eq!(fs::read_to_string("b.txt").unwrap(), "modified\n");
+        cmd::restore_stage(vec!["b.txt".to_string()], true); // theirs不存在
+        assert!(Path::new("b.txt").exists());
+        assert!(crate::commands::fsck::check().is_ok());
+
+        // 以删除的方式解决冲突
+        cmd::rm(vec!["b.txt".to_string()], false, false).unwrap();
+        assert!(unmerged_paths().is_empty());
+        merge(None, true, false, DiffAlgorithm::Myers);
+        assert!(head::merge_head().is_none());
+        assert!(!Commit::load(&head::current_head_commit())
+            .get_tree()
+            .get_recursive_blobs()
+            .iter()
+            .any(|(p, _)| p == Path::new("b.txt")));
+    }
 }
@@ -0,0 +1,58 @@
+use crate::utils::{
+    merge_base::CommitGraph,
+    revision::{resolve_commit, unknown_revision},
+};
+
+/** 输出两个commit的最近公共祖先；`--is-ancestor`时只检查祖先关系，不输出
+<br>返回值作为退出码：找到公共祖先 / 是祖先时为true
+ */
+pub fn merge_base(one: &str, two: &str, all: bool, is_ancestor: bool) -> bool {
+    let mut commits = Vec::new();
+    for name in [one, two] {
+        match resolve_commit(name) {
+            Some(commit) => commits.push(commit),
+            None => {
+                println!("{}", unknown_revision(name));
+                return false;
+            }
+        }
+    }
+
+    let mut graph = CommitGraph::new();
+    if is_ancestor {
+        return graph.is_ancestor(&commits[0], &commits[1]);
+    }
+    let bases = graph.merge_bases(&commits[0], &commits[1]);
+    let count = if all { bases.len() } else { 1 };
+    for base in bases.iter().take(count) {
+        println!("{}", base);
+    }
+    !bases.is_empty()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{commands as cmd, models::head, utils::test};
+
+    #[test]
+    fn test_merge_base() {
+        test::setup_with_empty_workdir();
+        cmd::commit("base".to_string(), true);
+        let base = head::current_head_commit();
+        cmd::switch(None, Some("feature".to_string()), false);
+        cmd::commit("feature".to_string(), true);
+        cmd::switch(Some("master".to_string()), None, false);
+        cmd::commit("master".to_string(), true);
+
+        assert_eq!(resolve_commit("feature"), Some(head::get_branch_head(&"feature".to_string())));
+        assert_eq!(resolve_commit(&base[0..7]), Some(base.clone()));
+        assert_eq!(resolve_commit("no_such_branch"), None);
+
+        assert!(merge_base("master", "feature", false, false));
+        assert!(merge_base("HEAD", "feature", true, false));
+        assert!(merge_base(&base, "feature", false, true));
+        assert!(!merge_base("master", "feature", false, true));
+        assert!(!merge_base("master", "no_such_branch", false, false));
+    }
+}
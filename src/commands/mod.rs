@@ -1,19 +1,35 @@
 pub mod add;
 pub use add::add;
+pub mod blame;
+pub use blame::blame;
 pub mod branch;
 pub use branch::branch;
 pub mod commit;
 pub use commit::commit;
+pub mod diff;
+pub use diff::diff;
+pub mod fsck;
+pub use fsck::fsck;
+pub mod gc;
+pub use gc::{gc, repack};
 pub mod init;
 pub use init::init;
 pub mod log;
 pub use log::log;
 pub mod merge;
 pub use merge::merge;
+pub mod merge_base;
+pub use merge_base::merge_base;
+pub mod prune;
+pub use prune::prune;
 pub mod remove;
 pub use remove::remove as rm;
 pub mod restore;
-pub use restore::restore;
+pub use restore::{restore, restore_stage};
+pub mod rev_list;
+pub use rev_list::rev_list;
+pub mod show;
+pub use show::show;
 pub mod status;
 pub use status::status;
 pub mod switch;
@@ -0,0 +1,207 @@
+use std::{
+    collections::HashSet,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{Local, NaiveDate, TimeZone};
+use colored::Colorize;
+
+use crate::{
+    models::Hash,
+    utils::{reachable, store::Store, util},
+};
+
+/// 默认宽限期：2周内的object不会被删除，避免删掉刚写入、还未被引用的object
+pub const DEFAULT_EXPIRE: &str = "2.weeks.ago";
+
+/** 解析过期时间，早于该时间的不可达object才会被删除；`never`返回None
+<br>支持：`now`、`never`、`<n>.<unit>[.ago]`（unit: seconds/minutes/hours/days/weeks）、`YYYY-MM-DD`
+ */
+pub fn parse_expire(expire: &str) -> Result<Option<SystemTime>, String> {
+    let now = SystemTime::now();
+    match expire {
+        "now" => return Ok(Some(now)),
+        "never" => return Ok(None),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(expire, "%Y-%m-%d") {
+        let time = Local.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap()).earliest();
+        return time.map(|time| Some(time.into())).ok_or(format!("invalid date: {}", expire));
+    }
+    let parts = expire.trim_end_matches(".ago").split('.').collect::<Vec<_>>();
+    let (count, unit) = match parts[..] {
+        [count, unit] => (count.parse::<u64>().map_err(|_| format!("invalid expire time: {}", expire))?, unit),
+        _ => return Err(format!("invalid expire time: {}", expire)),
+    };
+    let seconds = match unit.trim_end_matches('s') {
+        "second" => 1,
+        "minute" => 60,
+        "hour" => 60 * 60,
+        "day" => 24 * 60 * 60,
+        "week" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("invalid expire unit: {}", unit)),
+    };
+    Ok(Some(now - Duration::from_secs(count * seconds)))
+}
+
+/// 找出不可达的object（loose & packed）
+pub fn unreachable_objects(store: &Store) -> HashSet<Hash> {
+    let reachable = reachable::reachable_objects();
+    store
+        .list_objects()
+        .into_iter()
+        .filter(|hash| !reachable.contains(hash))
+        .collect()
+}
+
+/// 找出不可达、且修改时间不晚于`expire`的object（loose & packed）
+pub fn expired_objects(store: &Store, expire: Option<SystemTime>) -> HashSet<Hash> {
+    let expire = match expire {
+        Some(expire) => expire,
+        None => return HashSet::new(),
+    };
+    unreachable_objects(store)
+        .into_iter()
+        .filter(|hash| store.mtime(hash).is_some_and(|mtime| mtime <= expire))
+        .collect()
+}
+
+/// 删除过期的不可达loose object，返回被删除（`dry_run`时为将被删除）的object
+pub fn prune_objects(expire: Option<SystemTime>, dry_run: bool) -> Vec<Hash> {
+    let store = Store::new();
+    let expired = expired_objects(&store, expire);
+    let mut pruned = store
+        .list_loose()
+        .into_iter()
+        .filter(|hash| expired.contains(hash))
+        .collect::<Vec<_>>();
+    pruned.sort();
+    if !dry_run {
+        pruned.retain(|hash| store.remove_loose(hash));
+    }
+    pruned
+}
+
+/** 删除不可达的loose object：从所有分支、HEAD以及暂存区出发都无法访问的object
+<br>只删除修改时间早于`--expire`（默认2周前）的object；`--dry-run`只列出，不删除
+<br>已打包的object由`mit gc`清理
+ */
+pub fn prune(expire: Option<String>, dry_run: bool) {
+    util::check_repo_exist();
+    let expire = match parse_expire(&expire.unwrap_or(DEFAULT_EXPIRE.to_string())) {
+        Ok(expire) => expire,
+        Err(err) => {
+            println!("fatal: {}", err);
+            return;
+        }
+    };
+    let store = Store::new();
+    for hash in prune_objects(expire, dry_run) {
+        if dry_run {
+            println!("{} {}", hash, store.object_type(&hash));
+        } else {
+            println!("{} {}", "Removing".red(), hash);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        commands as cmd,
+        models::{head, reflog, Blob},
+        utils::{revision, test},
+    };
+
+    #[test]
+    fn test_parse_expire() {
+        let now = SystemTime::now();
+        assert!(parse_expire("now").unwrap().unwrap() >= now);
+        assert!(parse_expire("never").unwrap().is_none());
+        let two_weeks = parse_expire("2.weeks.ago").unwrap().unwrap();
+        let diff = now.duration_since(two_weeks).unwrap().as_secs();
+        assert!(diff.abs_diff(14 * 24 * 3600) < 10);
+        assert!(parse_expire("1.day").unwrap().unwrap() < now);
+        assert!(parse_expire("2020-01-01").unwrap().unwrap() < now);
+        assert!(parse_expire("2.fortnights.ago").is_err());
+        assert!(parse_expire("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_prune() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("committed"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        let committed = Blob::dry_new("committed".into()).get_hash();
+
+        // 被覆盖的暂存内容：不可达
+        test::ensure_file(Path::new("b.txt"), Some("old"));
+        cmd::add(vec!["b.txt".to_string()], false, false);
+        let old = Blob::dry_new("old".into()).get_hash();
+        // 只在暂存区中：可达
+        test::ensure_file(Path::new("b.txt"), Some("staged"));
+        cmd::add(vec!["b.txt".to_string()], false, false);
+        let staged = Blob::dry_new("staged".into()).get_hash();
+
+        // 宽限期内不删除
+        assert!(prune_objects(parse_expire(DEFAULT_EXPIRE).unwrap(), false).is_empty());
+        assert!(prune_objects(None, false).is_empty());
+
+        assert_eq!(prune_objects(Some(SystemTime::now()), true), vec![old.clone()]);
+        let store = Store::new();
+        assert!(store.contains(&old)); // dry-run不删除
+
+        assert_eq!(prune_objects(Some(SystemTime::now()), false), vec![old.clone()]);
+        let store = Store::new();
+        assert!(!store.contains(&old));
+        assert!(store.contains(&staged));
+        assert!(store.contains(&committed));
+        assert!(store.contains(&head::current_head_commit()));
+        assert!(crate::commands::fsck::check().is_ok());
+    }
+
+    #[test]
+    fn test_prune_deleted_branch() {
+        test::setup_with_empty_workdir();
+        cmd::commit("first".to_string(), true);
+        cmd::branch(Some("tmp".to_string()), None, false, None, false, false);
+        cmd::switch(Some("tmp".to_string()), None, false);
+        test::ensure_file(Path::new("tmp.txt"), Some("tmp"));
+        cmd::add(vec![], true, false);
+        cmd::commit("second".to_string(), false);
+        let second = head::current_head_commit();
+        cmd::switch(Some("master".to_string()), None, false);
+        cmd::branch(None, None, false, Some("tmp".to_string()), true, false);
+
+        // 分支的reflog随分支删除，但HEAD的reflog中仍有记录
+        assert!(prune_objects(Some(SystemTime::now()), false).is_empty());
+        reflog::remove(reflog::HEAD);
+        let pruned = prune_objects(Some(SystemTime::now()), false);
+        assert_eq!(pruned.len(), 3); // commit + tree + blob
+        assert!(pruned.contains(&second));
+        assert!(crate::commands::fsck::check().is_ok());
+    }
+
+    #[test]
+    fn test_prune_reflog() {
+        test::setup_with_empty_workdir();
+        cmd::commit("first".to_string(), true);
+        let first = head::current_head_commit();
+        // 在分离的HEAD上提交，切换回master后只有reflog引用该commit
+        cmd::switch(Some(first), None, true);
+        test::ensure_file(Path::new("detached.txt"), Some("detached"));
+        cmd::add(vec![], true, false);
+        cmd::commit("detached".to_string(), false);
+        let detached = head::current_head_commit();
+        cmd::switch(Some("master".to_string()), None, false);
+
+        assert!(prune_objects(Some(SystemTime::now()), false).is_empty());
+        assert!(Store::new().contains(&detached));
+        assert_eq!(revision::resolve_commit("HEAD@{1}"), Some(detached));
+        assert!(crate::commands::fsck::check().is_ok());
+    }
+}
@@ -12,7 +12,7 @@ pub fn remove(files: Vec<String>, cached: bool, recursive: bool) -> io::Result<(
             println!("Warning: {} not exist", file.red());
             continue;
         }
-        if !index.contains(&path) {
+        if !index.tracked(&path) {
             //不能删除未跟踪的文件
             println!("Warning: {} not tracked", file.red());
             continue;
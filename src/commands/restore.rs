@@ -7,7 +7,7 @@ use std::{
 use crate::utils::path_ext::PathExt;
 use crate::{
     models::*,
-    utils::{store, util},
+    utils::{revision, util},
 };
 
 fn restore_to_file(hash: &Hash, path: &PathBuf) {
@@ -146,6 +146,31 @@ pub fn restore_index(filter: Option<&Vec<PathBuf>>, target_blobs: &[(PathBuf, Ha
     }
     index.save();
 }
+/** 从未解决冲突文件的某个版本恢复工作区：`--ours`(stage 2) | `--theirs`(stage 3)
+<br>只修改工作区，冲突仍需要通过`mit add`标记为已解决
+ */
+pub fn restore_stage(paths: Vec<String>, theirs: bool) {
+    util::check_repo_exist();
+    let (stage, name) = if theirs { (3, "their") } else { (2, "our") };
+    let index = Index::get_instance();
+    let conflicted = index.get_conflicted_files();
+    for path in paths {
+        let filter = vec![PathBuf::from(&path).to_absolute()];
+        let mut files: Vec<PathBuf> = util::filter_to_fit_paths(&conflicted, &filter);
+        if files.is_empty() {
+            println!("error: pathspec '{}' did not match any unmerged file", path);
+            continue;
+        }
+        files.sort();
+        for file in files {
+            match index.get_conflict(&file).and_then(|conflict| conflict.stage(stage)) {
+                Some(hash) => restore_to_file(&hash, &file),
+                None => println!("error: path '{}' does not have {} version", file.to_relative().display(), name),
+            }
+        }
+    }
+}
+
 /**
 对于工作区中的新文件，若已跟踪，则删除；若未跟踪，则保留<br>
 对于暂存区中被删除的文件，同样会恢复<br>
@@ -153,6 +178,16 @@ pub fn restore_index(filter: Option<&Vec<PathBuf>>, target_blobs: &[(PathBuf, Ha
  */
 pub fn restore(paths: Vec<String>, source: Option<String>, worktree: bool, staged: bool) {
     let paths = paths.iter().map(PathBuf::from).collect::<Vec<PathBuf>>();
+    if source.is_none() && !staged {
+        // 冲突文件在暂存区中没有stage 0，无法从暂存区恢复，需要使用--ours | --theirs
+        let unmerged: Vec<PathBuf> = util::filter_to_fit_paths(&Index::get_instance().get_conflicted_files(), &paths);
+        if !unmerged.is_empty() {
+            for path in unmerged {
+                println!("error: path '{}' is unmerged", path.to_relative().display());
+            }
+            return;
+        }
+    }
     let target_commit: Hash = {
         match source {
             None => {
@@ -164,24 +199,15 @@ pub fn restore(paths: Vec<String>, source: Option<String>, worktree: bool, stage
                     Hash::default() //index
                 }
             }
-            Some(ref src) => {
-                if src == "HEAD" {
-                    //Default Source
-                    head::current_head_commit() // "" if not exist
-                } else if head::list_local_branches().contains(src) {
-                    // Branch Name, e.g. master
-                    head::get_branch_head(src) // "" if not exist
-                } else {
-                    // [Commit Hash, e.g. a1b2c3d4] || [Wrong Branch Name]
-                    let store = store::Store::new();
-                    let commit = store.search(src);
-                    if commit.is_none() || !util::is_typeof_commit(commit.clone().unwrap()) {
-                        println!("fatal: 非法的 commit hash: '{}'", src);
-                        return;
-                    }
-                    commit.unwrap()
+            // HEAD不存在（没有commit）时恢复为空，与未指定source相同
+            Some(ref src) if src == "HEAD" && head::current_head_commit().is_empty() => Hash::default(),
+            Some(ref src) => match revision::resolve_commit(src) {
+                Some(commit) => commit,
+                None => {
+                    println!("{}", revision::unknown_revision(src));
+                    return;
                 }
-            }
+            },
         }
     };
 
@@ -195,13 +221,7 @@ pub fn restore(paths: Vec<String>, source: Option<String>, worktree: bool, stage
         } else {
             //从[target_commit]中恢复
             if target_commit.is_empty() {
-                //target_commit不存在 无法从目标恢复
-                if source.is_some() {
-                    // 如果指定了source，说明source解析失败，报错
-                    println!("fatal: could not resolve {}", source.unwrap());
-                    return;
-                }
-                Vec::new() //否则使用[空]来恢复 代表default status
+                Vec::new() //HEAD不存在（没有commit），使用[空]来恢复 代表default status
             } else {
                 //target_commit存在，最正常的情况，谢天谢地
                 let tree = Commit::load(&target_commit).get_tree();
@@ -236,6 +256,19 @@ mod test {
         assert!(index.get_tracked_files().is_empty());
     }
 
+    #[test]
+    fn test_restore_unborn_head() {
+        test::setup_with_empty_workdir();
+        test::ensure_files(&vec!["a.txt", "b.txt"]);
+        cmd::add(vec![], true, false);
+        assert_eq!(Index::get_instance().get_tracked_files().len(), 2);
+
+        // 没有commit时HEAD为空，全部取消暂存，工作区中的文件保留
+        cmd::restore(vec![".".to_string()], Some("HEAD".to_string()), false, true);
+        assert!(Index::get_instance().get_tracked_files().is_empty());
+        assert_eq!(status::changes_to_be_staged().new.len(), 2);
+    }
+
     #[test]
     fn test_restore_worktree() {
         test::setup_with_empty_workdir();
@@ -258,4 +291,32 @@ mod test {
         assert_eq!(status::changes_to_be_staged().new.len(), 1);
         assert_eq!(status::changes_to_be_staged().deleted.len(), 0);
     }
+
+    #[test]
+    fn test_restore_binary_file() {
+        test::setup_with_empty_workdir();
+        let path = PathBuf::from("image.png");
+        let content: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0x00, 0xff, 0xfe, 0xe9, 0x0d, 0x0a];
+        fs::write(&path, &content).unwrap();
+        cmd::add(vec![], true, false);
+        cmd::commit("add binary".to_string(), false);
+
+        fs::write(&path, b"broken").unwrap();
+        cmd::restore(vec![".".to_string()], Some("HEAD".to_string()), true, false);
+        assert_eq!(fs::read(&path).unwrap(), content);
+
+        fs::remove_file(&path).unwrap();
+        cmd::restore(vec![".".to_string()], None, true, false); //from index
+        assert_eq!(fs::read(&path).unwrap(), content);
+        assert!(status::changes_to_be_staged().is_empty());
+
+        // 切换到删除了该文件的分支再切换回来，从commit中恢复的内容与原来逐字节相同
+        cmd::switch(None, Some("other".to_string()), false);
+        fs::remove_file(&path).unwrap();
+        cmd::add(vec![], true, false);
+        cmd::commit("remove binary".to_string(), false);
+        cmd::switch(Some("master".to_string()), None, false);
+        assert_eq!(fs::read(&path).unwrap(), content);
+        assert!(status::changes_to_be_staged().is_empty());
+    }
 }
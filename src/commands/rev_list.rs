@@ -0,0 +1,47 @@
+use crate::{
+    models::Hash,
+    utils::rev_list::{self, Order, RevSet},
+};
+
+/** 按顺序列出版本范围内的commit，每行一个hash，供脚本使用
+<br>revs：`<rev>`、`^<rev>`、`A..B`、`A...B`，可以有多个起点
+ */
+pub fn rev_list(revs: Vec<String>, order: Order, reverse: bool) {
+    match list(&revs, order, reverse) {
+        Ok(commits) => commits.iter().for_each(|hash| println!("{}", hash)),
+        Err(msg) => println!("{}", msg),
+    }
+}
+
+fn list(revs: &[String], order: Order, reverse: bool) -> Result<Vec<Hash>, String> {
+    if revs.is_empty() {
+        return Err("fatal: 没有指定版本，如：mit rev-list HEAD".to_string());
+    }
+    let mut commits = rev_list::walk(&RevSet::parse(revs)?, order).collect::<Vec<_>>();
+    if reverse {
+        commits.reverse();
+    }
+    Ok(commits)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{commands as cmd, models::head, utils::test};
+
+    #[test]
+    fn test_rev_list() {
+        test::setup_with_empty_workdir();
+        cmd::commit("first".to_string(), true);
+        let first = head::current_head_commit();
+        cmd::commit("second".to_string(), true);
+        let second = head::current_head_commit();
+
+        let revs = |revs: &[&str]| revs.iter().map(|rev| rev.to_string()).collect::<Vec<_>>();
+        assert_eq!(list(&revs(&["HEAD"]), Order::Topo, false), Ok(vec![second.clone(), first.clone()]));
+        assert_eq!(list(&revs(&["HEAD"]), Order::Topo, true), Ok(vec![first.clone(), second.clone()]));
+        assert_eq!(list(&revs(&["HEAD~1..HEAD"]), Order::Default, false), Ok(vec![second]));
+        assert!(list(&[], Order::Default, false).is_err());
+        assert!(list(&revs(&["HEAD~5"]), Order::Default, false).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use std::io::{self, Write};
+
+use colored::Colorize;
+
+use crate::{
+    commands::diff::{self, DiffOptions},
+    models::{Blob, Commit, ObjectType, Tree},
+    utils::{revision, store::Store},
+};
+
+/** 显示object：
+ * <br>commit：提交信息，以及相对于第一个parent的修改（merge commit不显示修改）
+ * <br>tree：目录下的文件列表，子目录以`/`结尾
+ * <br>blob：文件的原始内容
+ * <br>支持`<rev>:<path>`，显示某个commit中的文件或目录
+ */
+pub fn show(objects: Vec<String>, options: DiffOptions) {
+    let objects = if objects.is_empty() {
+        vec!["HEAD".to_string()]
+    } else {
+        objects
+    };
+    for name in objects {
+        if !show_object(&name, &options) {
+            println!("{}", revision::unknown_revision(&name));
+            return;
+        }
+    }
+}
+
+/// 显示单个object，无法解析时返回false
+fn show_object(name: &str, options: &DiffOptions) -> bool {
+    let Some(hash) = revision::resolve(name) else {
+        return false;
+    };
+    match Store::new().object_type(&hash) {
+        ObjectType::Commit => show_commit(&Commit::load(&hash), options),
+        ObjectType::Tree => show_tree(name, &Tree::load(&hash)),
+        ObjectType::Blob => {
+            let _ = io::stdout().write_all(&Blob::load(&hash).get_content());
+        }
+        ObjectType::Invalid => return false,
+    }
+    true
+}
+
+fn show_commit(commit: &Commit, options: &DiffOptions) {
+    let hash = commit.get_hash();
+    let parents = commit.get_parent_hash();
+    println!("{}{}", "commit ".yellow(), hash.yellow());
+    if parents.len() > 1 {
+        let abbrev = parents.iter().map(|parent| &parent[..7]).collect::<Vec<_>>();
+        println!("Merge: {}", abbrev.join(" "));
+    }
+    println!("Author: {}", commit.get_author());
+    println!("Date:   {}", commit.get_date());
+    println!();
+    println!("    {}", commit.get_message());
+    println!();
+    if parents.len() <= 1 {
+        let files = match parents.first() {
+            Some(parent) => diff::diff_commits(parent, &hash),
+            None => diff::diff_root(&hash),
+        };
+        let files = diff::detect_renames(files, options.find_renames, options.find_copies);
+        diff::print_diff(&files, options);
+    }
+}
+
+/// 与Git相同：`tree <name>`，空行，然后每行一个条目
+fn show_tree(name: &str, tree: &Tree) {
+    println!("{}", format!("tree {}", name).yellow());
+    println!();
+    for line in format_tree(tree) {
+        println!("{}", line);
+    }
+}
+
+fn format_tree(tree: &Tree) -> Vec<String> {
+    let mut entries = tree.entries.clone();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+        .iter()
+        .map(|entry| match entry.filemode.0.as_str() {
+            "tree" => format!("{}/", entry.name),
+            _ => entry.name.clone(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{commands as cmd, models::head, utils::test};
+
+    #[test]
+    fn test_show() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("v1\n"));
+        test::ensure_file(Path::new("dir/b.txt"), Some("b\n"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        let first = head::current_head_commit();
+        test::ensure_file(Path::new("a.txt"), Some("v2\n"));
+        cmd::add(vec![], true, false);
+        cmd::commit("second".to_string(), false);
+
+        let blob = |name: &str| Blob::load(&revision::resolve(name).unwrap()).get_content();
+        assert_eq!(blob("HEAD:a.txt"), b"v2\n");
+        assert_eq!(blob(&format!("{}:a.txt", &first[..7])), b"v1\n");
+        assert_eq!(blob("master:dir/b.txt"), b"b\n");
+        assert_eq!(format_tree(&Tree::load(&revision::resolve("HEAD:").unwrap())), ["a.txt", "dir/"]);
+        assert_eq!(format_tree(&Tree::load(&revision::resolve("HEAD:dir").unwrap())), ["b.txt"]);
+        assert_eq!(revision::resolve("HEAD:no_such_file"), None);
+        assert_eq!(revision::resolve("no_such_branch:a.txt"), None);
+
+        let options = DiffOptions::default();
+        assert!(show_object("HEAD", &options));
+        assert!(show_object(&first, &options));
+        assert!(show_object("HEAD:dir", &options));
+        assert!(show_object("HEAD:a.txt", &options));
+        assert!(!show_object("HEAD:no_such_file", &options));
+    }
+}
@@ -1,11 +1,12 @@
+use crate::commands::{diff, merge};
 use crate::models::head;
 use crate::utils::path_ext::PathExt;
 use crate::{
-    models::{Blob, Commit, Index},
-    utils::util,
+    models::{Blob, Commit, ConflictEntry, Hash, Index},
+    utils::{similarity, util},
 };
 use colored::Colorize;
-use std::path::PathBuf;
+use std::{collections::HashMap, path::PathBuf};
 
 /** 获取需要commit的更改(staged)
    注：相对路径(to workdir)
@@ -15,11 +16,32 @@ pub struct Changes {
     pub new: Vec<PathBuf>,
     pub modified: Vec<PathBuf>,
     pub deleted: Vec<PathBuf>,
+    pub renamed: Vec<(PathBuf, PathBuf)>, // (原路径, 新路径)，只有调用[Changes::find_renames]后才会有
 }
 
 impl Changes {
     pub fn is_empty(&self) -> bool {
-        self.new.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+        self.new.is_empty() && self.modified.is_empty() && self.deleted.is_empty() && self.renamed.is_empty()
+    }
+
+    /** 将相似度不低于threshold的(删除, 新增)文件对识别为重命名
+    <br>old & new：变更前后的文件(相对路径 to workdir) -> blob hash
+     */
+    pub fn find_renames(&self, old: &HashMap<PathBuf, Hash>, new: &HashMap<PathBuf, Hash>, threshold: usize) -> Changes {
+        let load = |files: &HashMap<PathBuf, Hash>, paths: &[PathBuf]| {
+            paths.iter().map(|p| Blob::load(&files[p]).get_content()).collect::<Vec<_>>()
+        };
+        let (deleted, added) = (load(old, &self.deleted), load(new, &self.new));
+        let pairs = similarity::find_matches(&deleted, &added, threshold, true);
+
+        let mut change = self.clone();
+        for &(i, j, _) in &pairs {
+            change.renamed.push((self.deleted[i].clone(), self.new[j].clone()));
+        }
+        change.deleted.retain(|p| !change.renamed.iter().any(|(from, _)| from == p));
+        change.new.retain(|p| !change.renamed.iter().any(|(_, to)| to == p));
+        change.renamed.sort();
+        change
     }
 
     /// 使用paths过滤，返回绝对路径
@@ -29,6 +51,11 @@ impl Changes {
         change.new = util::filter_to_fit_paths(&abs_self.new, paths);
         change.modified = util::filter_to_fit_paths(&abs_self.modified, paths);
         change.deleted = util::filter_to_fit_paths(&abs_self.deleted, paths);
+        change.renamed = abs_self
+            .renamed
+            .into_iter()
+            .filter(|(from, to)| from.include_in(paths) || to.include_in(paths))
+            .collect();
         change
     }
 
@@ -38,6 +65,16 @@ impl Changes {
         self.filter_abs(paths).to_relative_from_abs()
     }
 
+    /// 去掉指定的文件(相对路径 to workdir)，如：未解决冲突的文件
+    pub fn exclude(&self, paths: &[PathBuf]) -> Changes {
+        let mut change = self.clone();
+        [&mut change.new, &mut change.modified, &mut change.deleted]
+            .iter_mut()
+            .for_each(|changes| changes.retain(|p| !paths.contains(p)));
+        change.renamed.retain(|(from, to)| !paths.contains(from) && !paths.contains(to));
+        change
+    }
+
     /// 转换为绝对路径（from workdir相对路径）
     pub fn to_absolute(&self) -> Changes {
         let mut change = self.clone();
@@ -50,6 +87,7 @@ impl Changes {
             .for_each(|paths| {
                 **paths = util::map(&**paths, |p| p.to_absolute_workdir());
             });
+        change.renamed = util::map(&self.renamed, |(from, to)| (from.to_absolute_workdir(), to.to_absolute_workdir()));
         change
     }
 
@@ -62,6 +100,9 @@ impl Changes {
             .for_each(|paths| {
                 **paths = util::map(&**paths, |p| util::get_relative_path_to_dir(p, &cur_dir));
             });
+        change.renamed = util::map(&self.renamed, |(from, to)| {
+            (util::get_relative_path_to_dir(from, &cur_dir), util::get_relative_path_to_dir(to, &cur_dir))
+        });
         change
     }
 
@@ -139,6 +180,27 @@ pub fn changes_to_be_staged() -> Changes {
     change
 }
 
+/// 冲突的类型，根据ours & theirs是否存在判断
+fn conflict_label(conflict: &ConflictEntry) -> &'static str {
+    match (&conflict.base, &conflict.ours, &conflict.theirs) {
+        (_, None, _) => "deleted by us",
+        (_, _, None) => "deleted by them",
+        (None, _, _) => "both added",
+        _ => "both modified",
+    }
+}
+
+/// 暂存区与HEAD的差异，并识别重命名（与Git相同，使用默认阈值）
+pub fn staged_changes_with_renames() -> Changes {
+    let changes = changes_to_be_committed();
+    if changes.deleted.is_empty() || changes.new.is_empty() {
+        return changes;
+    }
+    let head_commit = head::current_head_commit();
+    let old = if head_commit.is_empty() { HashMap::new() } else { diff::commit_files(&head_commit) };
+    changes.find_renames(&old, &diff::index_files(), similarity::DEFAULT_THRESHOLD)
+}
+
 /** 分为两个部分
 1. unstaged: 暂存区与工作区比较
 2. staged to be committed: 暂存区与HEAD(最后一次Commit::Tree)比较，即上次的暂存区
@@ -154,10 +216,34 @@ pub fn status() {
         }
     }
 
-    // 对当前目录进行过滤 & 转换为相对路径
-    let staged = changes_to_be_committed().to_relative();
-    let unstaged = changes_to_be_staged().to_relative();
+    let unmerged = merge::unmerged_paths();
+    if head::merge_head().is_some() || !unmerged.is_empty() {
+        if unmerged.is_empty() {
+            println!("All conflicts fixed but you are still merging.");
+            println!("  (use \"mit commit\" to conclude merge)");
+        } else {
+            println!("You have unmerged paths.");
+            println!("  (fix conflicts and run \"mit commit\")");
+            println!("  (use \"mit merge --abort\" to abort the merge)");
+            println!("Unmerged paths:");
+            println!("  use \"mit add <file>...\" to mark resolution");
+            let index = Index::get_instance();
+            unmerged.iter().for_each(|f| {
+                let abs_path = f.to_absolute_workdir();
+                let conflict = index.get_conflict(&abs_path).unwrap_or_default();
+                let str = format!("\t{}: {}", conflict_label(&conflict), util::get_relative_path(&abs_path).display());
+                println!("{}", str.bright_red());
+            });
+        }
+    }
+
+    // 对当前目录进行过滤 & 转换为相对路径，未解决冲突的文件单独显示
+    let staged = staged_changes_with_renames().exclude(&unmerged).to_relative();
+    let unstaged = changes_to_be_staged().exclude(&unmerged).to_relative();
     if staged.is_empty() && unstaged.is_empty() {
+        if !unmerged.is_empty() {
+            return;
+        }
         println!("nothing to commit, working tree clean");
         return;
     }
@@ -173,6 +259,10 @@ pub fn status() {
             let str = format!("\tmodified: {}", f.display());
             println!("{}", str.bright_green());
         });
+        staged.renamed.iter().for_each(|(from, to)| {
+            let str = format!("\trenamed: {} -> {}", from.display(), to.display());
+            println!("{}", str.bright_green());
+        });
         staged.new.iter().for_each(|f| {
             let str = format!("\tnew file: {}", f.display());
             println!("{}", str.bright_green());
@@ -241,4 +331,27 @@ mod tests {
 
         println!("{:?}", change);
     }
+
+    #[test]
+    fn test_staged_renames() {
+        test::setup_with_empty_workdir();
+        test::ensure_file(Path::new("a.txt"), Some("1\n2\n3\n4\n"));
+        test::ensure_file(Path::new("b.txt"), Some("b\n"));
+        cmd::add(vec![], true, false);
+        cmd::commit("init".to_string(), false);
+
+        std::fs::rename("a.txt", "c.txt").unwrap();
+        test::ensure_file(Path::new("c.txt"), Some("1\n2\n3\n5\n"));
+        std::fs::remove_file("b.txt").unwrap();
+        test::ensure_file(Path::new("d.txt"), Some("d\n"));
+        cmd::add(vec![], true, false);
+
+        let change = changes_to_be_committed();
+        assert_eq!((change.new.len(), change.deleted.len()), (2, 2));
+        let change = staged_changes_with_renames();
+        assert_eq!(change.renamed, vec![(PathBuf::from("a.txt"), PathBuf::from("c.txt"))]);
+        assert_eq!(change.new, vec![PathBuf::from("d.txt")]);
+        assert_eq!(change.deleted, vec![PathBuf::from("b.txt")]);
+        assert!(!change.exclude(&[PathBuf::from("c.txt")]).renamed.iter().any(|(_, to)| to == "c.txt"));
+    }
 }
@@ -2,7 +2,7 @@ use colored::Colorize;
 
 use crate::{
     models::{head, Commit, Hash},
-    utils::{store, util},
+    utils::revision,
 };
 
 use super::{
@@ -42,7 +42,7 @@ fn switch_to(branch: String, detach: bool) -> Result<(), SwitchErr> {
         return Err(SwitchErr::NoClean);
     }
 
-    let store = store::Store::new();
+    let branch = revision::expand_previous_branch(&branch);
     if head::list_local_branches().contains(&branch) {
         // 切到分支
         let branch_commit = head::get_branch_head(&branch);
@@ -50,14 +50,12 @@ fn switch_to(branch: String, detach: bool) -> Result<(), SwitchErr> {
         head::change_head_to_branch(&branch); // 更改head
         println!("切换到分支： '{}'", branch.green())
     } else if detach {
-        let commit = store.search(&branch);
-        if commit.is_none() || util::check_object_type(commit.clone().unwrap()) != util::ObjectType::Commit {
-            println!("fatal: 非法的 commit: '{}'", branch);
+        let Some(commit) = revision::resolve_commit(&branch) else {
+            println!("{}", revision::unknown_revision(&branch));
             return Err(SwitchErr::InvalidObject);
-        }
+        };
 
         // 切到commit
-        let commit = commit.unwrap();
         switch_to_commit(commit.clone());
         head::change_head_to_commit(&commit); // 更改head
         println!("切换到 detach commit： '{}'", commit.yellow())
@@ -74,7 +72,7 @@ pub fn switch(target_branch: Option<String>, create: Option<String>, detach: boo
         Some(new_branch) => {
             // 以target_branch为基础创建新分支create
             println!("create new branch: {:?}", new_branch);
-            branch::branch(Some(new_branch.clone()), target_branch.clone(), false, None, false);
+            branch::branch(Some(new_branch.clone()), target_branch.clone(), false, None, false, false);
             let _ = switch_to(new_branch, true);
         }
         None => {
@@ -98,7 +96,7 @@ mod test {
 
         cmd::commit("init".to_string(), true);
         let test_branch_1 = "test_branch_1".to_string();
-        cmd::branch(Some(test_branch_1.clone()), None, false, None, false);
+        cmd::branch(Some(test_branch_1.clone()), None, false, None, false, false);
 
         /* test 1: NoClean */
         let test_file_1 = PathBuf::from("test_file_1");
@@ -110,7 +108,7 @@ mod test {
 
         cmd::commit("add file 1".to_string(), true);
         let test_branch_2 = "test_branch_2".to_string();
-        cmd::branch(Some(test_branch_2.clone()), None, false, None, false); // branch2: test_file_1 exists
+        cmd::branch(Some(test_branch_2.clone()), None, false, None, false, false); // branch2: test_file_1 exists
 
         /* test 2: InvalidBranch */
         let result = switch_to("invalid_branch".to_string(), false);
@@ -1,8 +1,7 @@
-use base64::Engine;
-use flate2::{read::GzDecoder, write::GzEncoder, Compression};
-use std::io::{Read, Write};
-
-use crate::{models::Hash, utils::store};
+use crate::{
+    models::{Hash, ObjectType},
+    utils::store,
+};
 
 /**Blob<br>
 git中最基本的对象，他储存一份文件的内容，并使用hash作为标识符。
@@ -10,51 +9,37 @@ git中最基本的对象，他储存一份文件的内容，并使用hash作为
 #[derive(Debug, Clone)]
 pub struct Blob {
     hash: Hash,
-    data: String,
+    data: Vec<u8>,
 }
 
 impl Blob {
     /// 从源文件新建blob对象，并直接保存到/objects/中
-    pub fn new(data: String) -> Blob {
+    pub fn new(data: Vec<u8>) -> Blob {
         let mut blob = Blob { hash: "".to_string(), data };
         blob.save();
         blob
     }
 
     /// 从源文件新建blob对象，但不保存到/objects/中
-    pub fn dry_new(data: String) -> Blob {
+    pub fn dry_new(data: Vec<u8>) -> Blob {
         let mut blob = Blob { hash: "".to_string(), data };
         let s = store::Store::new();
-        let hash: String = s.dry_save(&Blob::encode(blob.data.clone()));
+        let hash: String = s.dry_save(ObjectType::Blob, &blob.data);
         blob.hash = hash;
         blob
     }
 
-    fn encode(data: String) -> String {
-        let mut cmopress_encoder = GzEncoder::new(Vec::new(), Compression::default());
-        cmopress_encoder.write_all(data.as_bytes()).unwrap();
-        let compressed_data = cmopress_encoder.finish().unwrap();
-        base64::engine::general_purpose::STANDARD_NO_PAD.encode(compressed_data)
-    }
-    fn decode(encoded: String) -> String {
-        let compressed_data = base64::engine::general_purpose::STANDARD_NO_PAD.decode(encoded).unwrap();
-        let mut decompress_decoder = GzDecoder::new(&compressed_data[..]);
-        let mut data = String::new();
-        decompress_decoder.read_to_string(&mut data).unwrap();
-        data
-    }
-
     pub fn load(hash: &String) -> Blob {
         let s = store::Store::new();
-        let encoded_data = s.load(hash);
-        let data = Blob::decode(encoded_data);
+        let (obj_type, data) = s.load(hash);
+        assert_eq!(obj_type, ObjectType::Blob, "object {} 不是blob", hash);
         Blob { hash: hash.clone(), data }
     }
 
     /// 写入文件
     pub fn save(&mut self) -> Hash {
         let s = store::Store::new();
-        let hash: String = s.save(&Blob::encode(self.data.clone()));
+        let hash: String = s.save(ObjectType::Blob, &self.data);
         self.hash = hash;
         self.hash.clone()
     }
@@ -63,7 +48,8 @@ impl Blob {
         self.hash.clone()
     }
 
-    pub fn get_content(&self) -> String {
+    /// 文件的原始字节
+    pub fn get_content(&self) -> Vec<u8> {
         self.data.clone()
     }
 }
@@ -80,6 +66,19 @@ mod test {
 
         let blob2 = super::Blob::load(&blob.hash);
         assert_eq!(blob2.get_hash(), blob.get_hash());
-        assert_eq!(blob2.data, test_data);
+        assert_eq!(blob2.data, test_data.as_bytes());
+    }
+
+    #[test]
+    fn test_binary_save_and_load() {
+        test::setup_with_clean_mit();
+        // PNG文件头 + 非法UTF-8序列 + Latin-1字符
+        let test_data: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xff, 0xfe, 0xe9, 0x80];
+        assert!(String::from_utf8(test_data.clone()).is_err());
+        let blob = super::Blob::new(test_data.clone());
+
+        let blob2 = super::Blob::load(&blob.hash);
+        assert_eq!(blob2.get_hash(), blob.get_hash());
+        assert_eq!(blob2.get_content(), test_data);
     }
 }
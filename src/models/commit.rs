@@ -1,8 +1,8 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use serde::{Deserialize, Serialize};
 
-use crate::utils::{store, util};
+use crate::utils::{config::ObjectFormat, store, util};
 
 use super::*;
 /*Commit
@@ -29,7 +29,10 @@ impl Commit {
     pub fn get_date(&self) -> String {
         util::format_time(&self.date)
     }
-    #[cfg(test)]
+    /// 提交时间，用于按时间排序
+    pub fn get_time(&self) -> SystemTime {
+        self.date
+    }
     pub fn get_tree_hash(&self) -> String {
         self.tree.clone()
     }
@@ -45,9 +48,13 @@ impl Commit {
     pub fn get_author(&self) -> String {
         self.author.clone()
     }
-    // pub fn get_committer(&self) -> String {
-    //     self.committer.clone()
-    // }
+    pub fn get_committer(&self) -> String {
+        self.committer.clone()
+    }
+    /// 作者、提交者的邮箱：仓库中只记录了名字，与Git格式中的`<{name}@mit>`相同
+    pub fn email(name: &str) -> String {
+        format!("{}@mit", name)
+    }
 
     pub fn new(index: &Index, parent: Vec<Hash>, message: String) -> Commit {
         let mut tree = Tree::new(index);
@@ -65,20 +72,100 @@ impl Commit {
 
     pub fn load(hash: &String) -> Commit {
         let s = store::Store::new();
-        let commit_data = s.load(hash);
-        let mut commit: Commit = serde_json::from_str(&commit_data).unwrap();
+        let (obj_type, commit_data) = s.load(hash);
+        assert_eq!(obj_type, ObjectType::Commit, "object {} 不是commit", hash);
+        let mut commit = Commit::decode(&commit_data, s.format())
+            .unwrap_or_else(|| panic!("储存库疑似损坏，commit格式错误: {}", hash));
         commit.hash = hash.clone();
         commit
     }
 
+    /// 与[Commit::load]相同，但object不存在、不是commit或格式错误时返回None，用于检查仓库
+    pub fn try_load(hash: &String) -> Option<Commit> {
+        let s = store::Store::new();
+        match s.try_load(hash)? {
+            (ObjectType::Commit, commit_data) => {
+                let mut commit = Commit::decode(&commit_data, s.format())?;
+                commit.hash = hash.clone();
+                Some(commit)
+            }
+            _ => None,
+        }
+    }
+
     pub fn save(&mut self) -> String {
         // unimplemented!()
         let s = store::Store::new();
-        let commit_data = serde_json::to_string_pretty(&self).unwrap();
-        let hash = s.save(&commit_data);
+        let commit_data = self.encode(s.format());
+        let hash = s.save(ObjectType::Commit, &commit_data);
         self.hash = hash.clone();
         hash
     }
+
+    /** 按仓库格式编码
+    Git格式：
+    ```text
+    tree {hash}
+    parent {hash}
+    author {name} <{name}@mit> {timestamp} +0000
+    committer {name} <{name}@mit> {timestamp} +0000
+
+    {message}
+    ```
+     */
+    fn encode(&self, format: ObjectFormat) -> Vec<u8> {
+        match format {
+            ObjectFormat::Mit => serde_json::to_string_pretty(&self).unwrap().into_bytes(),
+            ObjectFormat::Git => {
+                let timestamp = self.date.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+                let signature = |name: &String| format!("{} <{}> {} +0000", name, Commit::email(name), timestamp);
+                let mut data = format!("tree {}\n", self.tree);
+                for parent in &self.parent {
+                    data += &format!("parent {}\n", parent);
+                }
+                data += &format!("author {}\n", signature(&self.author));
+                data += &format!("committer {}\n", signature(&self.committer));
+                data += &format!("\n{}\n", self.message);
+                data.into_bytes()
+            }
+        }
+    }
+
+    fn decode(data: &[u8], format: ObjectFormat) -> Option<Commit> {
+        match format {
+            ObjectFormat::Mit => serde_json::from_slice(data).ok(),
+            ObjectFormat::Git => {
+                let data = String::from_utf8_lossy(data);
+                let (headers, message) = data.split_once("\n\n").unwrap_or((&data, ""));
+                let mut commit = Commit {
+                    hash: "".to_string(),
+                    date: SystemTime::UNIX_EPOCH,
+                    author: "".to_string(),
+                    committer: "".to_string(),
+                    message: message.strip_suffix('\n').unwrap_or(message).to_string(),
+                    parent: Vec::new(),
+                    tree: "".to_string(),
+                };
+                // 解析 "{name} <{email}> {timestamp} {timezone}"
+                let parse_signature = |value: &str| {
+                    let name = value.split(" <").next().unwrap_or_default().to_string();
+                    let timestamp = value.rsplit(' ').nth(1).and_then(|t| t.parse::<u64>().ok()).unwrap_or(0);
+                    (name, SystemTime::UNIX_EPOCH + Duration::from_secs(timestamp))
+                };
+                for line in headers.lines() {
+                    let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+                    match key {
+                        "tree" => commit.tree = value.to_string(),
+                        "parent" => commit.parent.push(value.to_string()),
+                        "author" => (commit.author, commit.date) = parse_signature(value),
+                        "committer" => commit.committer = parse_signature(value).0,
+                        _ => {} // 忽略未知头部，如gpgsig
+                    }
+                }
+                (!commit.tree.is_empty()).then_some(commit)
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -102,4 +189,28 @@ mod test {
         assert_eq!(commit.parent.len(), 2);
         println!("{:?}", commit)
     }
+
+    #[test]
+    fn test_git_format() {
+        test::setup_with_clean_mit_git_format();
+        let mut commit = super::Commit {
+            hash: "".to_string(),
+            date: std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1700000000),
+            author: "mit".to_string(),
+            committer: "mit".to_string(),
+            message: "init".to_string(),
+            parent: vec![],
+            tree: "cf63d874473ba4a7588d6a4ec394320971d5b36b".to_string(),
+        };
+        // 与 `git commit-tree` 的结果一致
+        let hash = commit.save();
+        assert_eq!(hash, "371cdf5c574ed9b31a6910429f1f79177be11a73");
+
+        let loaded = super::Commit::load(&hash);
+        assert_eq!(loaded.get_message(), "init");
+        assert_eq!(loaded.get_author(), "mit");
+        assert_eq!(loaded.date, commit.date);
+        assert_eq!(loaded.tree, commit.tree);
+        assert!(loaded.get_parent_hash().is_empty());
+    }
 }
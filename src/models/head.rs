@@ -1,4 +1,7 @@
-use crate::{models::Hash, utils::util};
+use crate::{
+    models::{reflog, Hash},
+    utils::util,
+};
 
 pub enum Head {
     Detached(String),
@@ -16,14 +19,22 @@ pub fn current_head() -> Head {
         Head::Detached(head_content)
     }
 }
-pub fn update_branch(branch_name: &String, commit_hash: &String) {
-    // 更新分支head
+/** 更新分支head，并记录reflog（message）；分支为当前分支时同时记录HEAD的reflog */
+pub fn update_branch(branch_name: &String, commit_hash: &String, message: &str) {
+    let old = get_branch_head(branch_name);
     let mut branch = util::get_storage_path().unwrap();
     branch.push("refs");
     branch.push("heads");
     branch.push(branch_name);
     std::fs::write(&branch, commit_hash)
         .unwrap_or_else(|_| panic!("无法写入branch in {:?} with {}", branch, commit_hash));
+    if old == *commit_hash {
+        return;
+    }
+    reflog::append(&reflog::branch_ref(branch_name), &old, commit_hash, message);
+    if matches!(current_head(), Head::Branch(current) if current == *branch_name) {
+        reflog::append(reflog::HEAD, &old, commit_hash, message);
+    }
 }
 
 pub fn get_branch_head(branch_name: &String) -> String {
@@ -45,6 +56,7 @@ pub fn delete_branch(branch_name: &String) {
     branch.push(branch_name);
     if branch.exists() {
         std::fs::remove_file(branch).expect("无法删除branch");
+        reflog::remove(&reflog::branch_ref(branch_name));
     } else {
         panic!("branch file not exist");
     }
@@ -62,21 +74,50 @@ pub fn current_head_commit() -> String {
     }
 }
 
-/** 将当前的head指向commit_hash，根据当前的head类型，更新不同的文件 */
-pub fn update_head_commit(commit_hash: &String) {
+/** 将当前的head指向commit_hash，根据当前的head类型，更新不同的文件；message记录在reflog中 */
+pub fn update_head_commit(commit_hash: &String, message: &str) {
     let head = current_head();
     match head {
         Head::Branch(branch_name) => {
-            update_branch(&branch_name, commit_hash);
+            update_branch(&branch_name, commit_hash, message);
         }
-        Head::Detached(_) => {
+        Head::Detached(old) => {
             let mut head = util::get_storage_path().unwrap();
             head.push("HEAD");
             std::fs::write(head, commit_hash).expect("无法写入HEAD");
+            reflog::append(reflog::HEAD, &old, commit_hash, message);
         }
     }
 }
 
+/// HEAD的名字：分支名或commit hash，用于reflog中的`checkout: moving from <from> to <to>`
+fn current_head_name() -> String {
+    match current_head() {
+        Head::Branch(branch_name) => branch_name,
+        Head::Detached(commit_hash) => commit_hash,
+    }
+}
+
+/** 读取tag指向的object（.mit/refs/tags/<name>），不存在时返回None */
+pub fn get_tag(tag_name: &str) -> Option<Hash> {
+    let mut tag = util::get_storage_path().unwrap();
+    tag.push("refs");
+    tag.push("tags");
+    tag.push(tag_name);
+    std::fs::read_to_string(tag).ok().map(|hash| hash.trim().to_string())
+}
+
+/** 列出所有tag（.mit/refs/tags） */
+pub fn list_tags() -> Vec<String> {
+    let mut tag_dir = util::get_storage_path().unwrap();
+    tag_dir.push("refs");
+    tag_dir.push("tags");
+    match std::fs::read_dir(tag_dir) {
+        Ok(entries) => entries.map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
 /** 列出本地的branch */
 pub fn list_local_branches() -> Vec<String> {
     let mut branches = Vec::new();
@@ -96,18 +137,67 @@ pub fn list_local_branches() -> Vec<String> {
 
 /** 切换head到branch */
 pub fn change_head_to_branch(branch_name: &String) {
+    let (from, old) = (current_head_name(), current_head_commit());
     let mut head = util::get_storage_path().unwrap();
     head.push("HEAD");
     let branch_head = get_branch_head(branch_name);
     std::fs::write(head, format!("ref: refs/heads/{}", branch_name)).expect("无法写入HEAD");
-    update_head_commit(&branch_head);
+    update_head_commit(&branch_head, "");
+    let message = format!("checkout: moving from {} to {}", from, branch_name);
+    reflog::append(reflog::HEAD, &old, &branch_head, &message);
 }
 
 /** 切换head到非branchcommit */
 pub fn change_head_to_commit(commit_hash: &String) {
+    let (from, old) = (current_head_name(), current_head_commit());
     let mut head = util::get_storage_path().unwrap();
     head.push("HEAD");
     std::fs::write(head, commit_hash).expect("无法写入HEAD");
+    let message = format!("checkout: moving from {} to {}", from, commit_hash);
+    reflog::append(reflog::HEAD, &old, commit_hash, &message);
+}
+
+/** 合并状态：合并因冲突中断时，记录在.mit中，直到`mit commit`、`merge --continue`或`merge --abort`
+ * <br>MERGE_HEAD：被合并的commit；MERGE_MSG：合并的提交信息；ORIG_HEAD：合并前的HEAD，用于`merge --abort`
+ */
+pub const MERGE_HEAD: &str = "MERGE_HEAD";
+pub const MERGE_MSG: &str = "MERGE_MSG";
+pub const ORIG_HEAD: &str = "ORIG_HEAD";
+
+/** 读取.mit下的特殊文件，如MERGE_HEAD，不存在时返回None */
+pub fn read_special(name: &str) -> Option<String> {
+    let path = util::get_storage_path().unwrap().join(name);
+    std::fs::read_to_string(path).ok()
+}
+
+/** 写入.mit下的特殊文件，如MERGE_HEAD */
+pub fn write_special(name: &str, content: &str) {
+    let path = util::get_storage_path().unwrap().join(name);
+    std::fs::write(&path, content).unwrap_or_else(|_| panic!("无法写入{:?}", path));
+}
+
+/** 删除.mit下的特殊文件，不存在时忽略 */
+pub fn remove_special(name: &str) {
+    let path = util::get_storage_path().unwrap().join(name);
+    if path.exists() {
+        std::fs::remove_file(&path).unwrap_or_else(|_| panic!("无法删除{:?}", path));
+    }
+}
+
+/** 正在进行的合并中，被合并的commit */
+pub fn merge_head() -> Option<Hash> {
+    read_special(MERGE_HEAD).map(|hash| hash.trim().to_string())
+}
+
+/** 合并(或其他危险操作)之前的HEAD commit */
+pub fn orig_head() -> Option<Hash> {
+    read_special(ORIG_HEAD).map(|hash| hash.trim().to_string())
+}
+
+/** 结束合并状态：删除MERGE_HEAD & MERGE_MSG，保留ORIG_HEAD */
+pub fn clear_merge_state() {
+    remove_special(MERGE_HEAD);
+    remove_special(MERGE_MSG);
 }
 
 #[cfg(test)]
@@ -123,7 +213,7 @@ mod test {
         assert!(branch_head.is_empty());
 
         let commit_hash = "1234567890".to_string();
-        super::update_branch(&branch_name, &commit_hash);
+        super::update_branch(&branch_name, &commit_hash, "branch: Created from HEAD");
         let branch_head = super::get_branch_head(&branch_name);
         assert!(!branch_head.is_empty());
         assert!(branch_head == commit_hash);
@@ -134,8 +224,8 @@ mod test {
         test::setup_with_clean_mit();
         let branch_one = "test_branch".to_string() + &rand::random::<u32>().to_string();
         let branch_two = "test_branch".to_string() + &rand::random::<u32>().to_string();
-        head::update_branch(&branch_one, &"1234567890".to_string());
-        head::update_branch(&branch_two, &"1234567890".to_string());
+        head::update_branch(&branch_one, &"1234567890".to_string(), "branch: Created from HEAD");
+        head::update_branch(&branch_two, &"1234567890".to_string(), "branch: Created from HEAD");
 
         let branches = super::list_local_branches();
         assert!(branches.contains(&branch_one));
@@ -146,7 +236,7 @@ mod test {
     fn test_change_head_to_branch() {
         test::setup_with_clean_mit();
         let branch_name = "test_branch".to_string() + &rand::random::<u32>().to_string();
-        head::update_branch(&branch_name, &"1234567890".to_string());
+        head::update_branch(&branch_name, &"1234567890".to_string(), "branch: Created from HEAD");
         super::change_head_to_branch(&branch_name);
         assert!(
             match super::current_head() {
@@ -176,7 +266,7 @@ mod test {
         test::setup_with_clean_mit();
         let branch_name = "test_branch".to_string() + &rand::random::<u32>().to_string();
         let commit_hash = "1234567890".to_string();
-        super::update_branch(&branch_name, &commit_hash);
+        super::update_branch(&branch_name, &commit_hash, "branch: Created from HEAD");
         let branch_head = super::get_branch_head(&branch_name);
         assert!(!branch_head.is_empty());
         assert!(branch_head == commit_hash);
@@ -26,7 +26,7 @@ impl Default for FileMetaData {
             size: Default::default(),
             created_time: SystemTime::now(),  // 或者使用 UNIX_EPOCH
             modified_time: SystemTime::now(), // 或者使用 UNIX_EPOCH
-            mode: Default::default(),
+            mode: "100644".to_string(),       // 普通文件；从commit恢复到暂存区时使用，否则tree中的mode为空
         }
     }
 }
@@ -44,13 +44,49 @@ impl FileMetaData {
     }
 }
 
+/** 未解决冲突的文件在index中的版本，与Git相同
+<br>stage 1: base（公共祖先）；stage 2: ours（HEAD）；stage 3: theirs（被合并的分支）；不存在的版本为None
+ */
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ConflictEntry {
+    pub base: Option<Hash>,
+    pub ours: Option<Hash>,
+    pub theirs: Option<Hash>,
+}
+
+impl ConflictEntry {
+    /// 按stage编号(1~3)获取版本
+    pub fn stage(&self, stage: u8) -> Option<Hash> {
+        match stage {
+            1 => self.base.clone(),
+            2 => self.ours.clone(),
+            3 => self.theirs.clone(),
+            _ => None,
+        }
+    }
+}
+
+/// index文件的储存格式：conflicts为空时省略；兼容旧版只有entries的格式
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum IndexFile {
+    Staged {
+        entries: HashMap<PathBuf, FileMetaData>,
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        conflicts: HashMap<PathBuf, ConflictEntry>,
+    },
+    Legacy(HashMap<PathBuf, FileMetaData>),
+}
+
 /** Index
 注意：逻辑处理均为绝对路径，但是存储时为相对路径(to workdir)<br>
+与Git相同，正常的文件只有一个版本(stage 0，即entries)；未解决冲突的文件没有stage 0，而是在conflicts中保存stage 1~3<br>
 <a href="https://wolfsonliu.github.io/archive/2018/li-jie-git-index-wen-jian.html">理解 Git index 文件</a>
  */
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct Index {
     entries: HashMap<PathBuf, FileMetaData>,
+    conflicts: HashMap<PathBuf, ConflictEntry>,
     working_dir: PathBuf,
 }
 
@@ -65,7 +101,7 @@ impl Index {
     /// 单例模式，线程不安全，但是本程序默认单线程
     pub fn get_instance() -> &'static mut Index {
         static mut INSTANCE: Lazy<Index> = Lazy::new(Index::new); //延迟初始化，线程不安全
-        unsafe { &mut INSTANCE }
+        unsafe { &mut *std::ptr::addr_of_mut!(INSTANCE) }
     }
 
     /// 重置index，主要用于测试，防止单例模式的影响
@@ -80,18 +116,50 @@ impl Index {
         path.to_absolute()
     }
 
-    // 添加文件
+    // 添加文件，若文件存在冲突，则标记为已解决
     pub fn add(&mut self, mut path: PathBuf, data: FileMetaData) {
         path = Index::preprocess(&path);
+        self.conflicts.remove(&path);
         self.entries.insert(path, data);
     }
 
-    // 删除文件
+    // 删除文件，若文件存在冲突，则标记为已解决（以删除的方式）
     pub fn remove(&mut self, path: &Path) {
         let path = Index::preprocess(path);
+        self.conflicts.remove(&path);
         self.entries.remove(&path);
     }
 
+    /// 记录冲突：删除文件的stage 0，保存stage 1~3
+    pub fn add_conflict(&mut self, path: &Path, conflict: ConflictEntry) {
+        let path = Index::preprocess(path);
+        self.entries.remove(&path);
+        self.conflicts.insert(path, conflict);
+    }
+
+    pub fn get_conflict(&self, path: &Path) -> Option<ConflictEntry> {
+        let path = Index::preprocess(path);
+        self.conflicts.get(&path).cloned()
+    }
+
+    /// 未解决冲突的文件（绝对路径）
+    pub fn get_conflicted_files(&self) -> Vec<PathBuf> {
+        self.conflicts.keys().cloned().collect()
+    }
+
+    pub fn get_conflicts(&self) -> HashMap<PathBuf, ConflictEntry> {
+        self.conflicts.clone()
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        !self.conflicts.is_empty()
+    }
+
+    /// 丢弃所有冲突，用于放弃合并
+    pub fn clear_conflicts(&mut self) {
+        self.conflicts.clear();
+    }
+
     // 获取文件元数据
     pub fn get(&self, path: &Path) -> Option<FileMetaData> {
         let path = Index::preprocess(path);
@@ -112,9 +180,9 @@ impl Index {
         self.entries.contains_key(&path)
     }
 
-    /// 检查文件是否被跟踪, same as [Index::contains]
+    /// 检查文件是否被跟踪：存在于stage 0，或者存在未解决的冲突
     pub fn tracked(&self, path: &Path) -> bool {
-        self.contains(path)
+        self.contains(path) || self.conflicts.contains_key(&Index::preprocess(path))
     }
 
     // /// 与暂存区比较，获取工作区中被删除的文件
@@ -147,25 +215,34 @@ impl Index {
 
     pub fn update(&mut self, mut path: PathBuf, data: FileMetaData) {
         path = Index::preprocess(&path);
+        self.conflicts.remove(&path);
         self.entries.insert(path, data);
     }
 
     /// 从index文件加载数据
     fn load(&mut self) {
         self.entries.clear();
+        self.conflicts.clear();
         self.working_dir = util::get_working_dir().unwrap();
 
         let path = Index::get_path();
         if path.exists() {
             let json = fs::read_to_string(path).expect("无法读取index");
-            let relative_index: HashMap<PathBuf, FileMetaData> = serde_json::from_str(&json).expect("无法解析index");
-            self.entries = relative_index
+            let (entries, conflicts) = match serde_json::from_str(&json).expect("无法解析index") {
+                IndexFile::Staged { entries, conflicts } => (entries, conflicts),
+                IndexFile::Legacy(entries) => (entries, HashMap::new()),
+            };
+            self.entries = entries
                 .into_iter()
                 .map(|(path, value)| {
                     let abs_path = self.working_dir.join(path);
                     (abs_path, value)
                 })
                 .collect();
+            self.conflicts = conflicts
+                .into_iter()
+                .map(|(path, value)| (self.working_dir.join(path), value))
+                .collect();
         } else {
             // println!("index文件不存在，创建空index");
         }
@@ -189,7 +266,13 @@ impl Index {
                 (relative_path, value.clone())
             })
             .collect();
-        let json = serde_json::to_string_pretty(&relative_index).unwrap();
+        let relative_conflicts = self
+            .conflicts
+            .iter()
+            .map(|(path, value)| (util::get_relative_path_to_dir(path, &self.working_dir), value.clone()))
+            .collect();
+        let index_file = IndexFile::Staged { entries: relative_index, conflicts: relative_conflicts };
+        let json = serde_json::to_string_pretty(&index_file).unwrap();
 
         fs::write(Index::get_path(), json).expect("无法写入index");
     }
@@ -256,4 +339,43 @@ mod tests {
         index.save();
         assert!(!Index::new().is_empty()); //保存后，新读取的index不是空的
     }
+
+    #[test]
+    fn test_conflict_stages() {
+        test::setup_with_empty_workdir();
+        let index = Index::get_instance();
+        let path = PathBuf::from("a.txt");
+        test::ensure_file(&path, Some("a"));
+        index.add(path.clone(), FileMetaData::new(&Blob::new(util::read_workfile(&path)), &path));
+        let conflict = ConflictEntry {
+            base: None,
+            ours: Some("ours".into()),
+            theirs: Some("theirs".into()),
+        };
+        index.add_conflict(&path, conflict.clone());
+        assert!(!index.contains(&path)); // 没有stage 0
+        assert!(index.tracked(&path));
+        assert!(index.has_conflicts());
+        assert_eq!(conflict.stage(2), Some("ours".into()));
+        assert_eq!(conflict.stage(1), None);
+
+        index.save();
+        let loaded = Index::new();
+        assert_eq!(loaded.get_conflict(&path), Some(conflict));
+
+        // add标记为已解决
+        index.add(path.clone(), FileMetaData::new(&Blob::new(util::read_workfile(&path)), &path));
+        assert!(!index.has_conflicts());
+        assert!(index.contains(&path));
+    }
+
+    #[test]
+    fn test_load_legacy_format() {
+        test::setup_with_empty_workdir();
+        let entry = serde_json::to_string(&FileMetaData::default()).unwrap();
+        fs::write(Index::get_path(), format!("{{\"a.txt\": {}}}", entry)).unwrap();
+        let index = Index::new();
+        assert!(index.contains(Path::new("a.txt")));
+        assert!(!index.has_conflicts());
+    }
 }
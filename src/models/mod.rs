@@ -3,11 +3,13 @@ pub use blob::Blob;
 pub mod commit;
 pub use commit::Commit;
 pub mod index;
+pub use index::ConflictEntry;
 pub use index::FileMetaData;
 pub use index::Index;
 pub mod object;
-pub use object::Hash;
+pub use object::{Hash, ObjectType};
 pub mod head;
+pub mod reflog;
 pub mod tree;
 
 pub use tree::Tree;
@@ -1 +1,38 @@
 pub type Hash = String;
+
+/// object的类型，储存在每个object的头部 "{type} {size}\0"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectType {
+    Blob,
+    Tree,
+    Commit,
+    Invalid, // 不存在或无法识别
+}
+
+impl ObjectType {
+    /// 头部中的类型名
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectType::Blob => "blob",
+            ObjectType::Tree => "tree",
+            ObjectType::Commit => "commit",
+            ObjectType::Invalid => "invalid",
+        }
+    }
+
+    /// 从头部中的类型名解析，无法识别时返回Invalid
+    pub fn parse(name: &str) -> ObjectType {
+        match name {
+            "blob" => ObjectType::Blob,
+            "tree" => ObjectType::Tree,
+            "commit" => ObjectType::Commit,
+            _ => ObjectType::Invalid,
+        }
+    }
+}
+
+impl std::fmt::Display for ObjectType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
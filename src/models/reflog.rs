@@ -0,0 +1,126 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{models::Hash, utils::util};
+
+/*Reflog
+* 记录HEAD和每个分支的移动历史，储存在.mit/logs/HEAD与.mit/logs/refs/heads/<branch>中，格式与Git相同：
+* `<old> <new> <name> <<email>> <timestamp> <timezone>\t<message>`，每次移动追加一行
+* 用于解析`@{n}`（n次移动之前的位置）与`@{-n}`（之前第n次切换离开的分支）
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReflogEntry {
+    pub old: Hash, // 移动前的commit，新建时为空
+    pub new: Hash,
+    pub message: String,
+}
+
+/// HEAD的reflog名
+pub const HEAD: &str = "HEAD";
+
+/// 分支的reflog名
+pub fn branch_ref(branch_name: &str) -> String {
+    format!("refs/heads/{}", branch_name)
+}
+
+fn log_path(ref_name: &str) -> PathBuf {
+    util::get_storage_path().unwrap().join("logs").join(ref_name)
+}
+
+/// 追加一条记录
+pub fn append(ref_name: &str, old: &Hash, new: &Hash, message: &str) {
+    let path = log_path(ref_name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap_or_else(|_| panic!("无法创建{:?}", path.parent()));
+    let null = "0".repeat(new.len());
+    let old = if old.is_empty() { &null } else { old };
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let line = format!("{} {} mit <mit> {} +0000\t{}\n", old, new, time, message.lines().next().unwrap_or(""));
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .unwrap_or_else(|_| panic!("无法写入{:?}", path));
+    file.write_all(line.as_bytes()).unwrap_or_else(|_| panic!("无法写入{:?}", path));
+}
+
+/// 读取所有记录，从旧到新；不存在时为空
+pub fn read(ref_name: &str) -> Vec<ReflogEntry> {
+    let content = fs::read_to_string(log_path(ref_name)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let (info, message) = line.split_once('\t').unwrap_or((line, ""));
+            let mut parts = info.split(' ');
+            let (old, new) = (parts.next()?, parts.next()?);
+            let old = if old.chars().all(|c| c == '0') { "" } else { old };
+            Some(ReflogEntry {
+                old: old.to_string(),
+                new: new.to_string(),
+                message: message.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 所有reflog（HEAD & 每个分支）的记录
+pub fn read_all() -> Vec<ReflogEntry> {
+    fn collect(dir: &Path, ref_names: &mut Vec<String>, root: &Path) {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                collect(&path, ref_names, root);
+            } else if let Ok(name) = path.strip_prefix(root) {
+                ref_names.push(name.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+    let root = util::get_storage_path().unwrap().join("logs");
+    let mut ref_names = Vec::new();
+    collect(&root, &mut ref_names, &root);
+    ref_names.iter().flat_map(|ref_name| read(ref_name)).collect()
+}
+
+/// 删除reflog（删除分支时），不存在时忽略
+pub fn remove(ref_name: &str) {
+    let path = log_path(ref_name);
+    if path.exists() {
+        fs::remove_file(&path).unwrap_or_else(|_| panic!("无法删除{:?}", path));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::test;
+
+    #[test]
+    fn test_reflog() {
+        test::setup_with_clean_mit();
+        assert!(read(HEAD).is_empty());
+        let (one, two) = ("1".repeat(40), "2".repeat(40));
+        append(HEAD, &"".to_string(), &one, "commit (initial): first\nbody");
+        append(HEAD, &one, &two, "checkout: moving from master to dev");
+        let entries = read(HEAD);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0],
+            ReflogEntry {
+                old: "".into(),
+                new: one.clone(),
+                message: "commit (initial): first".into()
+            }
+        );
+        assert_eq!(entries[1].old, one);
+        assert_eq!(entries[1].message, "checkout: moving from master to dev");
+
+        append(&branch_ref("dev"), &"".to_string(), &two, "branch: Created from HEAD");
+        assert_eq!(read(&branch_ref("dev")).len(), 1);
+        assert_eq!(read_all().len(), 3);
+        remove(&branch_ref("dev"));
+        assert!(read(&branch_ref("dev")).is_empty());
+    }
+}
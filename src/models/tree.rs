@@ -1,11 +1,13 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::utils::PathExt;
-use crate::utils::{store, util};
+use crate::utils::{config::ObjectFormat, store, PathExt};
 
-use super::{Hash, Index};
+use super::{Hash, Index, ObjectType};
 /*Tree
 * Tree是一个版本中所有文件的集合。从根目录还是，每个目录是一个Tree，每个文件是一个Blob。Tree之间互相嵌套表示文件的层级关系。
 * 每一个Tree对象也是对应到git储存仓库的一个文件，其内容是一个或多个TreeEntry。
@@ -17,6 +19,9 @@ pub struct TreeEntry {
     pub name: String,               // file name
 }
 
+/// 目录(tree)的filemode
+const TREE_MODE: &str = "40000";
+
 /// 相对路径(to workdir)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tree {
@@ -30,7 +35,7 @@ fn store_path_to_tree(index: &Index, current_root: PathBuf) -> Tree {
     let get_blob_entry = |path: &PathBuf| {
         let mete = index.get(path).unwrap().clone();
         let filename = path.file_name().unwrap().to_str().unwrap().to_string();
-        
+
         TreeEntry {
             filemode: (String::from("blob"), mete.mode),
             object_hash: mete.hash,
@@ -39,12 +44,14 @@ fn store_path_to_tree(index: &Index, current_root: PathBuf) -> Tree {
     };
     let mut tree = Tree { hash: "".to_string(), entries: Vec::new() };
     let mut processed_path: HashSet<String> = HashSet::new();
-    let path_entries: Vec<PathBuf> = index
+    let mut path_entries: Vec<PathBuf> = index
         .get_tracked_files()
         .iter()
         .map(|file| file.to_relative_workdir())
         .filter(|path| path.starts_with(&current_root))
         .collect();
+    // 暂存区为HashMap，按路径排序保证entry顺序固定：内容相同的目录得到相同的hash
+    path_entries.sort();
     for path in path_entries.iter() {
         // 判断是不是直接在根目录下
         let in_path = path.parent().unwrap() == current_root;
@@ -71,9 +78,8 @@ fn store_path_to_tree(index: &Index, current_root: PathBuf) -> Tree {
             processed_path.insert(process_path.to_string());
 
             let sub_tree = store_path_to_tree(index, current_root.clone().join(process_path));
-            let mode = util::get_file_mode(&util::get_working_dir().unwrap().join(process_path));
             tree.entries.push(TreeEntry {
-                filemode: (String::from("tree"), mode),
+                filemode: (String::from("tree"), TREE_MODE.to_string()), // 目录可能已不存在于工作区，不能从文件系统获取
                 object_hash: sub_tree.get_hash(),
                 name: process_path.to_string(),
             });
@@ -88,26 +94,116 @@ impl Tree {
         self.hash.clone()
     }
 
+    /// 根据暂存区创建Tree，暂存区存在未解决的冲突时无法创建
     pub fn new(index: &Index) -> Tree {
+        assert!(!index.has_conflicts(), "暂存区存在未解决的冲突，无法写入tree");
         store_path_to_tree(index, "".into())
     }
 
     pub fn load(hash: &String) -> Tree {
         let s = store::Store::new();
-        let tree_data = s.load(hash);
-        let mut tree: Tree = serde_json::from_str(&tree_data).unwrap();
+        let (obj_type, tree_data) = s.load(hash);
+        assert_eq!(obj_type, ObjectType::Tree, "object {} 不是tree", hash);
+        let mut tree =
+            Tree::decode(&tree_data, s.format()).unwrap_or_else(|| panic!("储存库疑似损坏，tree格式错误: {}", hash));
         tree.hash = hash.clone();
         tree
     }
 
+    /// 与[Tree::load]相同，但object不存在、不是tree或格式错误时返回None，用于检查仓库
+    pub fn try_load(hash: &String) -> Option<Tree> {
+        let s = store::Store::new();
+        match s.try_load(hash)? {
+            (ObjectType::Tree, tree_data) => {
+                let mut tree = Tree::decode(&tree_data, s.format())?;
+                tree.hash = hash.clone();
+                Some(tree)
+            }
+            _ => None,
+        }
+    }
+
     pub fn save(&mut self) -> Hash {
         let s = store::Store::new();
-        let tree_data = serde_json::to_string_pretty(&self).unwrap();
-        let hash = s.save(&tree_data);
+        let tree_data = self.encode(s.format());
+        let hash = s.save(ObjectType::Tree, &tree_data);
         self.hash = hash.clone();
         hash
     }
 
+    /** 按仓库格式编码
+    Git格式：每个entry为 "{mode} {name}\0{20字节hash}"，entry按名称排序（目录按"name/"参与排序）
+     */
+    fn encode(&self, format: ObjectFormat) -> Vec<u8> {
+        match format {
+            ObjectFormat::Mit => serde_json::to_string_pretty(&self).unwrap().into_bytes(),
+            ObjectFormat::Git => {
+                let mut entries = self.entries.iter().collect::<Vec<_>>();
+                let sort_key = |entry: &TreeEntry| {
+                    let mut key = entry.name.clone().into_bytes();
+                    if entry.filemode.0 == "tree" {
+                        key.push(b'/');
+                    }
+                    key
+                };
+                entries.sort_by_key(|entry| sort_key(entry));
+                let mut data = Vec::new();
+                for entry in entries {
+                    data.extend_from_slice(format!("{} {}\0", entry.filemode.1, entry.name).as_bytes());
+                    data.extend(hex::decode(&entry.object_hash).expect("非法的object hash"));
+                }
+                data
+            }
+        }
+    }
+
+    fn decode(data: &[u8], format: ObjectFormat) -> Option<Tree> {
+        match format {
+            ObjectFormat::Mit => serde_json::from_slice(data).ok(),
+            ObjectFormat::Git => {
+                let mut tree = Tree { hash: "".to_string(), entries: Vec::new() };
+                let mut rest = data;
+                while !rest.is_empty() {
+                    let nul = rest.iter().position(|&b| b == 0)?;
+                    let header = String::from_utf8(rest[..nul].to_vec()).ok()?;
+                    let (mode, name) = header.split_once(' ')?;
+                    let object_hash = hex::encode(rest.get(nul + 1..nul + 21)?);
+                    // git会将目录mode写为 "40000"，而 ls-tree 显示为 "040000"
+                    let obj_type = if mode.trim_start_matches('0') == TREE_MODE {
+                        "tree"
+                    } else {
+                        "blob"
+                    };
+                    tree.entries.push(TreeEntry {
+                        filemode: (obj_type.to_string(), mode.to_string()),
+                        object_hash,
+                        name: name.to_string(),
+                    });
+                    rest = &rest[nul + 21..];
+                }
+                Some(tree)
+            }
+        }
+    }
+
+    /// 按相对路径(to workdir)查找文件或目录，逐级进入子tree
+    pub fn find(&self, path: &Path) -> Option<TreeEntry> {
+        let mut tree = self.clone();
+        let mut components = path.components().peekable();
+        while let Some(component) = components.next() {
+            let name = component.as_os_str().to_str()?;
+            let entry = tree.entries.iter().find(|entry| entry.name == name)?.clone();
+            if components.peek().is_none() {
+                return Some(entry);
+            }
+            if entry.filemode.0 != "tree" {
+                return None;
+            }
+            tree = Tree::load(&entry.object_hash);
+        }
+        None
+    }
+
     ///注：相对路径(to workdir)
     pub fn get_recursive_blobs(&self) -> Vec<(PathBuf, Hash)> {
         //TODO 返回HashMap
@@ -199,4 +295,57 @@ mod test {
         assert!(blobs.contains(&(PathBuf::from(test_files[0]), test_blobs[0].get_hash())));
         assert!(blobs.contains(&(PathBuf::from(test_files[1]), test_blobs[1].get_hash())));
     }
+
+    #[test]
+    fn test_find() {
+        test::setup_with_clean_mit();
+        let index = Index::get_instance();
+        for test_file in ["b.txt", "mit_src/a.txt"] {
+            let test_file = PathBuf::from(test_file);
+            test::ensure_file(&test_file, None);
+            index.add(test_file.clone(), FileMetaData::new(&Blob::new(util::read_workfile(&test_file)), &test_file));
+        }
+
+        let tree = Tree::new(index);
+        assert_eq!(tree.find(&PathBuf::from("mit_src")).unwrap().filemode.0, "tree");
+        assert_eq!(tree.find(&PathBuf::from("mit_src/a.txt")).unwrap().name, "a.txt");
+        assert!(tree.find(&PathBuf::from("b.txt/a.txt")).is_none());
+        assert!(tree.find(&PathBuf::from("c.txt")).is_none());
+        assert!(tree.find(&PathBuf::from("")).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_new_with_conflicts() {
+        test::setup_with_clean_mit();
+        let index = Index::get_instance();
+        let conflict = ConflictEntry {
+            base: None,
+            ours: Some("ours".into()),
+            theirs: Some("theirs".into()),
+        };
+        index.add_conflict(&PathBuf::from("a.txt"), conflict);
+        let _ = Tree::new(index);
+    }
+
+    #[test]
+    fn test_git_format() {
+        test::setup_with_clean_mit_git_format();
+        let index = Index::get_instance();
+        for (test_file, content) in [("a.txt", "hello world"), ("d/b.txt", "x")] {
+            let test_file = PathBuf::from(test_file);
+            test::ensure_file(&test_file, Some(content));
+            index.add(test_file.clone(), FileMetaData::new(&Blob::new(util::read_workfile(&test_file)), &test_file));
+        }
+
+        // 与 `git write-tree` 的结果一致
+        let tree = Tree::new(index);
+        assert_eq!(tree.get_hash(), "cf63d874473ba4a7588d6a4ec394320971d5b36b");
+
+        let loaded_tree = Tree::load(&tree.get_hash());
+        let blobs = loaded_tree.get_recursive_blobs();
+        assert_eq!(blobs.len(), 2);
+        assert!(blobs.contains(&(PathBuf::from("a.txt"), "95d09f2b10159347eece71399a7e2e907ea3df4f".to_string())));
+        assert!(loaded_tree.entries.iter().any(|e| e.name == "d" && e.filemode.0 == "tree"));
+    }
 }
@@ -0,0 +1,54 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::util;
+
+/// object的储存格式，在`mit init`时为每个仓库选定，之后不可更改
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ObjectFormat {
+    /// mit原生格式：tree & commit 使用JSON编码，objects/ 下扁平存放
+    #[default]
+    Mit,
+    /// 与Git兼容：二进制tree、文本commit，按hash前两位分目录存放(objects/ab/cdef...)
+    Git,
+}
+
+impl ObjectFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectFormat::Mit => "mit",
+            ObjectFormat::Git => "git",
+        }
+    }
+}
+
+/// 获取.mit/config文件路径
+pub fn get_config_path() -> PathBuf {
+    util::get_storage_path().unwrap().join("config")
+}
+
+/** 写入仓库配置，格式与git config(INI)兼容，以便git工具可以直接读取<br>
+mit自己的配置放在[mit]节中，git会忽略未知的节
+ */
+pub fn write_config(mit_dir: &Path, format: ObjectFormat) -> io::Result<()> {
+    let config = format!(
+        "[core]\n\trepositoryformatversion = 0\n\tfilemode = true\n\tbare = false\n[mit]\n\tobjectformat = {}\n",
+        format.as_str()
+    );
+    fs::write(mit_dir.join("config"), config)
+}
+
+/// 读取仓库的object格式，没有config的旧仓库视为[ObjectFormat::Mit]
+pub fn object_format() -> ObjectFormat {
+    let config = fs::read_to_string(get_config_path()).unwrap_or_default();
+    for line in config.lines() {
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "objectformat" && value.trim() == ObjectFormat::Git.as_str() {
+                return ObjectFormat::Git;
+            }
+        }
+    }
+    ObjectFormat::Mit
+}
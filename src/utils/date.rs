@@ -0,0 +1,110 @@
+use std::time::{Duration, SystemTime};
+
+use chrono::{NaiveDate, NaiveDateTime};
+
+/*Date
+* `--since`、`--until`等参数中的日期，时区为UTC（与log显示的时间相同）：
+* - 绝对时间：`2024-01-31`、`2024-01-31 12:30`、`2024-01-31 12:30:45`（也可以用`T`分隔日期和时间）
+* - 相对时间：`now`、`yesterday`、`<n> <unit> ago`，单词之间也可以用`.`分隔，如`2.weeks.ago`
+*   <br>unit为second、minute、hour、day、week、month（30天）、year（365天），可以带复数`s`
+*/
+
+/// 解析日期，相对时间以当前时间为基准
+pub fn parse(value: &str) -> Result<SystemTime, String> {
+    parse_from(value, SystemTime::now())
+}
+
+fn parse_from(value: &str, now: SystemTime) -> Result<SystemTime, String> {
+    let invalid = || format!("无法解析的日期: '{}'", value);
+    let value = value.trim();
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(datetime.and_utc().into());
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc().into());
+    }
+
+    let words = value.split(|c: char| c == '.' || c.is_whitespace()).filter(|word| !word.is_empty());
+    let words = words.map(str::to_lowercase).collect::<Vec<_>>();
+    let seconds = match words.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["now"] => 0,
+        ["yesterday"] => 86400,
+        [n, unit, "ago"] => {
+            let n = n.parse::<u64>().map_err(|_| invalid())?;
+            let unit = match unit.strip_suffix('s').unwrap_or(unit) {
+                "second" | "sec" => 1,
+                "minute" | "min" => 60,
+                "hour" => 3600,
+                "day" => 86400,
+                "week" => 7 * 86400,
+                "month" => 30 * 86400,
+                "year" => 365 * 86400,
+                _ => return Err(invalid()),
+            };
+            n.checked_mul(unit).ok_or_else(invalid)?
+        }
+        _ => return Err(invalid()),
+    };
+    now.checked_sub(Duration::from_secs(seconds)).ok_or_else(invalid)
+}
+
+/** 相对于当前时间的描述，如`5 minutes ago`，与Git的规则类似：
+<br>90秒内按秒、90分钟内按分钟、36小时内按小时、14天内按天、10周内按周、1年内按月，之后按年
+ */
+pub fn relative(time: SystemTime) -> String {
+    let seconds = SystemTime::now().duration_since(time).unwrap_or_default().as_secs();
+    let (n, unit) = match seconds {
+        0..=89 => (seconds, "second"),
+        90..=5399 => ((seconds + 30) / 60, "minute"),
+        5400..=129_599 => ((seconds + 1800) / 3600, "hour"),
+        _ => {
+            let days = (seconds + 43200) / 86400;
+            match days {
+                0..=13 => (days, "day"),
+                14..=69 => ((days + 3) / 7, "week"),
+                70..=364 => ((days + 15) / 30, "month"),
+                _ => ((days + 183) / 365, "year"),
+            }
+        }
+    };
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let ago = |seconds: u64| Ok(now - Duration::from_secs(seconds));
+        assert_eq!(parse_from("now", now), ago(0));
+        assert_eq!(parse_from("yesterday", now), ago(86400));
+        assert_eq!(parse_from("2 weeks ago", now), ago(14 * 86400));
+        assert_eq!(parse_from("2.weeks.ago", now), ago(14 * 86400));
+        assert_eq!(parse_from("1 hour ago", now), ago(3600));
+        assert_eq!(parse_from(" 3 Months ago ", now), ago(90 * 86400));
+
+        let epoch = |seconds: u64| Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+        assert_eq!(parse_from("2023-11-14", now), epoch(1_699_920_000));
+        assert_eq!(parse_from("2023-11-14 22:13:20", now), epoch(1_700_000_000));
+        assert_eq!(parse_from("2023-11-14T22:13:20", now), epoch(1_700_000_000));
+        assert_eq!(parse_from("2023-11-14 22:13", now), epoch(1_699_999_980));
+
+        assert!(parse_from("2 fortnights ago", now).is_err());
+        assert!(parse_from("x days ago", now).is_err());
+        assert!(parse_from("last tuesday", now).is_err());
+    }
+
+    #[test]
+    fn test_relative() {
+        let ago = |seconds: u64| relative(SystemTime::now() - Duration::from_secs(seconds));
+        assert_eq!(ago(1), "1 second ago");
+        assert_eq!(ago(600), "10 minutes ago");
+        assert_eq!(ago(3 * 86400), "3 days ago");
+        assert_eq!(ago(21 * 86400), "3 weeks ago");
+        assert_eq!(ago(800 * 86400), "2 years ago");
+    }
+}
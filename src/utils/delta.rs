@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+/** delta编码，与Git packfile中的delta格式一致
+ * <br>头部：varint(base大小) + varint(target大小)
+ * <br>指令：copy(0x80 | flags, offset, size) 从base复制；insert(1..=127, 字面量) 直接插入
+ */
+const BLOCK_SIZE: usize = 16; // 匹配的最小块大小
+const MAX_INSERT: usize = 0x7f;
+const MAX_COPY: usize = 0xffffff;
+
+fn write_varint(out: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_varint(data: &[u8], mut pos: usize) -> Option<(usize, usize)> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(pos)?;
+        pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Some((value, pos));
+        }
+    }
+}
+
+fn flush_insert(out: &mut Vec<u8>, insert: &mut Vec<u8>) {
+    for chunk in insert.chunks(MAX_INSERT) {
+        out.push(chunk.len() as u8);
+        out.extend_from_slice(chunk);
+    }
+    insert.clear();
+}
+
+fn emit_copy(out: &mut Vec<u8>, mut offset: usize, mut len: usize) {
+    while len > 0 {
+        let size = len.min(MAX_COPY);
+        let mut op = 0x80u8;
+        let mut args = Vec::new();
+        for i in 0..4 {
+            let byte = (offset >> (8 * i)) as u8;
+            if byte != 0 {
+                op |= 1 << i;
+                args.push(byte);
+            }
+        }
+        for i in 0..3 {
+            let byte = (size >> (8 * i)) as u8;
+            if byte != 0 {
+                op |= 0x10 << i;
+                args.push(byte);
+            }
+        }
+        out.push(op);
+        out.extend(args);
+        offset += size;
+        len -= size;
+    }
+}
+
+/// 计算将base转换为target的delta
+pub fn encode(base: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_varint(&mut out, base.len());
+    write_varint(&mut out, target.len());
+
+    // 索引base中的所有块（按块对齐），target中任意位置都可以匹配
+    let mut blocks: HashMap<&[u8], usize> = HashMap::new();
+    for start in (0..base.len().saturating_sub(BLOCK_SIZE - 1)).step_by(BLOCK_SIZE) {
+        blocks.entry(&base[start..start + BLOCK_SIZE]).or_insert(start);
+    }
+
+    let mut insert = Vec::new();
+    let mut pos = 0;
+    while pos < target.len() {
+        let found = target.get(pos..pos + BLOCK_SIZE).and_then(|block| blocks.get(block));
+        if let Some(&start) = found {
+            // 向后扩展匹配
+            let mut len = BLOCK_SIZE;
+            while start + len < base.len() && pos + len < target.len() && base[start + len] == target[pos + len] {
+                len += 1;
+            }
+            // 向前扩展匹配，吃掉待插入的字节
+            let (mut start, mut pos_start) = (start, pos);
+            while start > 0 && !insert.is_empty() && base[start - 1] == target[pos_start - 1] {
+                start -= 1;
+                pos_start -= 1;
+                len += 1;
+                insert.pop();
+            }
+            flush_insert(&mut out, &mut insert);
+            emit_copy(&mut out, start, len);
+            pos = pos_start + len;
+        } else {
+            insert.push(target[pos]);
+            pos += 1;
+        }
+    }
+    flush_insert(&mut out, &mut insert);
+    out
+}
+
+/// 将delta应用到base上，delta非法时返回None
+pub fn apply(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let (base_size, pos) = read_varint(delta, 0)?;
+    let (target_size, mut pos) = read_varint(delta, pos)?;
+    if base_size != base.len() {
+        return None;
+    }
+    let mut target = Vec::with_capacity(target_size);
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 != 0 {
+            // copy
+            let mut offset = 0usize;
+            for i in 0..4 {
+                if op & (1 << i) != 0 {
+                    offset |= (*delta.get(pos)? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            let mut size = 0usize;
+            for i in 0..3 {
+                if op & (0x10 << i) != 0 {
+                    size |= (*delta.get(pos)? as usize) << (8 * i);
+                    pos += 1;
+                }
+            }
+            if size == 0 {
+                size = 0x10000;
+            }
+            target.extend_from_slice(base.get(offset..offset.checked_add(size)?)?);
+        } else if op != 0 {
+            // insert
+            let size = op as usize;
+            target.extend_from_slice(delta.get(pos..pos + size)?);
+            pos += size;
+        } else {
+            return None; // 保留指令
+        }
+    }
+    (target.len() == target_size).then_some(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_and_apply() {
+        let base = "fn main() {\n    println!(\"hello world\");\n}\n".repeat(20).into_bytes();
+        let mut target = base.clone();
+        target.splice(100..110, b"inserted text".iter().cloned());
+        target.extend_from_slice(b"// tail\n");
+
+        let delta = encode(&base, &target);
+        assert!(delta.len() < target.len() / 4, "delta过大: {}", delta.len());
+        assert_eq!(apply(&base, &delta).unwrap(), target);
+    }
+
+    #[test]
+    fn test_unrelated_and_empty() {
+        let base = b"short".to_vec();
+        let target = (0..=255u8).cycle().take(1000).collect::<Vec<u8>>();
+        assert_eq!(apply(&base, &encode(&base, &target)).unwrap(), target);
+        assert_eq!(apply(&target, &encode(&target, &[])).unwrap(), Vec::<u8>::new());
+        assert_eq!(apply(&[], &encode(&[], &base)).unwrap(), base);
+        assert!(apply(b"other base", &encode(&base, &target)).is_none()); // base大小不符
+    }
+}
@@ -0,0 +1,966 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// 行级差分算法，`diff`、`merge`等命令通过`--diff-algorithm`选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DiffAlgorithm {
+    /// 最短编辑脚本，与Git默认相同
+    #[default]
+    Myers,
+    /// 以双方都只出现一次的行为锚点，代码移动、重构时更易读
+    Patience,
+    /// patience的扩展：以出现次数最少的行为锚点，重复行较多时表现更好
+    Histogram,
+}
+
+impl DiffAlgorithm {
+    /** 计算a与b的公共子序列，返回匹配的元素下标(a中下标, b中下标)，按顺序排列
+    <br>与Git相同，最后移动修改块使结果更易读（见[compact]）
+     */
+    pub fn matches<T: Eq + Hash + AsRef<[u8]>>(&self, a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+        let matches = match self {
+            DiffAlgorithm::Myers => myers(a, b),
+            DiffAlgorithm::Patience => patience(a, b),
+            DiffAlgorithm::Histogram => histogram(a, b),
+        };
+        compact(a, b, &matches)
+    }
+}
+
+/// 将a、b中的元素编号，相等的元素编号相同，之后只需比较编号
+fn classify<T: Eq + Hash>(a: &[T], b: &[T]) -> (Vec<usize>, Vec<usize>) {
+    let mut classes = HashMap::new();
+    let mut class = |item| {
+        let next = classes.len();
+        *classes.entry(item).or_insert(next)
+    };
+    let a = a.iter().map(&mut class).collect();
+    let b = b.iter().map(&mut class).collect();
+    (a, b)
+}
+
+/// 由每一行是否被修改得到匹配的下标：两边未修改的行按顺序一一对应；changed末尾多一个false
+fn unchanged_pairs(changed_a: &[bool], changed_b: &[bool]) -> Vec<(usize, usize)> {
+    let a = (0..changed_a.len() - 1).filter(|&i| !changed_a[i]);
+    let b = (0..changed_b.len() - 1).filter(|&j| !changed_b[j]);
+    a.zip(b).collect()
+}
+
+/// 在另一方中出现次数达到行数的平方根（最多为该值）的行视为出现多次，夹在没有出现的行之间时不参与搜索
+const MAX_EQUAL_LIMIT: usize = 1024;
+/// 判断出现多次的行是否丢弃时，向前后查看的行数
+const SIMILAR_SCAN_WINDOW: usize = 100;
+/// 长度超过该值的对角线（连续匹配）视为找到了较好的路径
+const SNAKE_COUNT: isize = 20;
+/// 编辑距离超过该值、且找到了较好的路径时，直接在该路径处拆分
+const HEURISTIC_MIN_COST: isize = 256;
+/// 编辑距离上限（对角线数的平方根）的最小值，超过上限后在走得最远的位置拆分，结果不再保证最短
+const MAX_COST_MIN: isize = 256;
+
+/// 约等于2倍的平方根，只需要量级
+fn bogo_sqrt(mut n: usize) -> usize {
+    let mut root = 1;
+    while n > 0 {
+        root <<= 1;
+        n >>= 2;
+    }
+    root
+}
+
+/// 一行在另一方中出现的次数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Occurrence {
+    Never,
+    Few,
+    Many,
+}
+
+/** 去掉不参与搜索的行：在另一方中没有出现的行一定是修改；出现多次的行被大量没有出现的行包围时，也视为修改
+<br>lines为去掉公共前缀、后缀后的部分，counts为另一方中每种行的数量；返回保留的行在lines中的下标
+ */
+fn discard(lines: &[usize], counts: &HashMap<usize, usize>, total: usize, changed: &mut [bool]) -> Vec<usize> {
+    let limit = bogo_sqrt(total).min(MAX_EQUAL_LIMIT);
+    let occurrences = lines
+        .iter()
+        .map(|line| match counts.get(line).copied().unwrap_or(0) {
+            0 => Occurrence::Never,
+            n if n >= limit => Occurrence::Many,
+            _ => Occurrence::Few,
+        })
+        .collect::<Vec<_>>();
+    // 前后连续的(没有出现的行数, 出现多次的行数+1)
+    let run = |range: &mut dyn Iterator<Item = usize>| {
+        let (mut never, mut many) = (0, 1);
+        for k in range {
+            match occurrences[k] {
+                Occurrence::Never => never += 1,
+                Occurrence::Many => many += 1,
+                Occurrence::Few => break,
+            }
+        }
+        (never, many)
+    };
+    let surrounded = |i: usize| {
+        let (start, end) = (i.saturating_sub(SIMILAR_SCAN_WINDOW), (i + SIMILAR_SCAN_WINDOW).min(lines.len() - 1));
+        let (never_before, many_before) = run(&mut (start..i).rev());
+        if never_before == 0 {
+            return false;
+        }
+        let (never_after, many_after) = run(&mut (i + 1..=end));
+        if never_after == 0 {
+            return false;
+        }
+        let (never, many) = (never_before + never_after, many_before + many_after);
+        many * 4 < many + never // 出现多次的行不到1/4
+    };
+
+    let mut kept = Vec::new();
+    for (i, occurrence) in occurrences.iter().enumerate() {
+        match occurrence {
+            Occurrence::Few => kept.push(i),
+            Occurrence::Many if !surrounded(i) => kept.push(i),
+            _ => changed[i] = true,
+        }
+    }
+    kept
+}
+
+/** Myers差分算法，与Git（xdiff）的实现相同：
+<br>先去掉公共的前缀和后缀，以及不参与搜索的行（见[discard]），再从两端同时搜索，在相遇的位置拆分后递归
+<br>编辑距离较大时使用启发式规则提前拆分，结果不一定是最短的编辑脚本
+<br><a href="http://www.xmailserver.org/diff2.pdf">An O(ND) Difference Algorithm and Its Variations</a>
+ */
+pub fn myers<T: Eq + Hash>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    let (a, b) = classify(a, b);
+    let (prefix, suffix) = common_affix(&a, &b);
+    let (mut changed_a, mut changed_b) = (vec![false; a.len() + 1], vec![false; b.len() + 1]);
+    let (a_end, b_end) = (a.len() - suffix, b.len() - suffix);
+    let count = |lines: &[usize]| {
+        let mut counts = HashMap::new();
+        lines.iter().for_each(|&line| *counts.entry(line).or_insert(0) += 1);
+        counts
+    };
+    let kept_a = discard(&a[prefix..a_end], &count(&b), a.len(), &mut changed_a[prefix..a_end]);
+    let kept_b = discard(&b[prefix..b_end], &count(&a), b.len(), &mut changed_b[prefix..b_end]);
+    let (kept_a, kept_b) = (
+        kept_a.into_iter().map(|i| i + prefix).collect::<Vec<_>>(),
+        kept_b.into_iter().map(|j| j + prefix).collect::<Vec<_>>(),
+    );
+
+    let mut search = MyersSearch::new(kept_a.iter().map(|&i| a[i]).collect(), kept_b.iter().map(|&j| b[j]).collect());
+    let (x_changed, y_changed) = search.run();
+    x_changed.into_iter().for_each(|x| changed_a[kept_a[x]] = true);
+    y_changed.into_iter().for_each(|y| changed_b[kept_b[y]] = true);
+    unchanged_pairs(&changed_a, &changed_b)
+}
+
+/// Myers搜索的状态：forward、backward为正向、反向搜索在每条对角线（x - y）上到达的x
+struct MyersSearch {
+    a: Vec<usize>,
+    b: Vec<usize>,
+    forward: Vec<isize>,
+    backward: Vec<isize>,
+    offset: isize, // 对角线在forward、backward中的下标偏移
+    max_cost: isize,
+}
+
+/// 拆分的位置(x, y)，以及拆分后的前后两部分是否需要最短的编辑脚本
+type Split = (isize, isize, bool, bool);
+
+impl MyersSearch {
+    fn new(a: Vec<usize>, b: Vec<usize>) -> MyersSearch {
+        let diagonals = a.len() + b.len() + 3;
+        MyersSearch {
+            offset: b.len() as isize + 1,
+            max_cost: (bogo_sqrt(diagonals) as isize).max(MAX_COST_MIN),
+            forward: vec![0; diagonals],
+            backward: vec![0; diagonals],
+            a,
+            b,
+        }
+    }
+
+    /// 返回a、b中被修改的行的下标（用栈代替递归）
+    fn run(&mut self) -> (Vec<usize>, Vec<usize>) {
+        let (mut a_changed, mut b_changed) = (Vec::new(), Vec::new());
+        let mut boxes = vec![(0, self.a.len() as isize, 0, self.b.len() as isize, false)];
+        while let Some((mut x_lo, mut x_hi, mut y_lo, mut y_hi, need_min)) = boxes.pop() {
+            let (a, b) = (&self.a, &self.b);
+            while x_lo < x_hi && y_lo < y_hi && a[x_lo as usize] == b[y_lo as usize] {
+                (x_lo, y_lo) = (x_lo + 1, y_lo + 1);
+            }
+            while x_lo < x_hi && y_lo < y_hi && a[x_hi as usize - 1] == b[y_hi as usize - 1] {
+                (x_hi, y_hi) = (x_hi - 1, y_hi - 1);
+            }
+            if x_lo == x_hi {
+                b_changed.extend(y_lo as usize..y_hi as usize);
+            } else if y_lo == y_hi {
+                a_changed.extend(x_lo as usize..x_hi as usize);
+            } else {
+                let (x, y, min_lo, min_hi) = self.split((x_lo, x_hi, y_lo, y_hi), need_min);
+                boxes.push((x, x_hi, y, y_hi, min_hi));
+                boxes.push((x_lo, x, y_lo, y, min_lo));
+            }
+        }
+        (a_changed, b_changed)
+    }
+
+    /// 从两端同时搜索，返回相遇（或按启发式规则选择）的位置
+    fn split(&mut self, (x_lo, x_hi, y_lo, y_hi): (isize, isize, isize, isize), need_min: bool) -> Split {
+        let (a, b) = (&self.a, &self.b);
+        let (forward, backward) = (&mut self.forward, &mut self.backward);
+        let index = |k: isize| (k + self.offset) as usize;
+        let (k_min, k_max) = (x_lo - y_hi, x_hi - y_lo);
+        let (f_mid, b_mid) = (x_lo - y_lo, x_hi - y_hi);
+        let odd = (f_mid - b_mid) & 1 != 0;
+        let (mut f_min, mut f_max, mut b_min, mut b_max) = (f_mid, f_mid, b_mid, b_mid);
+        forward[index(f_mid)] = x_lo;
+        backward[index(b_mid)] = x_hi;
+
+        let mut cost = 0;
+        loop {
+            cost += 1;
+            let mut got_snake = false;
+
+            // 对角线的范围扩大1，超出边界时反向收缩；范围之外的值作为哨兵
+            if f_min > k_min {
+                f_min -= 1;
+                forward[index(f_min - 1)] = -1;
+            } else {
+                f_min += 1;
+            }
+            if f_max < k_max {
+                f_max += 1;
+                forward[index(f_max + 1)] = -1;
+            } else {
+                f_max -= 1;
+            }
+            for k in (f_min..=f_max).rev().step_by(2) {
+                let mut x = match forward[index(k - 1)] >= forward[index(k + 1)] {
+                    true => forward[index(k - 1)] + 1,
+                    false => forward[index(k + 1)],
+                };
+                let start = x;
+                let mut y = x - k;
+                while x < x_hi && y < y_hi && a[x as usize] == b[y as usize] {
+                    (x, y) = (x + 1, y + 1);
+                }
+                got_snake |= x - start > SNAKE_COUNT;
+                forward[index(k)] = x;
+                if odd && b_min <= k && k <= b_max && backward[index(k)] <= x {
+                    return (x, y, true, true);
+                }
+            }
+
+            if b_min > k_min {
+                b_min -= 1;
+                backward[index(b_min - 1)] = isize::MAX;
+            } else {
+                b_min += 1;
+            }
+            if b_max < k_max {
+                b_max += 1;
+                backward[index(b_max + 1)] = isize::MAX;
+            } else {
+                b_max -= 1;
+            }
+            for k in (b_min..=b_max).rev().step_by(2) {
+                let mut x = match backward[index(k - 1)] < backward[index(k + 1)] {
+                    true => backward[index(k - 1)],
+                    false => backward[index(k + 1)] - 1,
+                };
+                let start = x;
+                let mut y = x - k;
+                while x > x_lo && y > y_lo && a[x as usize - 1] == b[y as usize - 1] {
+                    (x, y) = (x - 1, y - 1);
+                }
+                got_snake |= start - x > SNAKE_COUNT;
+                backward[index(k)] = x;
+                if !odd && f_min <= k && k <= f_max && x <= forward[index(k)] {
+                    return (x, y, true, true);
+                }
+            }
+
+            if need_min {
+                continue;
+            }
+
+            // 编辑距离较大时，若某条路径走得足够远（离起点远、离中间的对角线近）且末尾有足够长的匹配，在该处拆分
+            if got_snake && cost > HEURISTIC_MIN_COST {
+                let mut best = None;
+                for k in (f_min..=f_max).rev().step_by(2) {
+                    let (x, y) = (forward[index(k)], forward[index(k)] - k);
+                    let value = (x - x_lo) + (y - y_lo) - (k - f_mid).abs();
+                    if value > 4 * cost
+                        && best.is_none_or(|(best, _, _)| value > best)
+                        && (x_lo + SNAKE_COUNT..x_hi).contains(&x)
+                        && (y_lo + SNAKE_COUNT..y_hi).contains(&y)
+                        && (1..=SNAKE_COUNT).all(|n| a[(x - n) as usize] == b[(y - n) as usize])
+                    {
+                        best = Some((value, x, y));
+                    }
+                }
+                if let Some((_, x, y)) = best {
+                    return (x, y, true, false);
+                }
+
+                for k in (b_min..=b_max).rev().step_by(2) {
+                    let (x, y) = (backward[index(k)], backward[index(k)] - k);
+                    let value = (x_hi - x) + (y_hi - y) - (k - b_mid).abs();
+                    if value > 4 * cost
+                        && best.is_none_or(|(best, _, _)| value > best)
+                        && x_lo < x
+                        && x <= x_hi - SNAKE_COUNT
+                        && y_lo < y
+                        && y <= y_hi - SNAKE_COUNT
+                        && (0..SNAKE_COUNT).all(|n| a[(x + n) as usize] == b[(y + n) as usize])
+                    {
+                        best = Some((value, x, y));
+                    }
+                }
+                if let Some((_, x, y)) = best {
+                    return (x, y, false, true);
+                }
+            }
+
+            // 编辑距离超过上限，在正向或反向走得最远（x + y）的位置拆分
+            if cost >= self.max_cost {
+                let (mut f_best, mut f_best_x) = (-1, -1);
+                for k in (f_min..=f_max).rev().step_by(2) {
+                    let mut x = forward[index(k)].min(x_hi);
+                    let mut y = x - k;
+                    if y_hi < y {
+                        (x, y) = (y_hi + k, y_hi);
+                    }
+                    if f_best < x + y {
+                        (f_best, f_best_x) = (x + y, x);
+                    }
+                }
+                let (mut b_best, mut b_best_x) = (isize::MAX, isize::MAX);
+                for k in (b_min..=b_max).rev().step_by(2) {
+                    let mut x = backward[index(k)].max(x_lo);
+                    let mut y = x - k;
+                    if y < y_lo {
+                        (x, y) = (y_lo + k, y_lo);
+                    }
+                    if x + y < b_best {
+                        (b_best, b_best_x) = (x + y, x);
+                    }
+                }
+                return match (x_hi + y_hi) - b_best < f_best - (x_lo + y_lo) {
+                    true => (f_best_x, f_best - f_best_x, true, false),
+                    false => (b_best_x, b_best - b_best_x, false, true),
+                };
+            }
+        }
+    }
+}
+
+/// 区域内公共的前缀与后缀长度
+fn common_affix<T: Eq>(a: &[T], b: &[T]) -> (usize, usize) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    (prefix, suffix)
+}
+
+/// 待处理的区域：a[a_lo..a_hi]与b[b_lo..b_hi]
+type Region = (usize, usize, usize, usize);
+
+/** 递归拆分区域的通用框架（用栈代替递归）：由split在区域中寻找匹配（按顺序的下标，相对于区域），
+再对匹配之间的区域继续拆分
+<br>split返回空时，区域中没有匹配；返回None时，该区域回退到Myers
+ */
+fn split_regions<T, F>(a: &[T], b: &[T], split: F) -> Vec<(usize, usize)>
+where
+    T: Eq + Hash,
+    F: Fn(&[T], &[T]) -> Option<Vec<(usize, usize)>>,
+{
+    let mut matches = Vec::new();
+    let mut regions: Vec<Region> = vec![(0, a.len(), 0, b.len())];
+    while let Some((a_lo, a_hi, b_lo, b_hi)) = regions.pop() {
+        if a_lo == a_hi || b_lo == b_hi {
+            continue;
+        }
+        let (a_mid, b_mid) = (&a[a_lo..a_hi], &b[b_lo..b_hi]);
+        match split(a_mid, b_mid) {
+            Some(found) if !found.is_empty() => {
+                let (mut x, mut y) = (a_lo, b_lo);
+                for (i, j) in found {
+                    let (i, j) = (a_lo + i, b_lo + j);
+                    regions.push((x, i, y, j));
+                    matches.push((i, j));
+                    (x, y) = (i + 1, j + 1);
+                }
+                regions.push((x, a_hi, y, b_hi));
+            }
+            Some(_) => {}
+            None => matches.extend(myers(a_mid, b_mid).into_iter().map(|(i, j)| (a_lo + i, b_lo + j))),
+        }
+    }
+    matches.sort_unstable();
+    matches
+}
+
+/// 最长递增子序列（patience sorting），输入按第一维有序，返回第二维递增的最长子序列
+fn longest_increasing(pairs: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut piles: Vec<usize> = Vec::new(); // 每堆顶部元素在pairs中的下标
+    let mut prev = vec![None; pairs.len()];
+    for (k, &(_, j)) in pairs.iter().enumerate() {
+        let pile = piles.partition_point(|&top| pairs[top].1 < j);
+        prev[k] = pile.checked_sub(1).map(|p| piles[p]);
+        if pile == piles.len() {
+            piles.push(k);
+        } else {
+            piles[pile] = k;
+        }
+    }
+    let mut result = Vec::new();
+    let mut cur = piles.last().copied();
+    while let Some(k) = cur {
+        result.push(pairs[k]);
+        cur = prev[k];
+    }
+    result.reverse();
+    result
+}
+
+/** Patience差分算法：以在a、b中都只出现一次的行为锚点，取其中最长的有序序列，
+锚点向前、后扩展连续相同的行后，再对之间的区域递归；没有这样的行时回退到Myers
+<br><a href="https://bramcohen.livejournal.com/73318.html">Patience Diff Advantages</a>
+ */
+pub fn patience<T: Eq + Hash>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    split_regions(a, b, |a, b| {
+        // 行 -> (在a中出现的次数, 在a中的下标, 在b中出现的次数, 在b中的下标)
+        let mut counts: HashMap<&T, (usize, usize, usize, usize)> = HashMap::new();
+        for (i, line) in a.iter().enumerate() {
+            let entry = counts.entry(line).or_default();
+            (entry.0, entry.1) = (entry.0 + 1, i);
+        }
+        let mut common = false;
+        for (j, line) in b.iter().enumerate() {
+            if let Some(entry) = counts.get_mut(line) {
+                (entry.2, entry.3) = (entry.2 + 1, j);
+                common = true;
+            }
+        }
+        if !common {
+            return Some(Vec::new());
+        }
+        let mut unique = counts
+            .into_values()
+            .filter(|&(a_count, _, b_count, _)| a_count == 1 && b_count == 1)
+            .map(|(_, i, _, j)| (i, j))
+            .collect::<Vec<_>>();
+        unique.sort_unstable();
+        let anchors = longest_increasing(&unique);
+        if anchors.is_empty() {
+            return None;
+        }
+
+        // 先从锚点向前扩展，再从上一个锚点向后扩展
+        let mut matches = Vec::new();
+        let (mut x, mut y) = (0, 0);
+        for anchor in anchors.into_iter().map(Some).chain([None]) {
+            let (mut next_x, mut next_y) = anchor.unwrap_or((a.len(), b.len()));
+            let mut before = Vec::new();
+            if anchor.is_some() {
+                while next_x > x && next_y > y && a[next_x - 1] == b[next_y - 1] {
+                    (next_x, next_y) = (next_x - 1, next_y - 1);
+                    before.push((next_x, next_y));
+                }
+            }
+            while x < next_x && y < next_y && a[x] == b[y] {
+                matches.push((x, y));
+                (x, y) = (x + 1, y + 1);
+            }
+            matches.extend(before.into_iter().rev());
+            if let Some((i, j)) = anchor {
+                matches.push((i, j));
+                (x, y) = (i + 1, j + 1);
+            }
+        }
+        Some(matches)
+    })
+}
+
+/// histogram中，出现次数超过该值的行不作为锚点（与Git相同）
+const MAX_CHAIN_LENGTH: usize = 64;
+
+/** Histogram差分算法（Git的默认推荐算法之一）：在a中统计每行出现的次数，
+以出现次数最少的公共行为锚点，并向两边扩展为最长的连续匹配，再对两侧的区域递归
+<br>所有公共行都出现太多次时，回退到Myers
+ */
+pub fn histogram<T: Eq + Hash>(a: &[T], b: &[T]) -> Vec<(usize, usize)> {
+    split_regions(a, b, |a, b| {
+        let mut positions: HashMap<&T, Vec<usize>> = HashMap::new();
+        for (i, line) in a.iter().enumerate() {
+            positions.entry(line).or_default().push(i);
+        }
+        let count = |i: usize| positions[&a[i]].len();
+        // (a中起始, b中起始, 长度)，以及其中出现次数的最小值
+        let mut best: Option<(usize, usize, usize)> = None;
+        let mut best_count = MAX_CHAIN_LENGTH + 1;
+        let mut common = false;
+        let mut j = 0;
+        while j < b.len() {
+            let mut next_j = j + 1;
+            let candidates = positions.get(&b[j]).map_or(&[][..], Vec::as_slice);
+            common |= !candidates.is_empty();
+            let mut k = match candidates.len() > best_count {
+                true => candidates.len(), // 出现次数比当前的锚点多
+                false => 0,
+            };
+            while k < candidates.len() {
+                let (mut start_a, mut start_b) = (candidates[k], j);
+                let (mut end_a, mut end_b) = (start_a + 1, j + 1);
+                let mut min_count = candidates.len();
+                while start_a > 0 && start_b > 0 && a[start_a - 1] == b[start_b - 1] {
+                    (start_a, start_b) = (start_a - 1, start_b - 1);
+                    if min_count > 1 {
+                        min_count = min_count.min(count(start_a));
+                    }
+                }
+                while end_a < a.len() && end_b < b.len() && a[end_a] == b[end_b] {
+                    if min_count > 1 {
+                        min_count = min_count.min(count(end_a));
+                    }
+                    (end_a, end_b) = (end_a + 1, end_b + 1);
+                }
+                next_j = next_j.max(end_b);
+                // 更长，或出现次数更少
+                let len = end_a - start_a;
+                if best.map_or(1, |(_, _, len)| len) < len || min_count < best_count {
+                    best = Some((start_a, start_b, len));
+                    best_count = min_count;
+                }
+                // 跳过已包含在这次匹配中的位置
+                k += candidates[k..].partition_point(|&i| i < end_a);
+            }
+            j = next_j;
+        }
+        if common && best_count > MAX_CHAIN_LENGTH {
+            return None; // 所有公共行都出现太多次
+        }
+        Some(best.map_or_else(Vec::new, |(i, j, len)| (0..len).map(|k| (i + k, j + k)).collect()))
+    })
+}
+
+/// 缩进启发式最多向上移动修改块的行数
+const MAX_SLIDING: usize = 100;
+/// 缩进的上限
+const MAX_INDENT: isize = 200;
+/// 连续空行的上限
+const MAX_BLANKS: isize = 20;
+
+/// 一处修改块：连续被修改的行[start, end)，可以为空（两处修改块之间）
+#[derive(Debug, Clone, Copy)]
+struct Group {
+    start: usize,
+    end: usize,
+}
+
+/** 修改块的遍历与移动，changed末尾多一个false
+<br>移动时被修改的行内容不变：向下移动时，块的第一行与块之后的一行相同，修改的可以是其中任意一行
+ */
+impl Group {
+    fn first(changed: &[bool]) -> Group {
+        Group { start: 0, end: changed.iter().take_while(|&&c| c).count() }
+    }
+
+    fn next(&mut self, changed: &[bool]) -> bool {
+        if self.end == changed.len() - 1 {
+            return false;
+        }
+        self.start = self.end + 1;
+        self.end = self.start;
+        while changed[self.end] {
+            self.end += 1;
+        }
+        true
+    }
+
+    fn previous(&mut self, changed: &[bool]) -> bool {
+        if self.start == 0 {
+            return false;
+        }
+        self.end = self.start - 1;
+        self.start = self.end;
+        while self.start > 0 && changed[self.start - 1] {
+            self.start -= 1;
+        }
+        true
+    }
+
+    /// 向下移动一行，与之后的修改块相连时合并
+    fn slide_down(&mut self, lines: &[usize], changed: &mut [bool]) -> bool {
+        if self.end == lines.len() || lines[self.start] != lines[self.end] {
+            return false;
+        }
+        changed[self.start] = false;
+        changed[self.end] = true;
+        (self.start, self.end) = (self.start + 1, self.end + 1);
+        while changed[self.end] {
+            self.end += 1;
+        }
+        true
+    }
+
+    /// 向上移动一行，与之前的修改块相连时合并
+    fn slide_up(&mut self, lines: &[usize], changed: &mut [bool]) -> bool {
+        if self.start == 0 || lines[self.start - 1] != lines[self.end - 1] {
+            return false;
+        }
+        (self.start, self.end) = (self.start - 1, self.end - 1);
+        changed[self.start] = true;
+        changed[self.end] = false;
+        while self.start > 0 && changed[self.start - 1] {
+            self.start -= 1;
+        }
+        true
+    }
+}
+
+/** 与Git相同，在不改变修改内容的前提下移动修改块（先a中删除的行，再b中新增的行）：
+<br>尽量与另一方的修改块对齐，否则按缩进启发式（见[split_score]）选择最易读的位置
+ */
+fn compact<T: Eq + Hash + AsRef<[u8]>>(a: &[T], b: &[T], matches: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let (a_classes, b_classes) = classify(a, b);
+    let (mut changed_a, mut changed_b) = (vec![true; a.len() + 1], vec![true; b.len() + 1]);
+    (changed_a[a.len()], changed_b[b.len()]) = (false, false);
+    for &(i, j) in matches {
+        (changed_a[i], changed_b[j]) = (false, false);
+    }
+    let lines = |items: &[T]| items.iter().map(|item| item.as_ref().to_vec()).collect::<Vec<_>>();
+    compact_changes(&lines(a), &a_classes, &mut changed_a, &changed_b);
+    compact_changes(&lines(b), &b_classes, &mut changed_b, &changed_a);
+    unchanged_pairs(&changed_a, &changed_b)
+}
+
+/// 移动一方的修改块，other为另一方的修改，两方的修改块一一对应（之间为相同的行）
+fn compact_changes(lines: &[Vec<u8>], classes: &[usize], changed: &mut [bool], other: &[bool]) {
+    let (mut group, mut other_group) = (Group::first(changed), Group::first(other));
+    loop {
+        if group.end > group.start {
+            // 先尽量向上、再尽量向下移动，与相邻的修改块合并后重复，直到大小不变
+            let (mut earliest_end, mut matching_end);
+            loop {
+                let size = group.end - group.start;
+                matching_end = None; // 与另一方的修改块对齐时，最靠下的位置
+                while group.slide_up(classes, changed) {
+                    other_group.previous(other);
+                }
+                earliest_end = group.end;
+                if other_group.end > other_group.start {
+                    matching_end = Some(group.end);
+                }
+                while group.slide_down(classes, changed) {
+                    other_group.next(other);
+                    if other_group.end > other_group.start {
+                        matching_end = Some(group.end);
+                    }
+                }
+                if size == group.end - group.start {
+                    break;
+                }
+            }
+
+            // 此时修改块在最靠下的位置，只需考虑向上移动
+            let size = group.end - group.start;
+            if group.end == earliest_end {
+                // 无法移动
+            } else if matching_end.is_some() {
+                while other_group.end == other_group.start {
+                    group.slide_up(classes, changed);
+                    other_group.previous(other);
+                }
+            } else {
+                let lowest = earliest_end
+                    .max((group.start).saturating_sub(1))
+                    .max(group.end.saturating_sub(MAX_SLIDING));
+                let mut best: Option<(usize, SplitScore)> = None;
+                for end in lowest..=group.end {
+                    let mut score = SplitScore::default();
+                    score.add(lines, end);
+                    score.add(lines, end - size);
+                    if best.as_ref().is_none_or(|(_, best)| score.compare(best) <= 0) {
+                        best = Some((end, score));
+                    }
+                }
+                let best_end = best.map_or(group.end, |(end, _)| end);
+                while group.end > best_end {
+                    group.slide_up(classes, changed);
+                    other_group.previous(other);
+                }
+            }
+        }
+        if !group.next(changed) {
+            break;
+        }
+        other_group.next(other);
+    }
+}
+
+/// 行的缩进（tab对齐到8列），只有空白字符时为-1
+fn indent(line: &[u8]) -> isize {
+    let mut indent = 0;
+    for &c in line {
+        match c {
+            b' ' => indent += 1,
+            b'\t' => indent += 8 - indent % 8,
+            b'\n' | b'\r' => {}
+            _ => return indent,
+        }
+        if indent >= MAX_INDENT {
+            return MAX_INDENT;
+        }
+    }
+    -1
+}
+
+/** 缩进启发式中拆分位置（修改块的开头或结尾，位于第split行之前）的评分，越小越好
+<br>倾向于在空行处、缩进较少的位置拆分，权重与Git相同
+ */
+#[derive(Debug, Clone, Default)]
+struct SplitScore {
+    effective_indent: isize,
+    penalty: isize,
+}
+
+impl SplitScore {
+    fn add(&mut self, lines: &[Vec<u8>], split: usize) {
+        let end_of_file = split >= lines.len();
+        let line_indent = lines.get(split).map_or(-1, |line| indent(line));
+        // 之前、之后连续的空行数，以及之前、之后第一个非空行的缩进
+        let (mut pre_blank, mut pre_indent) = (0, -1);
+        for line in lines[..split].iter().rev() {
+            pre_indent = indent(line);
+            if pre_indent != -1 {
+                break;
+            }
+            pre_blank += 1;
+            if pre_blank == MAX_BLANKS {
+                pre_indent = 0;
+                break;
+            }
+        }
+        let (mut post_blank, mut post_indent) = (0, -1);
+        for line in lines.iter().skip(split + 1) {
+            post_indent = indent(line);
+            if post_indent != -1 {
+                break;
+            }
+            post_blank += 1;
+            if post_blank == MAX_BLANKS {
+                post_indent = 0;
+                break;
+            }
+        }
+
+        if pre_indent == -1 && pre_blank == 0 {
+            self.penalty += 1; // 文件开头
+        }
+        if end_of_file {
+            self.penalty += 21;
+        }
+        let post_blank = if line_indent == -1 { 1 + post_blank } else { 0 };
+        let total_blank = pre_blank + post_blank;
+        self.penalty += -30 * total_blank + 6 * post_blank;
+
+        let indent = if line_indent != -1 { line_indent } else { post_indent };
+        let any_blanks = total_blank != 0;
+        self.effective_indent += indent;
+        if indent == -1 || pre_indent == -1 || indent == pre_indent {
+            // 不需要调整
+        } else if indent > pre_indent {
+            self.penalty += if any_blanks { 10 } else { -4 };
+        } else if post_indent != -1 && post_indent > indent {
+            self.penalty += if any_blanks { 17 } else { 24 };
+        } else {
+            self.penalty += if any_blanks { 17 } else { 23 };
+        }
+    }
+
+    /// 小于0时self更好
+    fn compare(&self, other: &SplitScore) -> isize {
+        let indents = self.effective_indent.cmp(&other.effective_indent) as isize;
+        60 * indents + (self.penalty - other.penalty)
+    }
+}
+
+/// 编辑脚本中的一步：保留（a中下标, b中下标）、删除a中的行、插入b中的行
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// 由匹配的元素下标生成完整的编辑脚本：两次匹配之间先删除后插入
+pub fn edit_script(matches: &[(usize, usize)], a_len: usize, b_len: usize) -> Vec<Edit> {
+    let mut script = Vec::new();
+    let (mut x, mut y) = (0, 0);
+    for &(mx, my) in matches.iter().chain([(a_len, b_len)].iter()) {
+        script.extend((x..mx).map(Edit::Delete));
+        script.extend((y..my).map(Edit::Insert));
+        if mx < a_len && my < b_len {
+            script.push(Edit::Equal(mx, my));
+        }
+        (x, y) = (mx + 1, my + 1);
+    }
+    script
+}
+
+/// unified diff中的一个hunk，start为0-based的起始行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub edits: Vec<Edit>,
+}
+
+/** 将编辑脚本划分为hunk：每处修改前后保留context行上下文，上下文重叠的修改合并为一个hunk
+ */
+pub fn hunks(script: &[Edit], context: usize) -> Vec<Hunk> {
+    let changes = script
+        .iter()
+        .enumerate()
+        .filter(|(_, edit)| !matches!(edit, Edit::Equal(..)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for i in changes {
+        let (lo, hi) = (i.saturating_sub(context), (i + 1 + context).min(script.len()));
+        match ranges.last_mut() {
+            Some(last) if lo <= last.1 => last.1 = hi,
+            _ => ranges.push((lo, hi)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(lo, hi)| {
+            let edits = script[lo..hi].to_vec();
+            // hunk之前的行数，即hunk在a、b中的起始位置
+            let old_start = script[..lo].iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+            let new_start = script[..lo].iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+            let old_len = edits.iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+            let new_len = edits.iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+            Hunk { old_start, old_len, new_start, new_len, edits }
+        })
+        .collect()
+}
+
+/// 按行切分，保留换行符（最后一行可能没有换行符）
+pub fn split_lines(content: &[u8]) -> Vec<&[u8]> {
+    content.split_inclusive(|&b| b == b'\n').collect()
+}
+
+/// 简单判断是否为二进制文件：前8000字节中包含\0（与Git相同）
+pub fn is_binary(content: &[u8]) -> bool {
+    content.iter().take(8000).any(|&b| b == 0)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use clap::ValueEnum;
+
+    use super::*;
+
+    fn check_lcs(a: &str, b: &str, expected_len: usize) {
+        let chars = |s: &str| s.chars().map(String::from).collect::<Vec<_>>();
+        let (a, b) = (chars(a), chars(b));
+        assert_eq!(myers(&a, &b).len(), expected_len);
+        // 所有算法的结果都必须是合法的公共子序列
+        for algorithm in DiffAlgorithm::value_variants() {
+            let matches = algorithm.matches(&a, &b);
+            assert!(matches.len() <= expected_len, "{:?} {:?}", algorithm, matches);
+            for window in matches.windows(2) {
+                assert!(window[0].0 < window[1].0 && window[0].1 < window[1].1);
+            }
+            for (x, y) in matches {
+                assert_eq!(a[x], b[y]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_myers() {
+        check_lcs("ABCABBA", "CBABAC", 4);
+        check_lcs("", "", 0);
+        check_lcs("abc", "", 0);
+        check_lcs("", "abc", 0);
+        check_lcs("abc", "abc", 3);
+        check_lcs("abcdef", "xyz", 0);
+        check_lcs("the quick brown fox", "the quick red fox", 15);
+    }
+
+    /// 按编辑脚本输出全部行（相当于无限上下文的unified diff的正文）
+    fn render(algorithm: DiffAlgorithm, old: &str, new: &str) -> String {
+        let (a, b) = (split_lines(old.as_bytes()), split_lines(new.as_bytes()));
+        let mut out = String::new();
+        for edit in edit_script(&algorithm.matches(&a, &b), a.len(), b.len()) {
+            let (prefix, line) = match edit {
+                Edit::Equal(i, _) => (' ', a[i]),
+                Edit::Delete(i) => ('-', a[i]),
+                Edit::Insert(j) => ('+', b[j]),
+            };
+            out.push(prefix);
+            out.push_str(std::str::from_utf8(line).unwrap());
+        }
+        out
+    }
+
+    /** 对比golden文件：`<name>_old<ext>`、`<name>_new<ext>`为输入，`<name>.<algorithm>.diff`为期望输出
+    <br>期望输出为`git diff --no-index --diff-algorithm=<algorithm> -U100`的正文
+     */
+    fn check_golden(name: &str, ext: &str) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/diff");
+        let read = |file: String| fs::read_to_string(dir.join(file)).unwrap();
+        let (old, new) = (read(format!("{}_old{}", name, ext)), read(format!("{}_new{}", name, ext)));
+        for algorithm in DiffAlgorithm::value_variants() {
+            let algorithm_name = algorithm.to_possible_value().unwrap().get_name().to_string();
+            let expected = read(format!("{}.{}.diff", name, algorithm_name));
+
+            assert_eq!(render(*algorithm, &old, &new), expected, "{} --diff-algorithm={}", name, algorithm_name);
+        }
+    }
+
+    #[test]
+    fn test_golden() {
+        check_golden("refactor", ".rs");
+        check_golden("frobnitz", ".c");
+    }
+
+    #[test]
+    fn test_hunks() {
+        let a = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "10"];
+        let b = ["1", "2", "three", "4", "5", "6", "7", "8", "9", "10", "11"];
+        let script = edit_script(&myers(&a, &b), a.len(), b.len());
+        assert_eq!(script.len(), 12);
+        assert_eq!(script[2..4], [Edit::Delete(2), Edit::Insert(2)]);
+
+        let result = hunks(&script, 3);
+        assert_eq!(result.len(), 2);
+        assert_eq!((result[0].old_start, result[0].old_len, result[0].new_start, result[0].new_len), (0, 6, 0, 6));
+        assert_eq!((result[1].old_start, result[1].old_len, result[1].new_start, result[1].new_len), (7, 3, 7, 4));
+        // 上下文重叠时合并
+        assert_eq!(hunks(&script, 4).len(), 1);
+        assert!(hunks(&edit_script(&myers(&a, &a), a.len(), a.len()), 3).is_empty());
+    }
+
+    #[test]
+    fn test_split_lines() {
+        assert_eq!(split_lines(b"a\nb\nc"), vec![&b"a\n"[..], b"b\n", b"c"]);
+        assert!(split_lines(b"").is_empty());
+        assert!(is_binary(b"a\0b"));
+        assert!(!is_binary("中文\n".as_bytes()));
+    }
+}
@@ -0,0 +1,115 @@
+use super::diff::{self, DiffAlgorithm};
+
+/// 三方合并的结果
+#[derive(Debug, PartialEq)]
+pub struct MergeResult {
+    pub content: Vec<u8>,
+    pub conflicts: usize, // 冲突块的数量，0表示合并成功
+}
+
+/// base与其他版本之间，未被修改的行：base中的行号 -> 其他版本中的行号
+fn match_map(base: &[&[u8]], other: &[&[u8]], algorithm: DiffAlgorithm) -> Vec<Option<usize>> {
+    let mut map = vec![None; base.len()];
+    for (i, j) in algorithm.matches(base, other) {
+        map[i] = Some(j);
+    }
+    map
+}
+
+fn push_lines(out: &mut Vec<u8>, lines: &[&[u8]]) {
+    for line in lines {
+        out.extend_from_slice(line);
+    }
+}
+
+/// 冲突标记需要单独成行：内容最后一行没有换行符时补上
+fn push_marker(out: &mut Vec<u8>, marker: &str) {
+    if !out.is_empty() && !out.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+    out.extend_from_slice(marker.as_bytes());
+    out.push(b'\n');
+}
+
+/** 按行进行三方合并（diff3）
+<br>以base为参照，两边都没有修改的行为稳定块；稳定块之间的区域，若只有一方修改（或双方修改相同）则直接采用，否则产生冲突：
+```text
+<<<<<<< ours_label
+ours
+=======
+theirs
+>>>>>>> theirs_label
+```
+ */
+pub fn merge(base: &[u8], ours: &[u8], theirs: &[u8], labels: (&str, &str), algorithm: DiffAlgorithm) -> MergeResult {
+    let (base, ours, theirs) = (diff::split_lines(base), diff::split_lines(ours), diff::split_lines(theirs));
+    let (ours_map, theirs_map) = (match_map(&base, &ours, algorithm), match_map(&base, &theirs, algorithm));
+
+    let mut result = MergeResult { content: Vec::new(), conflicts: 0 };
+    let (mut o, mut a, mut b) = (0, 0, 0); // base, ours, theirs中的位置
+    loop {
+        // 寻找下一个稳定行：在双方中都没有被修改
+        let next = (o..base.len()).find_map(|i| Some((i, ours_map[i]?, theirs_map[i]?)));
+        let (o_end, a_end, b_end) = next.unwrap_or((base.len(), ours.len(), theirs.len()));
+
+        let (base_chunk, ours_chunk, theirs_chunk) = (&base[o..o_end], &ours[a..a_end], &theirs[b..b_end]);
+        if ours_chunk == base_chunk || ours_chunk == theirs_chunk {
+            push_lines(&mut result.content, theirs_chunk); // 只有theirs修改，或双方修改相同
+        } else if theirs_chunk == base_chunk {
+            push_lines(&mut result.content, ours_chunk); // 只有ours修改
+        } else {
+            result.conflicts += 1;
+            push_marker(&mut result.content, &format!("<<<<<<< {}", labels.0));
+            push_lines(&mut result.content, ours_chunk);
+            push_marker(&mut result.content, "=======");
+            push_lines(&mut result.content, theirs_chunk);
+            push_marker(&mut result.content, &format!(">>>>>>> {}", labels.1));
+        }
+
+        match next {
+            Some(_) => {
+                result.content.extend_from_slice(base[o_end]); // 稳定行
+                (o, a, b) = (o_end + 1, a_end + 1, b_end + 1);
+            }
+            None => break,
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn merge_str(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+        let result =
+            merge(base.as_bytes(), ours.as_bytes(), theirs.as_bytes(), ("ours", "theirs"), DiffAlgorithm::Myers);
+        (String::from_utf8(result.content).unwrap(), result.conflicts)
+    }
+
+    #[test]
+    fn test_clean_merge() {
+        let base = "1\n2\n3\n4\n5\n";
+        assert_eq!(merge_str(base, "1\nA\n3\n4\n5\n", "1\n2\n3\n4\nB\n"), ("1\nA\n3\n4\nB\n".into(), 0));
+        assert_eq!(merge_str(base, base, "0\n1\n2\n3\n4\n5\n6\n"), ("0\n1\n2\n3\n4\n5\n6\n".into(), 0));
+        assert_eq!(merge_str(base, "1\n5\n", base), ("1\n5\n".into(), 0)); // 删除
+        assert_eq!(merge_str(base, "1\nX\n3\n4\n5\n", "1\nX\n3\n4\n5\n"), ("1\nX\n3\n4\n5\n".into(), 0));
+        assert_eq!(merge_str("", "a\n", ""), ("a\n".into(), 0));
+    }
+
+    #[test]
+    fn test_conflict() {
+        let (content, conflicts) = merge_str("1\n2\n3\n", "1\nours\n3\n", "1\ntheirs\n3\n");
+        assert_eq!(conflicts, 1);
+        assert_eq!(content, "1\n<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\n3\n");
+
+        // 没有换行符的最后一行
+        let (content, conflicts) = merge_str("a", "b", "c");
+        assert_eq!(conflicts, 1);
+        assert_eq!(content, "<<<<<<< ours\nb\n=======\nc\n>>>>>>> theirs\n");
+
+        // 双方都新增文件（base为空）
+        let (_, conflicts) = merge_str("", "x\n", "y\n");
+        assert_eq!(conflicts, 1);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::models::Hash;
+
+/// `log --graph`使用的字符集
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum GraphStyle {
+    /// `*`、`|`、`\`、`/`
+    #[default]
+    Ascii,
+    /// `●`、`│`、`╲`、`╱`
+    Unicode,
+}
+
+impl GraphStyle {
+    /// (commit, 竖线, 向右下, 向左下)
+    fn symbols(self) -> (char, char, char, char) {
+        match self {
+            GraphStyle::Ascii => ('*', '|', '\\', '/'),
+            GraphStyle::Unicode => ('●', '│', '╲', '╱'),
+        }
+    }
+}
+
+/*Graph
+* `log --graph`的分支线：每条线(lane)等待输出一个commit，每条线占2列
+* 输出commit时，它所在的线替换为它的parent：第一个parent沿用该线，其余parent在右侧新增线（分叉）；
+* parent已经在其他线中等待时（汇合），不再新增，该线合并到已有的线，右侧的线左移
+* 线的位置变化时输出过渡行（`\`、`/`），每行最多移动一列
+*/
+#[derive(Debug, Default)]
+pub struct Graph {
+    lanes: Vec<Hash>,
+    style: GraphStyle,
+}
+
+impl Graph {
+    pub fn new(style: GraphStyle) -> Graph {
+        Graph { lanes: Vec::new(), style }
+    }
+
+    /** 为一个commit的输出行加上图形前缀：第一行为commit所在行，之后依次为过渡行、竖线
+    <br>过渡行比输出行多时单独输出；parents应只包含之后会输出的commit，否则对应的线不会结束
+     */
+    pub fn render(&mut self, hash: &Hash, parents: &[Hash], lines: &[String]) -> Vec<String> {
+        let (mark, vertical, right, left) = self.style.symbols();
+        let column = match self.lanes.iter().position(|lane| lane == hash) {
+            Some(column) => column,
+            None => {
+                self.lanes.push(hash.clone()); // 新的分支顶端，放在最右侧
+                self.lanes.len() - 1
+            }
+        };
+        let old = std::mem::take(&mut self.lanes);
+        for (i, lane) in old.iter().enumerate() {
+            if i != column {
+                self.lanes.push(lane.clone());
+                continue;
+            }
+            for parent in parents {
+                if !old.contains(parent) && !self.lanes.contains(parent) {
+                    self.lanes.push(parent.clone());
+                }
+            }
+        }
+
+        // 每条线从旧位置移动到新位置
+        let position = |hash: &Hash| self.lanes.iter().position(|lane| lane == hash).unwrap();
+        let mut edges = Vec::new();
+        for (i, lane) in old.iter().enumerate() {
+            if i == column {
+                edges.extend(parents.iter().map(|parent| (i, position(parent))));
+            } else {
+                edges.push((i, position(lane)));
+            }
+        }
+        edges.dedup();
+
+        let width = old.len().max(self.lanes.len());
+        let mut rows = vec![row(width, (0..old.len()).map(|i| (2 * i, if i == column { mark } else { vertical })))];
+        let mut positions = edges.iter().map(|(from, _)| *from).collect::<Vec<_>>();
+        while positions.iter().zip(&edges).any(|(pos, (_, to))| pos != to) {
+            let mut cells = Vec::new();
+            for (pos, (_, to)) in positions.iter_mut().zip(&edges) {
+                if *pos < *to {
+                    cells.push((2 * *pos + 1, right));
+                    *pos += 1;
+                } else if *pos > *to {
+                    cells.push((2 * *pos - 1, left));
+                    *pos -= 1;
+                } else {
+                    cells.push((2 * *pos, vertical));
+                }
+            }
+            rows.push(row(width, cells));
+        }
+        let padding = row(width, (0..self.lanes.len()).map(|i| (2 * i, vertical)));
+
+        let mut result = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| format!("{}{}", rows.get(i).unwrap_or(&padding), line).trim_end().to_string())
+            .collect::<Vec<_>>();
+        result.extend(rows.iter().skip(lines.len()).map(|row| row.trim_end().to_string()));
+        result
+    }
+}
+
+/// 宽度为width条线的一行，其余位置为空格
+fn row(width: usize, cells: impl IntoIterator<Item = (usize, char)>) -> String {
+    let mut chars = vec![' '; 2 * width];
+    for (i, c) in cells {
+        chars[i] = c;
+    }
+    chars.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn render(graph: &mut Graph, hash: &str, parents: &[&str]) -> Vec<String> {
+        let parents = parents.iter().map(|parent| parent.to_string()).collect::<Vec<_>>();
+        let lines = [hash.to_string(), "".to_string()];
+        graph.render(&hash.to_string(), &parents, &lines)
+    }
+
+    #[test]
+    fn test_graph() {
+        // merge的两个parent：feature先输出，然后是master，最后汇合到base
+        let mut graph = Graph::new(GraphStyle::Ascii);
+        let mut output = render(&mut graph, "merge", &["m1", "f1"]);
+        output.extend(render(&mut graph, "f1", &["base"]));
+        output.extend(render(&mut graph, "m1", &["base"]));
+        output.extend(render(&mut graph, "base", &[]));
+        let expected = ["*   merge", "|\\", "| * f1", "| |", "* | m1", "|/", "* base", ""];
+        assert_eq!(output, expected);
+
+        // 两个分支顶端，汇合到同一个parent
+        let mut graph = Graph::new(GraphStyle::Unicode);
+        let mut output = render(&mut graph, "a", &["c"]);
+        output.extend(render(&mut graph, "b", &["c"]));
+        output.extend(render(&mut graph, "c", &[]));
+        assert_eq!(output, ["● a", "│", "│ ● b", "│╱", "● c", ""]);
+
+        // 三个parent：右侧已有的线依次右移，过渡行多于输出行时单独输出
+        let mut graph = Graph::new(GraphStyle::Ascii);
+        graph.lanes = vec!["octopus".to_string(), "x".to_string()];
+        let lines = render(&mut graph, "octopus", &["p1", "p2", "p3"]);
+        assert_eq!(lines, ["* |     octopus", "|\\ \\", "| |\\ \\"]);
+        assert_eq!(graph.lanes, ["p1", "p2", "p3", "x"]);
+    }
+}
@@ -0,0 +1,259 @@
+use std::{ops::Range, path::PathBuf};
+
+use regex::bytes::Regex;
+
+use super::diff::Edit;
+
+/*LineRange
+* `log -L`、`blame -L`指定的行范围：
+* - `<start>,<end>`：行号从1开始，包括end；省略start时从第一行开始，省略end时到最后一行
+*   <br>end也可以是`+<n>`（从start开始的n行）或`-<n>`（到start为止的n行）
+*   <br>start、end也可以是`/<regex>/`：start为第一个匹配的行，end为start之后第一个匹配的行
+* - `:<funcname>`：第一个匹配该正则的行所在的函数，见[function_range]
+*/
+
+/// 行范围的一端
+#[derive(Debug, Clone)]
+pub enum Bound {
+    Line(usize),   // 行号，从1开始
+    Offset(isize), // 相对于start的行数，只用于end
+    Regex(Regex),  // 第一个匹配的行
+}
+
+/// 行范围，在确定的文件内容中解析为具体的行（见[LineSpec::resolve]）
+#[derive(Debug, Clone)]
+pub enum LineSpec {
+    Lines(Option<Bound>, Option<Bound>),
+    Funcname(Regex),
+}
+
+impl LineSpec {
+    pub fn parse(spec: &str) -> Result<LineSpec, String> {
+        let invalid = || format!("fatal: 无效的行范围: '{}'", spec);
+        let regex = |pattern: &str| Regex::new(pattern).map_err(|_| format!("fatal: 无效的正则表达式: '{}'", pattern));
+        if let Some(funcname) = spec.strip_prefix(':') {
+            return match funcname.is_empty() {
+                true => Err(invalid()),
+                false => Ok(LineSpec::Funcname(regex(funcname)?)),
+            };
+        }
+        let bound = |value: &str, is_end: bool| -> Result<Option<Bound>, String> {
+            if value.is_empty() {
+                return Ok(None);
+            }
+            if let Some(pattern) = value.strip_prefix('/').and_then(|value| value.strip_suffix('/')) {
+                return Ok(Some(Bound::Regex(regex(pattern)?)));
+            }
+            if is_end && (value.starts_with('+') || value.starts_with('-')) {
+                return match value.parse::<isize>() {
+                    Ok(offset) if offset != 0 => Ok(Some(Bound::Offset(offset))),
+                    _ => Err(invalid()),
+                };
+            }
+            match value.parse::<usize>() {
+                Ok(line) if line > 0 && value.bytes().all(|b| b.is_ascii_digit()) => Ok(Some(Bound::Line(line))),
+                _ => Err(invalid()),
+            }
+        };
+        let (start, end) = spec.split_once(',').unwrap_or((spec, ""));
+        Ok(LineSpec::Lines(bound(start, false)?, bound(end, true)?))
+    }
+
+    /// 在文件内容（按行切分）中的范围，下标从0开始
+    pub fn resolve(&self, lines: &[&[u8]]) -> Result<Range<usize>, String> {
+        let find = |regex: &Regex, from: usize| {
+            (from..lines.len())
+                .find(|&i| regex.is_match(lines[i]))
+                .ok_or_else(|| format!("fatal: 没有匹配'{}'的行", regex))
+        };
+        let (start, end) = match self {
+            LineSpec::Funcname(regex) => return Ok(function_range(lines, find(regex, 0)?)),
+            LineSpec::Lines(start, end) => (start, end),
+        };
+        let start = match start {
+            None => 0,
+            Some(Bound::Line(line)) => line - 1,
+            Some(Bound::Regex(regex)) => find(regex, 0)?,
+            Some(Bound::Offset(_)) => unreachable!("start不能是相对行数"),
+        };
+        if start >= lines.len() {
+            return Err(format!("fatal: 文件只有{}行", lines.len()));
+        }
+        let range = match end {
+            None => start..lines.len(),
+            Some(Bound::Line(line)) if line - 1 < start => line - 1..start + 1, // 与Git相同，end在start之前时交换
+            Some(Bound::Line(line)) => start..(*line).min(lines.len()),
+            Some(Bound::Offset(offset)) if *offset > 0 => start..(start + *offset as usize).min(lines.len()),
+            Some(Bound::Offset(offset)) => (start + 1).saturating_sub(offset.unsigned_abs())..start + 1,
+            Some(Bound::Regex(regex)) => start..find(regex, start + 1)? + 1,
+        };
+        Ok(range)
+    }
+}
+
+/** 从start行开始的函数：之后缩进更多的行、空行，以及缩进相同的结束行（以`}`、`)`、`]`开头）
+<br>遇到缩进更少或缩进相同的其他行（如下一个函数、注释）时结束，末尾的空行不包括在内
+ */
+fn function_range(lines: &[&[u8]], start: usize) -> Range<usize> {
+    let indent = |line: &[u8]| line.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+    let base = indent(lines[start]);
+    let mut end = start + 1;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        let Some(&first) = line.iter().find(|b| !b.is_ascii_whitespace()) else {
+            continue; // 空行
+        };
+        match indent(line) {
+            n if n > base => end = i + 1,
+            n if n == base && matches!(first, b'}' | b')' | b']') => end = i + 1,
+            _ => break,
+        }
+    }
+    start..end
+}
+
+/// `log -L <range>:<file>`的参数
+#[derive(Debug, Clone)]
+pub struct FileRange {
+    pub spec: LineSpec,
+    pub path: PathBuf,
+}
+
+impl FileRange {
+    /// `<start>,<end>:<file>`或`:<funcname>:<file>`，funcname中可以包含`:`
+    pub fn parse(value: &str) -> Result<FileRange, String> {
+        let split = match value.starts_with(':') {
+            true => value.rsplit_once(':').filter(|(spec, _)| !spec.is_empty()),
+            false => value.split_once(':'),
+        };
+        match split {
+            Some((spec, path)) if !path.is_empty() => {
+                Ok(FileRange { spec: LineSpec::parse(spec)?, path: PathBuf::from(path) })
+            }
+            _ => Err(format!("fatal: -L的参数应为<start>,<end>:<file>或:<funcname>:<file>: '{}'", value)),
+        }
+    }
+}
+
+/** 将新版本中的行范围映射到旧版本中，script为旧版本 -> 新版本的编辑脚本
+<br>返回旧版本中的范围，以及script中属于该范围的部分：范围内的行，以及范围内部删除的行
+（紧邻范围之前删除的行只在被范围内新增的行替换时属于该范围，紧邻范围之后删除的行不属于该范围）
+<br>范围内的行都是新增的时，旧版本中的范围为空
+ */
+pub fn map_back(script: &[Edit], range: &Range<usize>) -> (Range<usize>, Range<usize>) {
+    let (mut old, mut new) = (0, 0);
+    let (mut first, mut last) = (None, None);
+    for (i, edit) in script.iter().enumerate() {
+        // 范围之前删除的行不属于该范围，除非被范围内新增的行替换
+        let replaced = || {
+            let next = script[i..].iter().find(|edit| !matches!(edit, Edit::Delete(_)));
+            matches!(next, Some(Edit::Insert(_)))
+        };
+        if new == range.start && first.is_none() && (!matches!(edit, Edit::Delete(_)) || replaced()) {
+            first = Some((i, old));
+        }
+        if new == range.end {
+            last = Some((i, old));
+            break;
+        }
+        match edit {
+            Edit::Equal(..) => (old, new) = (old + 1, new + 1),
+            Edit::Delete(_) => old += 1,
+            Edit::Insert(_) => new += 1,
+        }
+    }
+    let (first, last) = (first.unwrap_or((script.len(), old)), last.unwrap_or((script.len(), old)));
+    (first.1..last.1, first.0..last.0)
+}
+
+/// 范围是否被修改：script中属于该范围的部分（见[map_back]）包含新增或删除的行
+pub fn is_changed(script: &[Edit], edits: &Range<usize>) -> bool {
+    script[edits.clone()].iter().any(|edit| !matches!(edit, Edit::Equal(..)))
+}
+
+/// 排序并合并重叠或相邻的范围，去掉空范围
+pub fn merge(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.retain(|range| !range.is_empty());
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::diff;
+
+    fn resolve(spec: &str, content: &str) -> Result<Range<usize>, String> {
+        LineSpec::parse(spec)?.resolve(&diff::split_lines(content.as_bytes()))
+    }
+
+    #[test]
+    fn test_resolve() {
+        let content = "a\nb\nc\nd\ne\n";
+        assert_eq!(resolve("2,4", content), Ok(1..4));
+        assert_eq!(resolve("2,+2", content), Ok(1..3));
+        assert_eq!(resolve("4,-2", content), Ok(2..4));
+        assert_eq!(resolve("4,2", content), Ok(1..4));
+        assert_eq!(resolve("3", content), Ok(2..5));
+        assert_eq!(resolve(",2", content), Ok(0..2));
+        assert_eq!(resolve("2,100", content), Ok(1..5));
+        assert_eq!(resolve("/b/,/d/", content), Ok(1..4));
+        assert!(resolve("6,7", content).is_err());
+        assert!(resolve("0,2", content).is_err());
+        assert!(resolve("+1,2", content).is_err());
+        assert!(resolve("/x/,2", content).is_err());
+
+        let code = "use x;\n\nfn main() {\n    let a = 1;\n\n    a\n}\n\n/// doc\nfn other() {}\n";
+        assert_eq!(resolve(":fn main", code), Ok(2..7));
+        assert_eq!(resolve(":other", code), Ok(9..10));
+        let nested = "impl A {\n    fn new() {\n        x\n    }\n}\n";
+        assert_eq!(resolve(":fn new", nested), Ok(1..4));
+        assert!(resolve(":missing", code).is_err());
+
+        let range = FileRange::parse(":fn a::b:src/main.rs").unwrap();
+        assert_eq!(range.path, PathBuf::from("src/main.rs"));
+        assert!(matches!(range.spec, LineSpec::Funcname(regex) if regex.as_str() == "fn a::b"));
+        assert!(FileRange::parse("1,2").is_err());
+        assert!(FileRange::parse(":main").is_err());
+        assert!(FileRange::parse("1,2:").is_err());
+    }
+
+    #[test]
+    fn test_map_back() {
+        let (old, new) = (b"a\nb\nc\nd\ne\n", b"a\nB\nc\nd\nx\ne\n");
+        let (old, new) = (diff::split_lines(old), diff::split_lines(new));
+        let script = diff::edit_script(&diff::myers(&old, &new), old.len(), new.len());
+
+        // c、d没有修改，映射到旧版本中的相同位置
+        let (range, edits) = map_back(&script, &(2..4));
+        assert_eq!(range, 2..4);
+        assert!(!is_changed(&script, &edits));
+        // B替换了b
+        let (range, edits) = map_back(&script, &(0..3));
+        assert_eq!(range, 0..3);
+        assert!(is_changed(&script, &edits));
+        // 新增的x
+        let (range, edits) = map_back(&script, &(4..5));
+        assert_eq!(range, 4..4);
+        assert!(is_changed(&script, &edits));
+        let (range, edits) = map_back(&script, &(5..6));
+        assert_eq!(range, 4..5);
+        assert!(!is_changed(&script, &edits));
+        // 替换的b属于从B开始的范围，紧邻范围之前删除的行不属于该范围
+        let (range, edits) = map_back(&script, &(1..2));
+        assert_eq!((range, edits), (1..2, 1..3));
+        let (old, new) = (diff::split_lines(b"a\nb\nc\n"), diff::split_lines(b"a\nc\n"));
+        let script = diff::edit_script(&diff::myers(&old, &new), old.len(), new.len());
+        let (range, edits) = map_back(&script, &(1..2));
+        assert_eq!(range, 2..3);
+        assert!(!is_changed(&script, &edits));
+
+        assert_eq!(merge(vec![5..7, 1..3, 2..4, 4..4, 7..8]), [1..4, 5..8]);
+    }
+}
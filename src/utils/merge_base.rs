@@ -0,0 +1,204 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::models::{Commit, Hash};
+
+const PARENT1: u8 = 1; // 可以从第一个commit到达
+const PARENT2: u8 = 2; // 可以从第二个commit到达
+const STALE: u8 = 4; // 已经是某个公共祖先的祖先，不可能是最近的
+
+/** commit图：缓存每个commit的parent和generation，所有遍历都是迭代的，不会栈溢出
+ * <br>generation：root commit为1，其余为 1 + max(parent的generation)，祖先的generation一定更小，可以用来剪枝
+ */
+#[derive(Default)]
+pub struct CommitGraph {
+    parents: HashMap<Hash, Vec<Hash>>,
+    generations: HashMap<Hash, usize>,
+}
+
+impl CommitGraph {
+    pub fn new() -> CommitGraph {
+        CommitGraph::default()
+    }
+
+    /// commit的parent，只读取一次
+    pub fn parents(&mut self, hash: &Hash) -> Vec<Hash> {
+        if let Some(parents) = self.parents.get(hash) {
+            return parents.clone();
+        }
+        let parents = Commit::load(hash).get_parent_hash();
+        self.parents.insert(hash.clone(), parents.clone());
+        parents
+    }
+
+    /// commit的generation，后序遍历计算
+    pub fn generation(&mut self, hash: &Hash) -> usize {
+        let mut stack = vec![hash.clone()];
+        while let Some(top) = stack.last().cloned() {
+            if self.generations.contains_key(&top) {
+                stack.pop();
+                continue;
+            }
+            let parents = self.parents(&top);
+            let pending = parents
+                .iter()
+                .filter(|parent| !self.generations.contains_key(*parent))
+                .cloned()
+                .collect::<Vec<_>>();
+            if pending.is_empty() {
+                let generation = 1 + parents.iter().map(|parent| self.generations[parent]).max().unwrap_or(0);
+                self.generations.insert(top, generation);
+                stack.pop();
+            } else {
+                stack.extend(pending);
+            }
+        }
+        self.generations[hash]
+    }
+
+    /// ancestor是否是descendant的祖先（包括自身）；generation比ancestor小的commit不会被访问
+    pub fn is_ancestor(&mut self, ancestor: &Hash, descendant: &Hash) -> bool {
+        let min_generation = self.generation(ancestor);
+        let mut visited = HashSet::new();
+        let mut stack = vec![descendant.clone()];
+        while let Some(hash) = stack.pop() {
+            if hash == *ancestor {
+                return true;
+            }
+            if !visited.insert(hash.clone()) || self.generation(&hash) <= min_generation {
+                continue;
+            }
+            stack.extend(self.parents(&hash));
+        }
+        false
+    }
+
+    /** 所有的最近公共祖先（`merge-base --all`），按generation从大到小、hash排序
+    <br>与Git的paint_down_to_common相同：按generation从大到小遍历，标记可以从哪一方到达，
+    双方都能到达的commit即为公共祖先，它的祖先被标记为STALE
+     */
+    pub fn merge_bases(&mut self, one: &Hash, two: &Hash) -> Vec<Hash> {
+        if one == two {
+            return vec![one.clone()];
+        }
+        let mut flags: HashMap<Hash, u8> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        // 每个commit在队列中的次数，以及队列中不是STALE的commit数（包括重复）
+        let mut queued: HashMap<Hash, usize> = HashMap::new();
+        let mut non_stale = 0;
+        for (hash, flag) in [(one, PARENT1), (two, PARENT2)] {
+            *flags.entry(hash.clone()).or_default() |= flag;
+            *queued.entry(hash.clone()).or_default() += 1;
+            non_stale += 1;
+            queue.push((self.generation(hash), hash.clone()));
+        }
+
+        let mut candidates = Vec::new();
+        // 队列中全部是STALE时，不会再找到新的公共祖先
+        while non_stale > 0 {
+            let (_, hash) = queue.pop().unwrap();
+            *queued.get_mut(&hash).unwrap() -= 1;
+            let mut flag = flags[&hash];
+            if flag & STALE == 0 {
+                non_stale -= 1;
+            }
+            if flag & (PARENT1 | PARENT2) == (PARENT1 | PARENT2) && flag & STALE == 0 {
+                candidates.push(hash.clone());
+                flag |= STALE;
+                flags.insert(hash.clone(), flag);
+                non_stale -= queued[&hash];
+            }
+            for parent in self.parents(&hash) {
+                let parent_flag = flags.entry(parent.clone()).or_default();
+                if *parent_flag & flag != flag {
+                    let count = queued.entry(parent.clone()).or_default();
+                    if *parent_flag & STALE == 0 && flag & STALE != 0 {
+                        non_stale -= *count; // 队列中已有的parent变为STALE
+                    }
+                    *parent_flag |= flag;
+                    *count += 1;
+                    if *parent_flag & STALE == 0 {
+                        non_stale += 1;
+                    }
+                    queue.push((self.generation(&parent), parent));
+                }
+            }
+        }
+
+        // 去掉冗余：是其他候选者祖先的候选者（generation相同的commit互相不可达）
+        let mut bases = Vec::new();
+        for candidate in &candidates {
+            let redundant = candidates
+                .iter()
+                .any(|other| other != candidate && self.is_ancestor(candidate, other));
+            if !redundant {
+                bases.push(candidate.clone());
+            }
+        }
+        bases.sort_by_cached_key(|hash| (std::cmp::Reverse(self.generation(hash)), hash.clone()));
+        bases.dedup();
+        bases
+    }
+
+    /// 最近公共祖先，存在多个时取第一个（见[CommitGraph::merge_bases]）
+    pub fn merge_base(&mut self, one: &Hash, two: &Hash) -> Option<Hash> {
+        self.merge_bases(one, two).into_iter().next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{commands as cmd, models::head, utils::test};
+
+    fn commit(message: &str) -> Hash {
+        cmd::commit(message.to_string(), true);
+        head::current_head_commit()
+    }
+
+    #[test]
+    fn test_linear_history() {
+        test::setup_with_empty_workdir();
+        let first = commit("1");
+        for i in 2..200 {
+            commit(&i.to_string());
+        }
+        let last = head::current_head_commit();
+        let mut graph = CommitGraph::new();
+        assert_eq!(graph.generation(&last), 199);
+        assert_eq!(graph.generation(&first), 1);
+        assert!(graph.is_ancestor(&first, &last));
+        assert!(!graph.is_ancestor(&last, &first));
+        assert!(graph.is_ancestor(&last, &last));
+        assert_eq!(graph.merge_base(&first, &last), Some(first.clone()));
+        assert_eq!(graph.merge_bases(&last, &last), vec![last]);
+    }
+
+    #[test]
+    fn test_criss_cross() {
+        // base - a1 - a2(merge b1)
+        //     \- b1 - b2(merge a1)
+        test::setup_with_empty_workdir();
+        let base = commit("base");
+        cmd::branch(Some("b".to_string()), None, false, None, false, false);
+        let a1 = commit("a1");
+        cmd::switch(Some("b".to_string()), None, false);
+        let b1 = commit("b1");
+
+        let merge = |parents: Vec<Hash>, message: &str| {
+            let mut commit = Commit::new(crate::models::Index::get_instance(), parents, message.to_string());
+            commit.save()
+        };
+        let a2 = merge(vec![a1.clone(), b1.clone()], "a2");
+        let b2 = merge(vec![b1.clone(), a1.clone()], "b2");
+
+        let mut graph = CommitGraph::new();
+        let mut expected = vec![a1.clone(), b1.clone()];
+        expected.sort();
+        assert_eq!(graph.merge_bases(&a2, &b2), expected);
+        assert_eq!(graph.merge_bases(&a1, &b1), vec![base.clone()]);
+        assert_eq!(graph.merge_base(&a2, &b1), Some(b1.clone()));
+        assert!(graph.is_ancestor(&base, &a2));
+        assert!(!graph.is_ancestor(&a2, &b2));
+        assert_eq!(graph.generation(&a2), 3);
+    }
+}
@@ -1,5 +1,20 @@
+pub mod config;
+pub mod date;
+pub mod delta;
+pub mod diff;
+pub mod diff3;
+pub mod graph;
+pub mod line_range;
+pub mod merge_base;
+pub mod pack;
 pub mod path_ext;
+pub mod pretty;
 pub use path_ext::PathExt;
+pub mod reachable;
+pub mod rev_list;
+pub mod revision;
+pub mod similarity;
 pub mod store;
 pub mod test;
 pub mod util;
+pub mod word_diff;
@@ -0,0 +1,302 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{bufread::ZlibDecoder, write::ZlibEncoder, Compression};
+use sha1::{Digest, Sha1};
+
+use crate::models::{Hash, ObjectType};
+
+use super::delta;
+
+// pack中的object类型编号，与Git一致
+const OBJ_COMMIT: u8 = 1;
+const OBJ_TREE: u8 = 2;
+const OBJ_BLOB: u8 = 3;
+const OBJ_OFS_DELTA: u8 = 6;
+const OBJ_REF_DELTA: u8 = 7;
+
+const IDX_MAGIC: [u8; 4] = [0xff, b't', b'O', b'c'];
+const DELTA_WINDOW: usize = 10; // 在多少个相邻的object中寻找delta base
+const MAX_DELTA_DEPTH: usize = 10; // delta链的最大长度，限制读取开销
+
+fn type_code(obj_type: ObjectType) -> u8 {
+    match obj_type {
+        ObjectType::Commit => OBJ_COMMIT,
+        ObjectType::Tree => OBJ_TREE,
+        ObjectType::Blob => OBJ_BLOB,
+        ObjectType::Invalid => panic!("无法打包Invalid类型的object"),
+    }
+}
+
+fn code_type(code: u8) -> ObjectType {
+    match code {
+        OBJ_COMMIT => ObjectType::Commit,
+        OBJ_TREE => ObjectType::Tree,
+        OBJ_BLOB => ObjectType::Blob,
+        _ => ObjectType::Invalid,
+    }
+}
+
+/// pack中一个entry的类型
+enum EntryKind {
+    Object(ObjectType),
+    OfsDelta(u64), // base在pack中的offset
+    RefDelta(Hash),
+}
+
+/**Pack<br>
+将多个object打包为一个文件，相似的object以delta储存
+<br>.mit/objects/pack/pack-{checksum}.pack & .idx，格式与Git的pack v2 & idx v2一致
+ */
+pub struct Pack {
+    pack_path: PathBuf,
+    hashes: Vec<Hash>, // 有序，用于二分查找
+    offsets: Vec<u64>,
+}
+
+impl Pack {
+    /// 读取.idx文件，格式错误时返回None
+    pub fn open(idx_path: &Path) -> Option<Pack> {
+        let idx = fs::read(idx_path).ok()?;
+        if idx.get(0..4)? != IDX_MAGIC || u32::from_be_bytes(idx.get(4..8)?.try_into().ok()?) != 2 {
+            return None;
+        }
+        let read_u32 = |pos: usize| Some(u32::from_be_bytes(idx.get(pos..pos + 4)?.try_into().ok()?));
+        let count = read_u32(8 + 255 * 4)? as usize;
+        let hashes_start = 8 + 256 * 4;
+        let offsets_start = hashes_start + count * 20 + count * 4; // 跳过crc32
+        let mut hashes = Vec::with_capacity(count);
+        let mut offsets = Vec::with_capacity(count);
+        for i in 0..count {
+            hashes.push(hex::encode(idx.get(hashes_start + i * 20..hashes_start + (i + 1) * 20)?));
+            offsets.push(read_u32(offsets_start + i * 4)? as u64); // 不支持超过2GB的pack
+        }
+        Some(Pack { pack_path: idx_path.with_extension("pack"), hashes, offsets })
+    }
+
+    /// 列出目录下所有的pack
+    pub fn list(pack_dir: &Path) -> Vec<Pack> {
+        let entries = match fs::read_dir(pack_dir) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "idx"))
+            .filter_map(|path| Pack::open(&path))
+            .collect()
+    }
+
+    pub fn get_path(&self) -> PathBuf {
+        self.pack_path.clone()
+    }
+
+    /// pack中所有object的hash（有序）
+    pub fn hashes(&self) -> &[Hash] {
+        &self.hashes
+    }
+
+    fn find(&self, hash: &String) -> Option<u64> {
+        self.hashes.binary_search(hash).ok().map(|i| self.offsets[i])
+    }
+
+    pub fn contains(&self, hash: &String) -> bool {
+        self.find(hash).is_some()
+    }
+
+    /// 读取object，自动还原delta
+    pub fn load(&self, hash: &String) -> Option<(ObjectType, Vec<u8>)> {
+        self.load_at(self.find(hash)?, 0)
+    }
+
+    fn load_at(&self, offset: u64, depth: usize) -> Option<(ObjectType, Vec<u8>)> {
+        if depth > MAX_DELTA_DEPTH * 5 {
+            return None; // delta链过长，疑似损坏（Git生成的pack链长可达50）
+        }
+        let (kind, data) = self.read_entry(offset).ok()?;
+        match kind {
+            EntryKind::Object(ObjectType::Invalid) => None,
+            EntryKind::Object(obj_type) => Some((obj_type, data)),
+            EntryKind::OfsDelta(base_offset) => {
+                let (obj_type, base) = self.load_at(base_offset, depth + 1)?;
+                Some((obj_type, delta::apply(&base, &data)?))
+            }
+            EntryKind::RefDelta(base_hash) => {
+                let (obj_type, base) = self.load_at(self.find(&base_hash)?, depth + 1)?;
+                Some((obj_type, delta::apply(&base, &data)?))
+            }
+        }
+    }
+
+    /// 读取offset处的entry：头部 + zlib压缩的数据
+    fn read_entry(&self, offset: u64) -> io::Result<(EntryKind, Vec<u8>)> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "pack格式错误");
+        let mut file = File::open(&self.pack_path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut reader = BufReader::new(file);
+        let mut read_byte = || -> io::Result<u8> {
+            let mut byte = [0u8; 1];
+            reader.read_exact(&mut byte)?;
+            Ok(byte[0])
+        };
+
+        // 头部：type(3 bits) + size(varint)
+        let mut byte = read_byte()?;
+        let code = (byte >> 4) & 0x7;
+        let mut size = (byte & 0x0f) as usize;
+        let mut shift = 4;
+        while byte & 0x80 != 0 {
+            byte = read_byte()?;
+            size |= ((byte & 0x7f) as usize) << shift;
+            shift += 7;
+        }
+        let kind = match code {
+            OBJ_OFS_DELTA => {
+                byte = read_byte()?;
+                let mut distance = (byte & 0x7f) as u64;
+                while byte & 0x80 != 0 {
+                    byte = read_byte()?;
+                    distance = ((distance + 1) << 7) | (byte & 0x7f) as u64;
+                }
+                EntryKind::OfsDelta(offset.checked_sub(distance).ok_or_else(invalid)?)
+            }
+            OBJ_REF_DELTA => {
+                let mut base = [0u8; 20];
+                reader.read_exact(&mut base)?;
+                EntryKind::RefDelta(hex::encode(base))
+            }
+            code => EntryKind::Object(code_type(code)),
+        };
+
+        let mut data = vec![0u8; size];
+        ZlibDecoder::new(reader).read_exact(&mut data)?;
+        Ok((kind, data))
+    }
+
+    /** 将objects写入一个新的pack，返回(pack路径, delta数量)
+    <br>delta base的选择：同类型的object按大小降序排列，在相邻窗口中选出delta最小的base
+     */
+    pub fn write(pack_dir: &Path, mut objects: Vec<(Hash, ObjectType, Vec<u8>)>) -> io::Result<(PathBuf, usize)> {
+        objects.sort_by(|a, b| (type_code(a.1), b.2.len(), &a.0).cmp(&(type_code(b.1), a.2.len(), &b.0)));
+
+        let mut bases: Vec<Option<(usize, Vec<u8>)>> = vec![None; objects.len()];
+        let mut depths = vec![0usize; objects.len()];
+        for i in 0..objects.len() {
+            if objects[i].1 == ObjectType::Commit {
+                continue; // commit很小，不值得delta
+            }
+            let mut best: Option<(usize, Vec<u8>)> = None;
+            for j in i.saturating_sub(DELTA_WINDOW)..i {
+                if objects[j].1 != objects[i].1 || depths[j] >= MAX_DELTA_DEPTH {
+                    continue;
+                }
+                let delta = delta::encode(&objects[j].2, &objects[i].2);
+                let smaller = best.as_ref().is_none_or(|(_, best)| delta.len() < best.len());
+                if delta.len() < objects[i].2.len() / 2 && smaller {
+                    best = Some((j, delta));
+                }
+            }
+            if let Some((j, delta)) = best {
+                depths[i] = depths[j] + 1;
+                bases[i] = Some((j, delta));
+            }
+        }
+
+        // .pack
+        let mut pack = b"PACK".to_vec();
+        pack.extend(2u32.to_be_bytes());
+        pack.extend((objects.len() as u32).to_be_bytes());
+        let mut entries = Vec::with_capacity(objects.len()); // (hash, crc32, offset)
+        for (i, (hash, obj_type, data)) in objects.iter().enumerate() {
+            let offset = pack.len();
+            let (code, data) = match &bases[i] {
+                Some((_, delta)) => (OBJ_REF_DELTA, delta),
+                None => (type_code(*obj_type), data),
+            };
+            // 头部：type(3 bits) + size(varint)
+            let mut size = data.len();
+            let mut byte = (code << 4) | (size & 0x0f) as u8;
+            size >>= 4;
+            while size > 0 {
+                pack.push(byte | 0x80);
+                byte = (size & 0x7f) as u8;
+                size >>= 7;
+            }
+            pack.push(byte);
+            if let Some((base, _)) = &bases[i] {
+                pack.extend(hex::decode(&objects[*base].0).unwrap());
+            }
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            pack.extend(encoder.finish()?);
+            assert!(pack.len() < 1 << 31, "不支持超过2GB的pack");
+            entries.push((hash.clone(), crc32fast::hash(&pack[offset..]), offset as u32));
+        }
+        let checksum = Sha1::digest(&pack).to_vec();
+        pack.extend(&checksum);
+
+        // .idx
+        entries.sort();
+        let mut idx = IDX_MAGIC.to_vec();
+        idx.extend(2u32.to_be_bytes());
+        let first_bytes = entries
+            .iter()
+            .map(|(hash, _, _)| hex::decode(&hash[..2]).unwrap()[0])
+            .collect::<Vec<_>>();
+        for fan in 0..=255u8 {
+            idx.extend((first_bytes.iter().filter(|&&b| b <= fan).count() as u32).to_be_bytes());
+        }
+        entries.iter().for_each(|(hash, _, _)| idx.extend(hex::decode(hash).unwrap()));
+        entries.iter().for_each(|(_, crc, _)| idx.extend(crc.to_be_bytes()));
+        entries.iter().for_each(|(_, _, offset)| idx.extend(offset.to_be_bytes()));
+        idx.extend(&checksum);
+        idx.extend(Sha1::digest(&idx).to_vec());
+
+        fs::create_dir_all(pack_dir)?;
+        let pack_path = pack_dir.join(format!("pack-{}.pack", hex::encode(&checksum)));
+        fs::write(&pack_path, pack)?;
+        fs::write(pack_path.with_extension("idx"), idx)?;
+        Ok((pack_path, bases.iter().filter(|base| base.is_some()).count()))
+    }
+
+    /// 删除.pack & .idx
+    pub fn remove(&self) -> io::Result<()> {
+        fs::remove_file(self.pack_path.with_extension("idx"))?;
+        fs::remove_file(&self.pack_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{test, util};
+
+    #[test]
+    fn test_write_and_load() {
+        test::setup_with_clean_mit();
+        let pack_dir = util::get_storage_path().unwrap().join("objects").join("pack");
+        let base = "line of a source file\n".repeat(50);
+        let objects = vec![
+            ("a".repeat(40), ObjectType::Blob, base.clone().into_bytes()),
+            ("b".repeat(40), ObjectType::Blob, (base.clone() + "one more line\n").into_bytes()),
+            ("c".repeat(40), ObjectType::Commit, b"tree 1234\n\nmessage\n".to_vec()),
+            ("d".repeat(40), ObjectType::Tree, vec![0, 1, 2, 255]),
+        ];
+        let (pack_path, deltas) = Pack::write(&pack_dir, objects.clone()).unwrap();
+        assert_eq!(deltas, 1); // 较小的blob以较大的blob为base
+
+        let pack = Pack::open(&pack_path.with_extension("idx")).unwrap();
+        assert_eq!(pack.hashes().len(), objects.len());
+        for (hash, obj_type, data) in objects {
+            assert!(pack.contains(&hash));
+            assert_eq!(pack.load(&hash), Some((obj_type, data)));
+        }
+        assert!(pack.load(&"e".repeat(40)).is_none());
+        assert_eq!(Pack::list(&pack_dir).len(), 1);
+        pack.remove().unwrap();
+        assert!(Pack::list(&pack_dir).is_empty());
+    }
+}
@@ -0,0 +1,365 @@
+use std::{collections::HashMap, time::SystemTime};
+
+use colored::Colorize;
+
+use crate::models::{head, Commit, Hash};
+
+use super::date;
+
+/*Pretty
+* log中每个commit的输出格式，`--pretty=<format>`、`--format=<format>`：
+* - `oneline`：`<hash> <ref> <标题>`，`--oneline`时使用7位缩写hash
+* - `short`：commit、Author、标题；`medium`（默认）：commit、Author、Date、完整提交信息
+* - `full`：commit、Author、Commit、完整提交信息；`fuller`：在full的基础上增加AuthorDate、CommitDate
+* - `tformat:<string>`或含有`%`的字符串：按占位符输出，每个commit之后换行
+* - `format:<string>`：按占位符输出，commit之间换行，最后一个commit之后不换行（与Git相同）
+* - `json`：每个commit输出一行JSON
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Pretty {
+    Oneline {
+        abbrev: bool,
+    },
+    Short,
+    #[default]
+    Medium,
+    Full,
+    Fuller,
+    Format {
+        format: String,
+        separator: bool, // `format:`为分隔符语义，`tformat:`为结束符语义
+    },
+    Json,
+}
+
+impl Pretty {
+    /// 解析`--pretty`、`--format`的值
+    pub fn parse(value: &str) -> Result<Pretty, String> {
+        if let Some(format) = value.strip_prefix("format:") {
+            return Ok(Pretty::Format { format: format.to_string(), separator: true });
+        }
+        if let Some(format) = value.strip_prefix("tformat:") {
+            return Ok(Pretty::Format { format: format.to_string(), separator: false });
+        }
+        match value {
+            "oneline" => Ok(Pretty::Oneline { abbrev: false }),
+            "short" => Ok(Pretty::Short),
+            "medium" => Ok(Pretty::Medium),
+            "full" => Ok(Pretty::Full),
+            "fuller" => Ok(Pretty::Fuller),
+            "json" => Ok(Pretty::Json),
+            _ if value.contains('%') => Ok(Pretty::Format { format: value.to_string(), separator: false }),
+            _ => Err(format!("invalid --pretty format: '{}'", value)),
+        }
+    }
+
+    /// 是否为多行格式：每个commit之后（包括diff摘要之后）输出空行
+    pub fn is_multiline(&self) -> bool {
+        matches!(self, Pretty::Short | Pretty::Medium | Pretty::Full | Pretty::Fuller)
+    }
+
+    /// 是否为分隔符语义（`format:`）：最后一个commit之后不换行
+    pub fn is_separator(&self) -> bool {
+        matches!(self, Pretty::Format { separator: true, .. })
+    }
+
+    /// 一个commit的所有输出行，decorations为[decorations]的结果
+    pub fn format(&self, commit: &Commit, decorations: &HashMap<Hash, Vec<String>>) -> Vec<String> {
+        let hash = commit.get_hash();
+        let decoration = format_decoration(decorations, &hash, should_colorize());
+        let message = commit.get_message();
+        match self {
+            Pretty::Oneline { abbrev } => {
+                let shown = if *abbrev { &hash[..7] } else { &hash };
+                vec![format!("{}{} {}", shown.yellow(), decoration, subject(&message))]
+            }
+            Pretty::Format { format, .. } => {
+                expand(format, commit, decorations).split('\n').map(String::from).collect()
+            }
+            Pretty::Json => vec![to_json(commit, decorations.get(&hash))],
+            _ => {
+                let mut lines = vec![format!("{}{}", format!("commit {}", hash).yellow(), decoration)];
+                let parents = commit.get_parent_hash();
+                if parents.len() > 1 {
+                    let abbrev = parents.iter().map(|parent| &parent[..7]).collect::<Vec<_>>();
+                    lines.push(format!("Merge: {}", abbrev.join(" ")));
+                }
+                let (author, committer) = (commit.get_author(), commit.get_committer());
+                match self {
+                    Pretty::Short => lines.push(format!("Author: {}", author)),
+                    Pretty::Medium => {
+                        lines.push(format!("Author: {}", author));
+                        lines.push(format!("Date:   {}", commit.get_date()));
+                    }
+                    Pretty::Full => {
+                        lines.push(format!("Author: {}", author));
+                        lines.push(format!("Commit: {}", committer));
+                    }
+                    _ => {
+                        lines.push(format!("Author:     {}", author));
+                        lines.push(format!("AuthorDate: {}", commit.get_date()));
+                        lines.push(format!("Commit:     {}", committer));
+                        lines.push(format!("CommitDate: {}", commit.get_date()));
+                    }
+                }
+                lines.push(String::new());
+                if *self == Pretty::Short {
+                    lines.push(format!("    {}", subject(&message)));
+                } else {
+                    lines.extend(message.lines().map(|line| format!("    {}", line)));
+                }
+                lines.push(String::new());
+                lines
+            }
+        }
+    }
+}
+
+/** 指向每个commit的ref，与Git相同的顺序：`HEAD -> <当前分支>`（detached时为`HEAD`）、`tag: <tag>`、其他分支 */
+pub fn decorations() -> HashMap<Hash, Vec<String>> {
+    let mut refs: HashMap<Hash, Vec<String>> = HashMap::new();
+    let current_branch = match head::current_head() {
+        head::Head::Branch(branch_name) => Some(branch_name),
+        head::Head::Detached(_) => None,
+    };
+    let head_commit = head::current_head_commit();
+    if !head_commit.is_empty() {
+        let label = match &current_branch {
+            Some(branch_name) => format!("HEAD -> {}", branch_name),
+            None => "HEAD".to_string(),
+        };
+        refs.entry(head_commit).or_default().push(label);
+    }
+    let mut tags = head::list_tags();
+    tags.sort();
+    for tag in tags {
+        if let Some(hash) = head::get_tag(&tag) {
+            refs.entry(hash).or_default().push(format!("tag: {}", tag));
+        }
+    }
+    let mut branches = head::list_local_branches();
+    branches.sort();
+    for branch in branches.into_iter().filter(|branch| Some(branch) != current_branch.as_ref()) {
+        let hash = head::get_branch_head(&branch);
+        if !hash.is_empty() {
+            refs.entry(hash).or_default().push(branch);
+        }
+    }
+    refs
+}
+
+/// ` (HEAD -> master, tag: v1, feature)`，colorize时HEAD为蓝色、tag为黄色、分支为绿色；没有ref时为空
+fn format_decoration(decorations: &HashMap<Hash, Vec<String>>, hash: &Hash, colorize: bool) -> String {
+    let Some(refs) = decorations.get(hash) else {
+        return String::new();
+    };
+    if !colorize {
+        return format!(" ({})", refs.join(", "));
+    }
+    let refs = refs
+        .iter()
+        .map(|name| match name {
+            _ if name.starts_with("HEAD") => name.blue().to_string(),
+            _ if name.starts_with("tag: ") => name.yellow().bold().to_string(),
+            _ => name.green().to_string(),
+        })
+        .collect::<Vec<_>>();
+    format!("{}{}{}", " (".yellow(), refs.join(&", ".yellow().to_string()), ")".yellow())
+}
+
+/// 是否输出颜色（输出到终端时），与colored的设置相同
+fn should_colorize() -> bool {
+    colored::control::SHOULD_COLORIZE.should_colorize()
+}
+
+/// 标题：提交信息的第一行
+fn subject(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+/// 正文：标题之后空行以下的部分
+fn body(message: &str) -> &str {
+    message.split_once("\n\n").map(|(_, body)| body).unwrap_or_default()
+}
+
+/** 颜色指令对应的ANSI转义序列：`%Cred`、`%Cgreen`、`%Cblue`、`%Creset`，
+<br>以及`%C(<前景色> [背景色] [bold|dim|ul|blink|reverse])`；不输出颜色时为空
+ */
+fn color_code(spec: &str, colorize: bool) -> Option<String> {
+    const COLORS: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+    let mut codes = Vec::new();
+    let mut foreground = true;
+    for word in spec.split_whitespace() {
+        let code = match word {
+            "reset" | "normal" => 0,
+            "bold" => 1,
+            "dim" => 2,
+            "ul" => 4,
+            "blink" => 5,
+            "reverse" => 7,
+            _ => {
+                let color = COLORS.iter().position(|color| *color == word)? as u8;
+                let base = if foreground { 30 } else { 40 };
+                foreground = false;
+                base + color
+            }
+        };
+        codes.push(code.to_string());
+    }
+    if !colorize {
+        return Some(String::new());
+    }
+    Some(format!("\x1b[{}m", codes.join(";")))
+}
+
+/** 展开占位符，未知的占位符原样输出：
+ * <br>`%H`/`%h`：commit hash/缩写；`%T`/`%t`：tree hash/缩写；`%P`/`%p`：parent hash/缩写（空格分隔）
+ * <br>`%an`/`%ae`/`%ad`/`%ar`/`%at`：作者名/邮箱/日期/相对日期/时间戳，`%cn`/`%ce`/`%cd`/`%cr`/`%ct`：提交者
+ * <br>`%s`：标题；`%b`：正文；`%B`：完整提交信息；`%d`/`%D`：ref（带/不带括号）；`%n`：换行；`%%`：`%`
+ * <br>`%Cred`、`%Cgreen`、`%Cblue`、`%Creset`、`%C(...)`：颜色
+ */
+pub fn expand(format: &str, commit: &Commit, decorations: &HashMap<Hash, Vec<String>>) -> String {
+    expand_with(format, commit, decorations, should_colorize())
+}
+
+/// 展开占位符，colorize为false时颜色指令和`%d`都不输出颜色
+fn expand_with(format: &str, commit: &Commit, decorations: &HashMap<Hash, Vec<String>>, colorize: bool) -> String {
+    let hash = commit.get_hash();
+    let parents = commit.get_parent_hash();
+    let message = commit.get_message();
+    let timestamp = || {
+        let time = commit.get_time();
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .to_string()
+    };
+    let person = |name: String, field: char| match field {
+        'n' => Some(name),
+        'e' => Some(Commit::email(&name)),
+        'd' => Some(commit.get_date()),
+        'r' => Some(date::relative(commit.get_time())),
+        't' => Some(timestamp()),
+        _ => None,
+    };
+
+    let mut result = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('%') {
+        result += &rest[..start];
+        rest = &rest[start + 1..];
+        let mut chars = rest.chars();
+        let (value, len) = match (chars.next(), chars.next()) {
+            (Some('H'), _) => (Some(hash.clone()), 1),
+            (Some('h'), _) => (Some(hash[..7].to_string()), 1),
+            (Some('T'), _) => (Some(commit.get_tree_hash()), 1),
+            (Some('t'), _) => (Some(commit.get_tree_hash()[..7].to_string()), 1),
+            (Some('P'), _) => (Some(parents.join(" ")), 1),
+            (Some('p'), _) => (Some(parents.iter().map(|p| &p[..7]).collect::<Vec<_>>().join(" ")), 1),
+            (Some('a'), Some(field)) => (person(commit.get_author(), field), 2),
+            (Some('c'), Some(field)) => (person(commit.get_committer(), field), 2),
+            (Some('s'), _) => (Some(subject(&message).to_string()), 1),
+            (Some('b'), _) => (Some(body(&message).to_string()), 1),
+            (Some('B'), _) => (Some(message.clone()), 1),
+            (Some('d'), _) => (Some(format_decoration(decorations, &hash, colorize)), 1),
+            (Some('D'), _) => (Some(decorations.get(&hash).map(|refs| refs.join(", ")).unwrap_or_default()), 1),
+            (Some('n'), _) => (Some("\n".to_string()), 1),
+            (Some('%'), _) => (Some("%".to_string()), 1),
+            (Some('C'), _) => {
+                let named = ["red", "green", "blue", "reset"]
+                    .into_iter()
+                    .find(|name| rest[1..].starts_with(name));
+                match named {
+                    Some(name) => (color_code(name, colorize), 1 + name.len()),
+                    None => match rest[1..].strip_prefix('(').and_then(|spec| spec.split_once(')')) {
+                        Some((spec, _)) => (color_code(spec, colorize), spec.len() + 3),
+                        None => (None, 0),
+                    },
+                }
+            }
+            _ => (None, 0),
+        };
+        match value {
+            Some(value) => {
+                result += &value;
+                rest = &rest[len..];
+            }
+            None => result.push('%'), // 未知占位符，原样输出
+        }
+    }
+    result + rest
+}
+
+/// 一行JSON：hash、tree、parents、author、committer、date、timestamp、message、refs
+fn to_json(commit: &Commit, refs: Option<&Vec<String>>) -> String {
+    let time = commit.get_time().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+    serde_json::json!({
+        "hash": commit.get_hash(),
+        "tree": commit.get_tree_hash(),
+        "parents": commit.get_parent_hash(),
+        "author": commit.get_author(),
+        "committer": commit.get_committer(),
+        "date": commit.get_date(),
+        "timestamp": time.as_secs(),
+        "message": commit.get_message(),
+        "refs": refs.cloned().unwrap_or_default(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{commands as cmd, utils::test};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Pretty::parse("oneline"), Ok(Pretty::Oneline { abbrev: false }));
+        assert_eq!(Pretty::parse("fuller"), Ok(Pretty::Fuller));
+        assert_eq!(Pretty::parse("format:%h"), Ok(Pretty::Format { format: "%h".into(), separator: true }));
+        assert_eq!(Pretty::parse("tformat:%h %s"), Ok(Pretty::Format { format: "%h %s".into(), separator: false }));
+        assert_eq!(Pretty::parse("%H"), Ok(Pretty::Format { format: "%H".into(), separator: false }));
+        assert!(Pretty::parse("format:%h").unwrap().is_separator());
+        assert!(!Pretty::parse("%h").unwrap().is_separator());
+        assert_eq!(Pretty::parse("json"), Ok(Pretty::Json));
+        assert!(Pretty::parse("no_such_format").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        test::setup_with_empty_workdir();
+        cmd::commit("first".to_string(), true);
+        cmd::commit("second\n\nbody line".to_string(), true);
+        let decorations = decorations();
+        let commit = Commit::load(&head::current_head_commit());
+        let (hash, parent) = (commit.get_hash(), commit.get_parent_hash()[0].clone());
+
+        let format = |pretty: Pretty| pretty.format(&commit, &decorations);
+        assert_eq!(format(Pretty::Oneline { abbrev: true }), [format!("{} (HEAD -> master) second", &hash[..7])]);
+        assert_eq!(format(Pretty::Short).len(), 5);
+        assert_eq!(format(Pretty::Medium)[3], "");
+        assert_eq!(format(Pretty::Medium)[4..], ["    second", "    ", "    body line", ""]);
+        assert_eq!(format(Pretty::Full)[2], "Commit: mit-author");
+        assert_eq!(format(Pretty::Fuller)[3], "Commit:     mit-author");
+
+        let expand = |format: &str, colorize: bool| expand_with(format, &commit, &decorations, colorize);
+        let custom = "%h %p%n%an <%ae> %s|%b|%D%d %% %x %Cred%C(bold blue)%Creset";
+        let expected = format!(
+            "{} {}\nmit <mit@mit> second|body line|HEAD -> master (HEAD -> master) % %x ",
+            &hash[..7],
+            &parent[..7]
+        );
+        assert_eq!(expand(custom, false), expected);
+        let expected = format!("\x1b[31m{}\x1b[1;34m!\x1b[0m", &hash[..7]);
+        assert_eq!(expand("%Cred%h%C(bold blue)!%Creset", true), expected);
+        assert!(expand("%ar", false).ends_with(" ago"));
+        assert_eq!(expand("%at", false), expand("%ct", false));
+        assert_eq!(format(Pretty::parse("%h%n%s").unwrap()), [&hash[..7], "second"]);
+
+        let json: serde_json::Value = serde_json::from_str(&format(Pretty::Json)[0]).unwrap();
+        assert_eq!(json["hash"], hash);
+        assert_eq!(json["parents"], serde_json::json!([parent]));
+        assert_eq!(json["message"], "second\n\nbody line");
+        assert_eq!(json["refs"], serde_json::json!(["HEAD -> master"]));
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashSet;
+
+use crate::models::{head, reflog, Commit, Hash, Index, Tree};
+
+/// 所有ref指向的commit：HEAD & refs/heads/* & MERGE_HEAD & ORIG_HEAD，不存在的(未提交的分支)会被忽略
+/// <br>与Git相同，reflog中每条记录的old & new也包括在内，以便通过`HEAD@{n}`等找回
+pub fn ref_commits() -> Vec<Hash> {
+    let mut commits = vec![head::current_head_commit()];
+    commits.extend(head::merge_head());
+    commits.extend(head::orig_head());
+    for branch in head::list_local_branches() {
+        commits.push(head::get_branch_head(&branch));
+    }
+    for entry in reflog::read_all() {
+        commits.extend([entry.old, entry.new]);
+    }
+    commits
+        .into_iter()
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .collect()
+}
+
+/** 从tips出发，遍历所有可达的object（commit & parent & tree & blob）
+<br>不存在或损坏的object同样会被加入结果（以便检查），但不会继续遍历
+ */
+pub fn walk_from(tips: impl IntoIterator<Item = Hash>) -> HashSet<Hash> {
+    let mut reachable = HashSet::new();
+    let mut commits: Vec<Hash> = tips.into_iter().collect();
+    let mut trees: Vec<Hash> = Vec::new();
+    while let Some(hash) = commits.pop() {
+        if !reachable.insert(hash.clone()) {
+            continue;
+        }
+        if let Some(commit) = Commit::try_load(&hash) {
+            trees.push(commit.get_tree_hash());
+            commits.extend(commit.get_parent_hash());
+        }
+    }
+    while let Some(hash) = trees.pop() {
+        if !reachable.insert(hash.clone()) {
+            continue;
+        }
+        if let Some(tree) = Tree::try_load(&hash) {
+            for entry in tree.entries {
+                if entry.filemode.0 == "tree" {
+                    trees.push(entry.object_hash);
+                } else {
+                    reachable.insert(entry.object_hash);
+                }
+            }
+        }
+    }
+    reachable
+}
+
+/// 从所有ref、HEAD以及index（包括冲突文件的各个版本）出发的可达object，这些object不能被删除
+pub fn reachable_objects() -> HashSet<Hash> {
+    let mut reachable = walk_from(ref_commits());
+    let index = Index::get_instance();
+    reachable.extend(index.get_tracked_entries().into_values().map(|meta| meta.hash));
+    for conflict in index.get_conflicts().into_values() {
+        reachable.extend((1..=3).filter_map(|stage| conflict.stage(stage)));
+    }
+    reachable
+}
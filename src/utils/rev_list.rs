@@ -0,0 +1,435 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+use crate::models::{Commit, Hash};
+
+use super::{merge_base::CommitGraph, revision};
+
+/// commit的输出顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Order {
+    /// 按提交时间从新到旧（时间相同时子commit在前）
+    #[default]
+    Default,
+    /// 子commit一定在parent之前，其余按提交时间从新到旧
+    Date,
+    /// 子commit一定在parent之前，并且同一条分支上的commit连续输出
+    Topo,
+}
+
+/** 要遍历的commit集合：从include出发可达，且从exclude出发不可达
+<br>paths非空时按路径简化历史，见[PathFilter]
+ */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RevSet {
+    pub include: Vec<Hash>,
+    pub exclude: Vec<Hash>,
+    pub paths: Vec<PathBuf>, // 相对于workdir，空路径为整个仓库
+}
+
+impl RevSet {
+    /** 解析版本参数：
+     * <br>`<rev>`：包含rev及其祖先；`^<rev>`：排除rev及其祖先
+     * <br>`A..B`：即`^A B`；`A...B`：即`A B ^merge-base(A, B)`，两侧省略时为HEAD
+     */
+    pub fn parse(args: &[String]) -> Result<RevSet, String> {
+        let resolve = |name: &str| {
+            let name = if name.is_empty() { "HEAD" } else { name };
+            revision::resolve_commit(name).ok_or_else(|| revision::unknown_revision(name))
+        };
+        let mut set = RevSet::default();
+        for arg in args {
+            if let Some((a, b)) = arg.split_once("...") {
+                let (a, b) = (resolve(a)?, resolve(b)?);
+                set.exclude.extend(CommitGraph::new().merge_bases(&a, &b));
+                set.include.extend([a, b]);
+            } else if let Some((a, b)) = arg.split_once("..") {
+                set.exclude.push(resolve(a)?);
+                set.include.push(resolve(b)?);
+            } else if let Some(name) = arg.strip_prefix('^') {
+                set.exclude.push(resolve(name)?);
+            } else {
+                set.include.push(resolve(arg)?);
+            }
+        }
+        Ok(set)
+    }
+}
+
+/** 按路径简化历史，与Git默认的history simplification相同：
+ * <br>commit与parent在paths下的内容（tree中对应的object）相同时称为TREESAME，不输出；root commit与空tree比较
+ * <br>merge commit与某个parent TREESAME时，只沿该parent遍历（另一侧的修改没有进入merge的结果）
+ */
+#[derive(Debug, Default)]
+struct PathFilter {
+    paths: Vec<PathBuf>,
+    objects: HashMap<Hash, Vec<Option<Hash>>>,
+    simplified: HashMap<Hash, (bool, Vec<Hash>)>, // (是否TREESAME, 要遍历的parent)
+}
+
+impl PathFilter {
+    fn new(paths: &[PathBuf]) -> PathFilter {
+        PathFilter { paths: paths.to_vec(), ..Default::default() }
+    }
+
+    /// commit的tree中每个path对应的object，不存在时为None
+    fn objects(&mut self, hash: &Hash) -> Vec<Option<Hash>> {
+        if let Some(objects) = self.objects.get(hash) {
+            return objects.clone();
+        }
+        let tree = Commit::load(hash).get_tree();
+        let objects = self
+            .paths
+            .iter()
+            .map(|path| match path.as_os_str().is_empty() {
+                true => Some(tree.get_hash()),
+                false => tree.find(path).map(|entry| entry.object_hash),
+            })
+            .collect::<Vec<_>>();
+        self.objects.insert(hash.clone(), objects.clone());
+        objects
+    }
+
+    fn simplify(&mut self, graph: &mut CommitGraph, hash: &Hash) -> (bool, Vec<Hash>) {
+        let parents = graph.parents(hash);
+        if self.paths.is_empty() {
+            return (false, parents);
+        }
+        if let Some(result) = self.simplified.get(hash) {
+            return result.clone();
+        }
+        let objects = self.objects(hash);
+        let result = match parents.is_empty() {
+            true => (objects.iter().all(Option::is_none), parents),
+            false => match parents.iter().find(|parent| self.objects(parent) == objects) {
+                Some(same) => (true, vec![same.clone()]),
+                None => (false, parents),
+            },
+        };
+        self.simplified.insert(hash.clone(), result.clone());
+        result
+    }
+}
+
+/// 从starts出发可达的所有commit（包括自身），遇到stop中的commit时停止；filter为None时遍历所有parent
+fn reachable(
+    graph: &mut CommitGraph,
+    starts: &[Hash],
+    stop: &HashSet<Hash>,
+    mut filter: Option<&mut PathFilter>,
+) -> HashSet<Hash> {
+    let mut visited = HashSet::new();
+    let mut stack = starts.iter().filter(|hash| !stop.contains(*hash)).cloned().collect::<Vec<_>>();
+    while let Some(hash) = stack.pop() {
+        if !visited.insert(hash.clone()) {
+            continue;
+        }
+        let parents = match filter.as_deref_mut() {
+            Some(filter) => filter.simplify(graph, &hash).1,
+            None => graph.parents(&hash),
+        };
+        for parent in parents {
+            if !stop.contains(&parent) && !visited.contains(&parent) {
+                stack.push(parent);
+            }
+        }
+    }
+    visited
+}
+
+/** 按order列出集合中的所有commit，merge commit的所有parent都会被遍历（按路径简化时除外）
+<br>默认顺序且不按路径简化时逐个读取commit，调用者提前停止（如`log -n`）时不会读取全部历史；
+否则需要先读取集合中的所有commit再排序
+<br>`--reverse`由调用者在筛选之后处理（与Git相同，先限制数量再反转）
+ */
+pub fn walk(set: &RevSet, order: Order) -> Box<dyn Iterator<Item = Hash>> {
+    let mut graph = CommitGraph::new();
+    let excluded = reachable(&mut graph, &set.exclude, &HashSet::new(), None);
+    if order == Order::Default && set.paths.is_empty() {
+        return Box::new(DateWalk::new(graph, &set.include, excluded));
+    }
+    let mut filter = PathFilter::new(&set.paths);
+    let selected = reachable(&mut graph, &set.include, &excluded, Some(&mut filter));
+    let mut result = order_commits(&mut graph, &selected, order);
+    if !set.paths.is_empty() {
+        result.retain(|hash| !filter.simplify(&mut graph, hash).0);
+    }
+    Box::new(result.into_iter())
+}
+
+/** 按提交时间从新到旧逐个输出commit：队列中只保存已输出commit的parent，与Git默认的遍历方式相同
+<br>时间相同时先入队的在前，因此parent一定在它的子commit之后
+ */
+struct DateWalk {
+    graph: CommitGraph,
+    excluded: HashSet<Hash>,
+    visited: HashSet<Hash>,
+    queue: BinaryHeap<(SystemTime, Reverse<usize>, Hash)>, // (提交时间, 入队序号, hash)
+    count: usize,
+}
+
+impl DateWalk {
+    fn new(graph: CommitGraph, starts: &[Hash], excluded: HashSet<Hash>) -> DateWalk {
+        let queue = BinaryHeap::new();
+        let mut walk = DateWalk { graph, excluded, visited: HashSet::new(), queue, count: 0 };
+        starts.iter().for_each(|hash| walk.push(hash));
+        walk
+    }
+
+    fn push(&mut self, hash: &Hash) {
+        if self.excluded.contains(hash) || !self.visited.insert(hash.clone()) {
+            return;
+        }
+        let time = Commit::load(hash).get_time();
+        self.queue.push((time, Reverse(self.count), hash.clone()));
+        self.count += 1;
+    }
+}
+
+impl Iterator for DateWalk {
+    type Item = Hash;
+
+    fn next(&mut self) -> Option<Hash> {
+        let (_, _, hash) = self.queue.pop()?;
+        for parent in self.graph.parents(&hash) {
+            self.push(&parent);
+        }
+        Some(hash)
+    }
+}
+
+/// 按order排列commit
+fn order_commits(graph: &mut CommitGraph, selected: &HashSet<Hash>, order: Order) -> Vec<Hash> {
+    let mut times: HashMap<Hash, SystemTime> = HashMap::new();
+    let mut key = |graph: &mut CommitGraph, hash: &Hash| {
+        let time = *times.entry(hash.clone()).or_insert_with(|| Commit::load(hash).get_time());
+        (time, graph.generation(hash), Reverse(hash.clone()))
+    };
+    let mut keys = selected.iter().map(|hash| (key(graph, hash), hash.clone())).collect::<Vec<_>>();
+    keys.sort_by(|a, b| b.0.cmp(&a.0));
+    if order == Order::Default {
+        return keys.into_iter().map(|(_, hash)| hash).collect();
+    }
+
+    // 拓扑排序：所有子commit都输出之后，parent才能输出
+    let mut children = HashMap::<Hash, usize>::new();
+    for hash in selected {
+        for parent in graph.parents(hash) {
+            if selected.contains(&parent) {
+                *children.entry(parent).or_default() += 1;
+            }
+        }
+    }
+    let key_of = keys.iter().cloned().map(|(key, hash)| (hash, key)).collect::<HashMap<_, _>>();
+    let tips = keys
+        .iter()
+        .filter(|(_, hash)| !children.contains_key(hash))
+        .map(|(_, hash)| hash.clone());
+    let mut result = Vec::with_capacity(selected.len());
+    match order {
+        Order::Topo => {
+            // 栈：沿着一条分支一直走到merge的位置，再回到其他分支；最新的tip在栈顶
+            let mut stack = tips.collect::<Vec<_>>();
+            stack.reverse();
+            while let Some(hash) = stack.pop() {
+                for parent in graph.parents(&hash) {
+                    if let Some(count) = children.get_mut(&parent) {
+                        *count -= 1;
+                        if *count == 0 {
+                            stack.push(parent);
+                        }
+                    }
+                }
+                result.push(hash);
+            }
+        }
+        _ => {
+            let mut queue = tips.map(|hash| (key_of[&hash].clone(), hash)).collect::<BinaryHeap<_>>();
+            while let Some((_, hash)) = queue.pop() {
+                for parent in graph.parents(&hash) {
+                    if let Some(count) = children.get_mut(&parent) {
+                        *count -= 1;
+                        if *count == 0 {
+                            queue.push((key_of[&parent].clone(), parent));
+                        }
+                    }
+                }
+                result.push(hash);
+            }
+        }
+    }
+    result
+}
+
+/** 把parents改写为最近的、在shown中的祖先（中间的commit没有输出），用于`log --graph`连接分支线
+<br>返回shown中每个commit改写后的parent
+ */
+pub fn rewrite_parents(shown: &[Hash]) -> HashMap<Hash, Vec<Hash>> {
+    let mut graph = CommitGraph::new();
+    let shown_set = shown.iter().cloned().collect::<HashSet<_>>();
+    // 未输出的commit在shown中最近的祖先，后序遍历计算
+    let mut nearest: HashMap<Hash, Vec<Hash>> = HashMap::new();
+    let mut result = HashMap::new();
+    for hash in shown {
+        let mut stack = graph
+            .parents(hash)
+            .into_iter()
+            .filter(|p| !shown_set.contains(p))
+            .collect::<Vec<_>>();
+        while let Some(top) = stack.last().cloned() {
+            if nearest.contains_key(&top) {
+                stack.pop();
+                continue;
+            }
+            let parents = graph.parents(&top);
+            let pending = parents
+                .iter()
+                .filter(|p| !shown_set.contains(*p) && !nearest.contains_key(*p))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !pending.is_empty() {
+                stack.extend(pending);
+                continue;
+            }
+            let ancestors = nearest_shown(&parents, &shown_set, &nearest);
+            nearest.insert(top, ancestors);
+            stack.pop();
+        }
+        let parents = nearest_shown(&graph.parents(hash), &shown_set, &nearest);
+        result.insert(hash.clone(), parents);
+    }
+    result
+}
+
+/// parents中每个commit在shown中最近的祖先（本身在shown中时为自身），去重
+fn nearest_shown(parents: &[Hash], shown: &HashSet<Hash>, nearest: &HashMap<Hash, Vec<Hash>>) -> Vec<Hash> {
+    let mut result = Vec::new();
+    for parent in parents {
+        let found = match shown.contains(parent) {
+            true => std::slice::from_ref(parent),
+            false => nearest[parent].as_slice(),
+        };
+        for ancestor in found {
+            if !result.contains(ancestor) {
+                result.push(ancestor.clone());
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path, thread, time::Duration};
+
+    use super::*;
+    use crate::{
+        commands as cmd,
+        models::head,
+        utils::{test, util},
+    };
+
+    fn commit(message: &str) -> Hash {
+        thread::sleep(Duration::from_millis(5)); // 保证提交时间不同
+        cmd::commit(message.to_string(), true);
+        head::current_head_commit()
+    }
+
+    fn list(args: &[&str], order: Order) -> Vec<Hash> {
+        let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        walk(&RevSet::parse(&args).unwrap(), order).collect()
+    }
+
+    #[test]
+    fn test_walk() {
+        test::setup_with_empty_workdir();
+        let base = commit("base");
+        cmd::switch(None, Some("feature".to_string()), false);
+        let f1 = commit("f1");
+        cmd::switch(Some("master".to_string()), None, false);
+        let m1 = commit("m1");
+        let f2 = {
+            cmd::switch(Some("feature".to_string()), None, false);
+            commit("f2")
+        };
+        cmd::switch(Some("master".to_string()), None, false);
+        cmd::merge(Some("feature".to_string()), false, false, Default::default());
+        let merge = head::current_head_commit();
+
+        assert_eq!(list(&["HEAD"], Order::Default), [&merge, &f2, &m1, &f1, &base].map(Hash::clone));
+        assert_eq!(list(&["HEAD"], Order::Date), [&merge, &f2, &m1, &f1, &base].map(Hash::clone));
+        // 最后一个parent（feature）所在的分支先输出，且连续
+        assert_eq!(list(&["HEAD"], Order::Topo), [&merge, &f2, &f1, &m1, &base].map(Hash::clone));
+
+        assert_eq!(list(&["feature..master"], Order::Default), [&merge, &m1].map(Hash::clone));
+        assert_eq!(list(&["master", "^feature"], Order::Default), [&merge, &m1].map(Hash::clone));
+        assert_eq!(list(&["master..feature"], Order::Default), Vec::<Hash>::new());
+        assert_eq!(list(&["HEAD~1...feature"], Order::Default), [&f2, &m1, &f1].map(Hash::clone));
+        assert_eq!(list(&["HEAD~1", "feature", &format!("^{}", base)], Order::Topo).len(), 3);
+        assert_eq!(list(&["..feature"], Order::Default), Vec::<Hash>::new());
+
+        let args = ["no_such_branch..master".to_string()];
+        assert_eq!(RevSet::parse(&args), Err(revision::unknown_revision("no_such_branch")));
+    }
+
+    #[test]
+    fn test_walk_lazy() {
+        test::setup_with_empty_workdir();
+        let first = commit("first");
+        commit("second");
+        let third = commit("third");
+        // 默认顺序逐个读取commit，只取最新的commit时不会读取root commit
+        fs::remove_file(util::get_storage_path().unwrap().join("objects").join(&first)).unwrap();
+        let set = RevSet::parse(&["HEAD".into()]).unwrap();
+        assert_eq!(walk(&set, Order::Default).take(1).collect::<Vec<_>>(), [&third].map(Hash::clone));
+    }
+
+    #[test]
+    fn test_walk_paths() {
+        test::setup_with_empty_workdir();
+        let commit_file = |path: &str, content: &str, message: &str| {
+            test::ensure_file(Path::new(path), Some(content));
+            cmd::add(vec![], true, false);
+            commit(message)
+        };
+        let paths = |paths: &[&str]| {
+            let set = RevSet {
+                paths: paths.iter().map(PathBuf::from).collect(),
+                ..RevSet::parse(&["HEAD".into()]).unwrap()
+            };
+            walk(&set, Order::Topo).collect::<Vec<_>>()
+        };
+        let init = commit_file("src/a", "a\n", "init");
+        commit_file("doc/d", "d\n", "doc");
+        cmd::switch(None, Some("feature".to_string()), false);
+        let feature = commit_file("src/a", "a\nb\n", "feature: src");
+        let feature_doc = commit_file("doc/d", "d\nx\n", "feature: doc");
+        cmd::switch(Some("master".to_string()), None, false);
+        let master_doc = commit_file("doc/e", "e\n", "master: doc");
+        cmd::merge(Some("feature".to_string()), false, false, Default::default());
+        let merge = head::current_head_commit();
+
+        // merge与feature的src相同，只沿feature遍历；master上的commit没有修改src
+        assert_eq!(paths(&["src"]), [&feature, &init].map(Hash::clone));
+        assert_eq!(paths(&["src/a"]), [&feature, &init].map(Hash::clone));
+        // 两侧都修改了doc，merge的结果与两个parent都不同
+        assert_eq!(paths(&["doc"]).len(), 4);
+        assert_eq!(paths(&["doc"])[..2], [merge.clone(), feature_doc.clone()]);
+        assert_eq!(paths(&["doc/e"]), [&master_doc].map(Hash::clone));
+        // merge的src与master不同、doc/e与feature不同
+        assert_eq!(paths(&["src", "doc/e"]), [&merge, &feature, &master_doc, &init].map(Hash::clone));
+        assert_eq!(paths(&["no_such_file"]), Vec::<Hash>::new());
+        assert_eq!(paths(&[""]).len(), 6);
+
+        // 改写parent：跳过没有输出的commit
+        let rewritten = rewrite_parents(&[merge.clone(), feature.clone(), init.clone()]);
+        assert_eq!(rewritten[&merge], [init.clone(), feature.clone()]);
+        assert_eq!(rewritten[&feature], [&init].map(Hash::clone));
+        assert_eq!(rewritten[&init], Vec::<Hash>::new());
+        assert_eq!(rewrite_parents(std::slice::from_ref(&merge))[&merge], Vec::<Hash>::new());
+    }
+}
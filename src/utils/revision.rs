@@ -0,0 +1,257 @@
+use std::{
+    collections::{BinaryHeap, HashSet},
+    path::Path,
+};
+
+use regex::Regex;
+
+use crate::models::{head, reflog, Commit, Hash, Index};
+
+use super::{merge_base::CommitGraph, store::Store, util};
+
+/*Revision
+* 版本表达式，所有命令统一通过此模块解析：
+* - `<hash>`：完整或缩写（唯一前缀）的object hash
+* - `HEAD`、`@`、`ORIG_HEAD`、`MERGE_HEAD`、分支名、tag名（refs/tags）
+* - `<rev>~<n>`：第一个parent的第n代祖先；`<rev>^<n>`：第n个parent，`^0`为自身；可连续使用，如`HEAD~2^2`
+* - `@{-<n>}`：之前第n次切换离开的分支（或commit）
+* - `[<ref>]@{<n>}`：ref在reflog中n次移动之前的位置，省略ref时为当前分支
+* - `:/<regex>`：从所有分支可达的commit中，提交信息匹配regex的最新commit
+* - `<rev>:<path>`：commit中的文件或目录（path相对于workdir根目录，为空时为根tree）；`:<path>`为暂存区中的文件
+*/
+
+/// 无法解析时统一的错误信息
+pub fn unknown_revision(name: &str) -> String {
+    format!("fatal: 无法解析的版本: '{}'", name)
+}
+
+/// 解析为任意object
+pub fn resolve(rev: &str) -> Option<Hash> {
+    if let Some(pattern) = rev.strip_prefix(":/") {
+        return search_message(pattern);
+    }
+    if let Some((rev, path)) = rev.split_once(':') {
+        return resolve_path(rev, path);
+    }
+    // 第一个`~`或`^`之前为基础部分，`@{...}`属于基础部分
+    let split = rev.find(['~', '^']).unwrap_or(rev.len());
+    let (base, suffix) = rev.split_at(split);
+    let mut hash = resolve_base(base)?;
+    let mut rest = suffix;
+    while let Some(op) = rest.chars().next() {
+        let digits = rest[1..].chars().take_while(|c| c.is_ascii_digit()).count();
+        let n = if digits == 0 {
+            1
+        } else {
+            rest[1..1 + digits].parse::<usize>().ok()?
+        };
+        rest = &rest[1 + digits..];
+        hash = peel_commit(&hash)?;
+        hash = match op {
+            '~' => (0..n).try_fold(hash, |hash, _| Commit::load(&hash).get_parent_hash().first().cloned())?,
+            _ if n == 0 => hash,
+            _ => Commit::load(&hash).get_parent_hash().get(n - 1).cloned()?,
+        };
+    }
+    Some(hash)
+}
+
+/// 解析为commit，结果不是commit时返回None
+pub fn resolve_commit(rev: &str) -> Option<Hash> {
+    resolve(rev).filter(|hash| util::is_typeof_commit(hash.clone()))
+}
+
+/** `@{-n}`：之前第n次切换离开的分支名（或detached时的commit hash），来自HEAD的reflog
+<br>用于`mit switch @{-1}`等需要分支名而不是commit的场景
+ */
+pub fn previous_branch(n: usize) -> Option<String> {
+    reflog::read(reflog::HEAD)
+        .iter()
+        .rev()
+        .filter_map(|entry| entry.message.strip_prefix("checkout: moving from "))
+        .filter_map(|moving| moving.split_once(" to ").map(|(from, _)| from.to_string()))
+        .nth(n.checked_sub(1)?)
+}
+
+/** 将`@{-n}`（以及`-`，即`@{-1}`）展开为之前的分支名，其他名字不变
+<br>`switch`、`merge`需要分支名，以便切换到分支（而不是detached）或生成合并信息
+ */
+pub fn expand_previous_branch(name: &str) -> String {
+    let n = match name {
+        "-" => Some(1),
+        _ => name
+            .strip_prefix("@{-")
+            .and_then(|n| n.strip_suffix('}'))
+            .and_then(|n| n.parse().ok()),
+    };
+    n.and_then(previous_branch).unwrap_or_else(|| name.to_string())
+}
+
+fn peel_commit(hash: &Hash) -> Option<Hash> {
+    Some(hash.clone()).filter(|hash| util::is_typeof_commit(hash.clone()))
+}
+
+/// 不含`~`、`^`的部分：ref名、hash、`@{...}`
+fn resolve_base(base: &str) -> Option<Hash> {
+    if let Some(start) = base.find("@{") {
+        let selector = base[start + 2..].strip_suffix('}')?;
+        let name = &base[..start];
+        if let Some(n) = selector.strip_prefix('-') {
+            if !name.is_empty() {
+                return None;
+            }
+            let previous = previous_branch(n.parse().ok()?)?;
+            return resolve_ref(&previous).or_else(|| Store::new().search(&previous));
+        }
+        return resolve_reflog(name, selector.parse().ok()?);
+    }
+    if base.is_empty() || base == "@" {
+        return resolve_ref("HEAD");
+    }
+    resolve_ref(base).or_else(|| search_hash(base))
+}
+
+/// HEAD、特殊ref、分支、tag
+fn resolve_ref(name: &str) -> Option<Hash> {
+    let hash = match name {
+        "HEAD" => head::current_head_commit(),
+        head::ORIG_HEAD => head::orig_head()?,
+        head::MERGE_HEAD => head::merge_head()?,
+        _ if head::list_local_branches().contains(&name.to_string()) => head::get_branch_head(&name.to_string()),
+        _ => head::get_tag(name)?,
+    };
+    Some(hash.trim().to_string()).filter(|hash| !hash.is_empty())
+}
+
+/// 完整或缩写的hash，只接受16进制字符
+fn search_hash(prefix: &str) -> Option<Hash> {
+    if !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Store::new().search(&prefix.to_string())
+}
+
+/// `<ref>@{n}`：reflog中倒数第n+1条记录的新位置，`@{0}`即当前位置
+fn resolve_reflog(name: &str, n: usize) -> Option<Hash> {
+    let ref_name = match name {
+        "" => match head::current_head() {
+            head::Head::Branch(branch) => reflog::branch_ref(&branch),
+            head::Head::Detached(_) => reflog::HEAD.to_string(),
+        },
+        "HEAD" | "@" => reflog::HEAD.to_string(),
+        _ => reflog::branch_ref(name),
+    };
+    let entries = reflog::read(&ref_name);
+    entries.iter().rev().nth(n).map(|entry| entry.new.clone())
+}
+
+/// `:/regex`：按generation从新到旧遍历所有分支可达的commit
+fn search_message(pattern: &str) -> Option<Hash> {
+    let regex = Regex::new(pattern).ok()?;
+    let mut graph = CommitGraph::new();
+    let mut starts = head::list_local_branches()
+        .iter()
+        .filter_map(|branch| resolve_ref(branch))
+        .collect::<Vec<_>>();
+    starts.extend(resolve_ref("HEAD"));
+    let mut visited = starts.iter().cloned().collect::<HashSet<_>>();
+    let mut queue = starts
+        .into_iter()
+        .map(|hash| (graph.generation(&hash), hash))
+        .collect::<BinaryHeap<_>>();
+    while let Some((_, hash)) = queue.pop() {
+        if regex.is_match(&Commit::load(&hash).get_message()) {
+            return Some(hash);
+        }
+        for parent in graph.parents(&hash) {
+            if visited.insert(parent.clone()) {
+                queue.push((graph.generation(&parent), parent));
+            }
+        }
+    }
+    None
+}
+
+/// `<rev>:<path>`；rev为空时从暂存区中查找文件
+fn resolve_path(rev: &str, path: &str) -> Option<Hash> {
+    let path = path.trim_start_matches("./").trim_end_matches('/');
+    if rev.is_empty() {
+        let index = Index::get_instance();
+        return index.get_hash(&util::to_workdir_absolute_path(Path::new(path)));
+    }
+    let commit = resolve_commit(rev)?;
+    let tree = Commit::load(&commit).get_tree();
+    if path.is_empty() {
+        return Some(tree.get_hash());
+    }
+    tree.find(Path::new(path)).map(|entry| entry.object_hash)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{commands as cmd, models::ObjectType, utils::test};
+
+    fn object_type(rev: &str) -> ObjectType {
+        Store::new().object_type(&resolve(rev).unwrap())
+    }
+
+    #[test]
+    fn test_resolve() {
+        test::setup_with_empty_workdir();
+        assert_eq!(resolve("HEAD"), None);
+        test::ensure_file(&PathBuf::from("a.txt"), Some("1\n"));
+        cmd::add(vec![], true, false);
+        cmd::commit("first".to_string(), false);
+        let first = head::current_head_commit();
+        cmd::switch(None, Some("feature".to_string()), false);
+        cmd::commit("feature work".to_string(), true);
+        let feature = head::current_head_commit();
+        cmd::switch(Some("master".to_string()), None, false);
+        cmd::commit("second".to_string(), true);
+        let second = head::current_head_commit();
+        cmd::merge(Some("feature".to_string()), false, false, Default::default());
+        let merge = head::current_head_commit();
+
+        assert_eq!(resolve("HEAD"), Some(merge.clone()));
+        assert_eq!(resolve("@"), Some(merge.clone()));
+        assert_eq!(resolve("master"), Some(merge.clone()));
+        assert_eq!(resolve(&merge[..7]), Some(merge.clone()));
+        assert_eq!(resolve("HEAD~"), Some(second.clone()));
+        assert_eq!(resolve("HEAD^1"), Some(second.clone()));
+        assert_eq!(resolve("HEAD^2"), Some(feature.clone()));
+        assert_eq!(resolve("HEAD~2"), Some(first.clone()));
+        assert_eq!(resolve("master^2~1"), Some(first.clone()));
+        assert_eq!(resolve("HEAD^0"), Some(merge.clone()));
+        assert_eq!(resolve("HEAD~3"), None);
+        assert_eq!(resolve("HEAD^3"), None);
+        assert_eq!(resolve("no_such_branch"), None);
+
+        // reflog
+        assert_eq!(resolve("@{0}"), Some(merge.clone()));
+        assert_eq!(resolve("@{1}"), Some(second.clone()));
+        assert_eq!(resolve("master@{2}"), Some(first.clone()));
+        assert_eq!(resolve("master@{9}"), None);
+        assert_eq!(previous_branch(1), Some("feature".to_string()));
+        assert_eq!(previous_branch(2), Some("master".to_string()));
+        assert_eq!(resolve("@{-1}"), Some(feature.clone()));
+        assert_eq!(resolve("@{-1}~1"), Some(first.clone()));
+        assert_eq!(expand_previous_branch("-"), "feature");
+        assert_eq!(expand_previous_branch("@{-2}"), "master");
+        assert_eq!(expand_previous_branch("@{-9}"), "@{-9}");
+        assert_eq!(expand_previous_branch("HEAD"), "HEAD");
+
+        // :/regex & rev:path
+        assert_eq!(resolve(":/feature"), Some(merge.clone())); // Merge branch 'feature'
+        assert_eq!(resolve(":/^feature"), Some(feature.clone()));
+        assert_eq!(resolve(":/^fir"), Some(first.clone()));
+        assert_eq!(resolve(":/no such message"), None);
+        let blob = resolve("HEAD~2:a.txt").unwrap();
+        assert_eq!(object_type("HEAD~2:a.txt"), ObjectType::Blob);
+        assert_eq!(resolve(":a.txt"), Some(blob));
+        assert_eq!(object_type("HEAD:"), ObjectType::Tree);
+        assert_eq!(resolve_commit("HEAD:a.txt"), None);
+    }
+}
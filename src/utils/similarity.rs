@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+/// 默认的相似度阈值（与Git相同），`-M`、`-C`不指定数值时使用
+pub const DEFAULT_THRESHOLD: usize = 50;
+
+/** 解析相似度阈值，与Git相同：`50%`为百分比；不带`%`时视为小数部分，即`5`为50%，`05`为5%，`95`为95%
+ */
+pub fn parse_threshold(value: &str) -> Result<usize, String> {
+    let invalid = || format!("invalid similarity: '{}'", value);
+    if let Some(percent) = value.strip_suffix('%') {
+        let percent = percent.parse::<usize>().map_err(|_| invalid())?;
+        return if percent <= 100 { Ok(percent) } else { Err(invalid()) };
+    }
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let fraction = format!("0.{}", value).parse::<f64>().map_err(|_| invalid())?;
+    Ok((fraction * 100.0).round() as usize)
+}
+
+/** 两个文件内容的相似度(0~100)：按行统计相同内容的字节数，除以较大文件的字节数
+<br>与Git的estimate_similarity类似，行的顺序不影响结果
+ */
+pub fn similarity(a: &[u8], b: &[u8]) -> usize {
+    if a == b {
+        return 100;
+    }
+    let max_size = a.len().max(b.len());
+    if max_size == 0 {
+        return 100;
+    }
+    let mut lines: HashMap<&[u8], isize> = HashMap::new();
+    for line in a.split_inclusive(|&c| c == b'\n') {
+        *lines.entry(line).or_default() += 1;
+    }
+    let mut common = 0;
+    for line in b.split_inclusive(|&c| c == b'\n') {
+        if let Some(count) = lines.get_mut(line) {
+            if *count > 0 {
+                *count -= 1;
+                common += line.len();
+            }
+        }
+    }
+    common * 100 / max_size
+}
+
+/** 按相似度为targets寻找来源：返回(source下标, target下标, 相似度)，相似度从高到低贪心匹配
+<br>每个target最多匹配一次；exclusive为true（重命名）时每个source也只能匹配一次，否则（复制）可以多次
+<br>空文件不参与匹配（与Git相同）
+ */
+pub fn find_matches<S, T>(sources: &[S], targets: &[T], threshold: usize, exclusive: bool) -> Vec<(usize, usize, usize)>
+where
+    S: AsRef<[u8]>,
+    T: AsRef<[u8]>,
+{
+    let mut candidates = Vec::new();
+    for (i, source) in sources.iter().enumerate().filter(|(_, s)| !s.as_ref().is_empty()) {
+        for (j, target) in targets.iter().enumerate().filter(|(_, t)| !t.as_ref().is_empty()) {
+            let score = similarity(source.as_ref(), target.as_ref());
+            if score >= threshold {
+                candidates.push((score, i, j));
+            }
+        }
+    }
+    // 相似度高的优先，相同时按下标排序，保证结果稳定
+    candidates.sort_by(|x, y| y.0.cmp(&x.0).then((x.1, x.2).cmp(&(y.1, y.2))));
+
+    let (mut used_sources, mut used_targets) = (vec![false; sources.len()], vec![false; targets.len()]);
+    let mut result = Vec::new();
+    for (score, i, j) in candidates {
+        if used_targets[j] || (exclusive && used_sources[i]) {
+            continue;
+        }
+        used_sources[i] = true;
+        used_targets[j] = true;
+        result.push((i, j, score));
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!(parse_threshold("50%"), Ok(50));
+        assert_eq!(parse_threshold("100%"), Ok(100));
+        assert_eq!(parse_threshold("5"), Ok(50));
+        assert_eq!(parse_threshold("05"), Ok(5));
+        assert_eq!(parse_threshold("95"), Ok(95));
+        assert!(parse_threshold("101%").is_err());
+        assert!(parse_threshold("abc").is_err());
+        assert!(parse_threshold("").is_err());
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(similarity(b"a\nb\n", b"a\nb\n"), 100);
+        assert_eq!(similarity(b"a\nb\nc\nd\n", b"a\nb\nc\nx\n"), 75);
+        assert_eq!(similarity(b"a\nb\n", b"b\na\n"), 100);
+        assert_eq!(similarity(b"a\n", b"x\n"), 0);
+        assert_eq!(similarity(b"a\nb\n", b"a\nb\nc\nd\n"), 50);
+    }
+
+    #[test]
+    fn test_find_matches() {
+        let sources: [&[u8]; 2] = [b"1\n2\n3\n4\n", b"x\ny\n"];
+        let targets: [&[u8]; 3] = [b"x\ny\nz\n", b"1\n2\n3\n5\n", b""];
+        assert_eq!(find_matches(&sources, &targets, 50, true), vec![(0, 1, 75), (1, 0, 66)]);
+        assert_eq!(find_matches(&sources, &targets, 70, true), vec![(0, 1, 75)]);
+
+        // 复制：同一个source可以匹配多个target
+        let targets: [&[u8]; 2] = [b"1\n2\n3\n4\n", b"1\n2\n3\n4\n"];
+        assert_eq!(find_matches(&sources, &targets, 50, true).len(), 1);
+        assert_eq!(find_matches(&sources, &targets, 50, false).len(), 2);
+    }
+}
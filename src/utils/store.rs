@@ -1,56 +1,192 @@
-use std::path::PathBuf;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    io::{BufReader, Read, Write},
+    path::PathBuf,
+    rc::Rc,
+    time::SystemTime,
+};
 
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
 use sha1::{Digest, Sha1};
 
-use crate::models::Hash;
+use crate::models::{Hash, ObjectType};
 
-use super::util;
+use super::{config, config::ObjectFormat, pack::Pack, util};
+
+thread_local! {
+    /// pack目录 -> 其中的pack，进程内共享（每次读取object都会创建Store）；延迟加载，只有loose object不存在时才需要读取pack
+    static PACKS: RefCell<HashMap<PathBuf, Rc<Vec<Pack>>>> = RefCell::new(HashMap::new());
+}
 
 /// 管理.mit仓库的读写
 pub struct Store {
     store_path: PathBuf,
+    format: ObjectFormat,
 }
 
 /**Store负责管理objects
  * 每一个object文件名与内容的hash值相同
+ * <br>与Git相同，hash = sha1("{type} {size}\0{content}")，只与内容有关，与压缩算法无关
+ * <br>object文件 = zlib("{type} {size}\0{content}")，压缩只是储存细节
+ * <br>object可以是loose object（每个object一个文件），也可以被`mit gc`打包到objects/pack/中，读取时对两者透明
  */
 impl Store {
-    fn calc_hash(data: &String) -> String {
+    /// 构造object头部 "{type} {size}\0" 并拼接内容
+    fn wrap_object(obj_type: ObjectType, data: &[u8]) -> Vec<u8> {
+        assert_ne!(obj_type, ObjectType::Invalid, "无法保存Invalid类型的object");
+        let mut object = format!("{} {}\0", obj_type, data.len()).into_bytes();
+        object.extend_from_slice(data);
+        object
+    }
+
+    fn calc_hash(object: &[u8]) -> String {
         let mut hasher = Sha1::new();
-        hasher.update(data);
+        hasher.update(object);
         let hash = hasher.finalize();
         hex::encode(hash)
     }
 
+    fn compress(object: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(object).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn decompress(compressed: &[u8]) -> Option<Vec<u8>> {
+        let mut decoder = ZlibDecoder::new(compressed);
+        let mut object = Vec::new();
+        decoder.read_to_end(&mut object).ok()?;
+        Some(object)
+    }
+
+    /// 拆分object为(type, content)，并校验size
+    fn unwrap_object(object: &[u8]) -> Option<(ObjectType, Vec<u8>)> {
+        let nul = object.iter().position(|&b| b == 0)?;
+        let (obj_type, size) = Self::parse_header(&object[..nul])?;
+        let content = object[nul + 1..].to_vec();
+        if size != content.len() {
+            return None;
+        }
+        Some((obj_type, content))
+    }
+
+    /// 解析头部 "{type} {size}"（不含\0）
+    fn parse_header(header: &[u8]) -> Option<(ObjectType, usize)> {
+        let header = std::str::from_utf8(header).ok()?;
+        let (obj_type, size) = header.split_once(' ')?;
+        match ObjectType::parse(obj_type) {
+            ObjectType::Invalid => None,
+            obj_type => Some((obj_type, size.parse().ok()?)),
+        }
+    }
+
+    fn objects_dir(&self) -> PathBuf {
+        self.store_path.join("objects")
+    }
+
+    fn pack_dir(&self) -> PathBuf {
+        self.objects_dir().join("pack")
+    }
+
+    fn packs(&self) -> Rc<Vec<Pack>> {
+        PACKS.with(|packs| {
+            let mut packs = packs.borrow_mut();
+            packs
+                .entry(self.pack_dir())
+                .or_insert_with_key(|dir| Rc::new(Pack::list(dir)))
+                .clone()
+        })
+    }
+
+    /// 清除已加载的pack，pack被修改或仓库被删除后需要调用
+    pub fn reload_packs() {
+        PACKS.with(|packs| packs.borrow_mut().clear());
+    }
+
+    /// Git格式下按hash前两位分目录：objects/ab/cdef...
+    fn object_path(&self, hash: &String) -> PathBuf {
+        let objects = self.objects_dir();
+        match self.format {
+            ObjectFormat::Mit => objects.join(hash),
+            ObjectFormat::Git if hash.len() > 2 => objects.join(&hash[..2]).join(&hash[2..]),
+            ObjectFormat::Git => objects.join(hash),
+        }
+    }
+
+    /// 仓库的object格式，决定tree & commit的编码方式
+    pub fn format(&self) -> ObjectFormat {
+        self.format
+    }
+
     pub fn new() -> Store {
         util::check_repo_exist();
         let store_path = util::get_storage_path().unwrap();
-        Store { store_path }
+        Store { store_path, format: config::object_format() }
     }
-    pub fn load(&self, hash: &String) -> String {
+    /// 读取object，返回(类型, 内容)（内容不含头部）
+    pub fn load(&self, hash: &String) -> (ObjectType, Vec<u8>) {
         /* 读取文件内容 */
-        let mut path = self.store_path.clone();
-        path.push("objects");
-        path.push(hash);
-        match std::fs::read_to_string(path) {
-            Ok(content) => content,
-            Err(_) => panic!("储存库疑似损坏，无法读取文件"),
+        if !self.contains(hash) {
+            panic!("储存库疑似损坏，无法读取文件: {}", hash);
+        }
+        match self.try_load(hash) {
+            Some(object) => object,
+            None => panic!("储存库疑似损坏，object格式错误: {}", hash),
+        }
+    }
+
+    /// 与[Store::load]相同，但object不存在或格式错误时返回None，不会panic
+    pub fn try_load(&self, hash: &String) -> Option<(ObjectType, Vec<u8>)> {
+        match std::fs::read(self.object_path(hash)) {
+            Ok(compressed) => Self::unwrap_object(&Self::decompress(&compressed)?),
+            Err(_) => self.packs().iter().find(|pack| pack.contains(hash))?.load(hash),
+        }
+    }
+
+    /// 重新计算object的hash，检查内容是否与hash(文件名)一致
+    pub fn verify(&self, hash: &String) -> bool {
+        match self.try_load(hash) {
+            Some((obj_type, content)) => Self::calc_hash(&Self::wrap_object(obj_type, &content)) == *hash,
+            None => false,
         }
     }
 
+    /// object是否存在（loose or packed）
+    pub fn contains(&self, hash: &String) -> bool {
+        self.object_path(hash).is_file() || self.packs().iter().any(|pack| pack.contains(hash))
+    }
+
+    /// 只解压头部来获取object类型，不存在或无法识别时返回Invalid
+    pub fn object_type(&self, hash: &String) -> ObjectType {
+        let file = match std::fs::File::open(self.object_path(hash)) {
+            Ok(file) => file,
+            Err(_) => {
+                let packs = self.packs();
+                let pack = packs.iter().find(|pack| pack.contains(hash));
+                return pack
+                    .and_then(|pack| pack.load(hash))
+                    .map_or(ObjectType::Invalid, |(obj_type, _)| obj_type);
+            }
+        };
+        let mut header = Vec::new();
+        for byte in BufReader::new(ZlibDecoder::new(file)).bytes() {
+            match byte {
+                Ok(0) => break,
+                Ok(b) => header.push(b),
+                Err(_) => return ObjectType::Invalid,
+            }
+        }
+        Self::parse_header(&header).map_or(ObjectType::Invalid, |(obj_type, _)| obj_type)
+    }
+
     /** 根据前缀搜索，有歧义时返回 None */
     pub fn search(&self, hash: &String) -> Option<Hash> {
         if hash.is_empty() {
             return None;
         }
-        let objects = util::list_files(self.store_path.join("objects").as_path()).unwrap();
-        // 转string
-        let objects = objects
-            .iter()
-            .map(|x| x.file_name().unwrap().to_str().unwrap().to_string())
-            .collect::<Vec<String>>();
         let mut result = None;
-        for object in objects {
+        for object in self.list_objects() {
             if object.starts_with(hash) {
                 if result.is_some() {
                     return None;
@@ -61,30 +197,132 @@ impl Store {
         result
     }
 
-    pub fn save(&self, content: &String) -> Hash {
+    /// 保存object，obj_type: blob | tree | commit
+    pub fn save(&self, obj_type: ObjectType, content: &[u8]) -> Hash {
         /* 保存文件内容 */
-        let hash = Self::calc_hash(content);
-        let mut path = self.store_path.clone();
-        path.push("objects");
-        path.push(&hash);
+        let object = Self::wrap_object(obj_type, content);
+        let hash = Self::calc_hash(&object);
+        let path = self.object_path(&hash);
         // println!("Saved to: [{}]", path.display());
-        if path.exists() {
-            // IO优化，文件已存在，不再写入
+        if self.contains(&hash) {
+            // IO优化，文件已存在(loose or packed)，不再写入
             return hash;
         }
-        match std::fs::write(path, content) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        match std::fs::write(path, Self::compress(&object)) {
             Ok(_) => hash,
             Err(_) => panic!("储存库疑似损坏，无法写入文件"),
         }
     }
 
-    pub fn dry_save(&self, content: &String) -> Hash {
+    /// 保存为loose object（即使已经打包），并设置文件的修改时间
+    fn save_loose(&self, obj_type: ObjectType, content: &[u8], mtime: SystemTime) {
+        let object = Self::wrap_object(obj_type, content);
+        let path = self.object_path(&Self::calc_hash(&object));
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, Self::compress(&object)).expect("储存库疑似损坏，无法写入文件");
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(mtime).expect("无法设置修改时间");
+    }
+
+    pub fn dry_save(&self, obj_type: ObjectType, content: &[u8]) -> Hash {
         /* 不实际保存文件，返回Hash */
         #[warn(clippy::let_and_return)]
-        let hash = Self::calc_hash(content);
+        let hash = Self::calc_hash(&Self::wrap_object(obj_type, content));
         // TODO more such as  check
         hash
     }
+
+    /// 列出所有loose object（不包括pack）
+    pub fn list_loose(&self) -> Vec<Hash> {
+        let objects_dir = self.objects_dir();
+        let pack_dir = self.pack_dir();
+        let objects = util::list_files(objects_dir.as_path()).unwrap();
+        // 转string，分目录存放时 hash = 目录名 + 文件名
+        objects
+            .iter()
+            .filter(|x| !x.starts_with(&pack_dir))
+            .map(|x| x.strip_prefix(&objects_dir).unwrap().to_str().unwrap().replace(['/', '\\'], ""))
+            .collect()
+    }
+
+    /// 列出所有object（loose & packed），有序且不重复
+    pub fn list_objects(&self) -> Vec<Hash> {
+        let mut objects = self.list_loose();
+        for pack in self.packs().iter() {
+            objects.extend_from_slice(pack.hashes());
+        }
+        objects.sort();
+        objects.dedup();
+        objects
+    }
+
+    /// object的修改时间：loose object取文件的mtime，packed object取pack文件的mtime
+    pub fn mtime(&self, hash: &String) -> Option<SystemTime> {
+        let path = match self.object_path(hash) {
+            path if path.is_file() => path,
+            _ => self.packs().iter().find(|pack| pack.contains(hash))?.get_path(),
+        };
+        std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    /// 删除loose object，返回是否删除成功（packed object不受影响）
+    pub fn remove_loose(&self, hash: &String) -> bool {
+        let path = self.object_path(hash);
+        if std::fs::remove_file(&path).is_err() {
+            return false;
+        }
+        let parent = path.parent().unwrap();
+        if parent != self.objects_dir() && util::is_empty_dir(parent) {
+            std::fs::remove_dir(parent).unwrap(); // Git格式下的空目录 objects/ab/
+        }
+        true
+    }
+
+    /** 将所有object（loose & 旧的pack）打包为一个新的pack，然后删除loose object和旧的pack
+    <br>`exclude`中的object不会被打包，即被丢弃（用于`mit gc`清理不可达的object）
+    <br>`loose`中的object也不会被打包，而是保留为loose object（已打包的写出为loose object，修改时间取原pack的修改时间），
+    与Git相同，用于`mit gc`保留宽限期内的不可达object：它们的修改时间不会因为重新打包而更新，过期后可以被删除
+    <br>返回(打包的object数量, 以delta储存的数量)
+     */
+    pub fn repack(&self, exclude: &HashSet<Hash>, loose: &HashSet<Hash>) -> (usize, usize) {
+        for hash in loose.iter().filter(|hash| !exclude.contains(*hash)) {
+            if !self.object_path(hash).is_file() {
+                let mtime = self.mtime(hash).expect("object不存在");
+                let (obj_type, content) = self.load(hash);
+                self.save_loose(obj_type, &content, mtime);
+            }
+        }
+        let objects = self
+            .list_objects()
+            .into_iter()
+            .filter(|hash| !exclude.contains(hash) && !loose.contains(hash))
+            .map(|hash| {
+                let (obj_type, content) = self.load(&hash);
+                (hash, obj_type, content)
+            })
+            .collect::<Vec<_>>();
+        let count = objects.len();
+        if count == 0 && self.packs().is_empty() {
+            return (0, 0);
+        }
+        let (new_pack, deltas) = match count {
+            0 => (PathBuf::new(), 0), // 没有需要保留的object，只删除旧的数据
+            _ => Pack::write(&self.pack_dir(), objects).expect("无法写入pack"),
+        };
+
+        // 新的pack写入成功后，才能删除旧的数据
+        for pack in self.packs().iter() {
+            if pack.get_path() != new_pack {
+                pack.remove().expect("无法删除旧的pack");
+            }
+        }
+        Self::reload_packs();
+        for hash in self.list_loose().into_iter().filter(|hash| !loose.contains(hash)) {
+            assert!(self.remove_loose(&hash), "无法删除loose object");
+        }
+        (count, deltas)
+    }
 }
 #[cfg(test)]
 mod tests {
@@ -110,16 +348,92 @@ mod tests {
     fn test_save_and_load() {
         test::setup_with_clean_mit();
         let store = Store::new();
-        let content = "hello world".to_string();
-        let hash = store.save(&content);
-        let content2 = store.load(&hash);
+        let content = "hello world".as_bytes();
+        let hash = store.save(ObjectType::Blob, content);
+        let (obj_type, content2) = store.load(&hash);
+        assert_eq!(obj_type, ObjectType::Blob);
         assert_eq!(content, content2, "内容不一致");
     }
 
+    #[test]
+    fn test_hash_is_canonical() {
+        test::setup_with_clean_mit();
+        let store = Store::new();
+        // 与 `git hash-object` 的结果一致，与压缩无关
+        let hash = store.save(ObjectType::Blob, "hello world".as_bytes());
+        assert_eq!(hash, "95d09f2b10159347eece71399a7e2e907ea3df4f");
+        assert_eq!(store.dry_save(ObjectType::Blob, "hello world".as_bytes()), hash);
+        // 类型不同，hash不同
+        assert_ne!(store.dry_save(ObjectType::Tree, "hello world".as_bytes()), hash);
+    }
+
+    #[test]
+    fn test_git_format_fan_out() {
+        test::setup_with_clean_mit_git_format();
+        let store = Store::new();
+        assert_eq!(store.format(), ObjectFormat::Git);
+        let hash = store.save(ObjectType::Blob, "hello world".as_bytes());
+        let path = util::get_storage_path()
+            .unwrap()
+            .join("objects")
+            .join(&hash[..2])
+            .join(&hash[2..]);
+        assert!(path.exists());
+        assert_eq!(store.load(&hash).1, "hello world".as_bytes());
+        assert_eq!(store.search(&hash[..6].to_string()), Some(hash));
+    }
+
+    #[test]
+    fn test_repack() {
+        test::setup_with_clean_mit_git_format();
+        let store = Store::new();
+        let base = "some text that will be edited\n".repeat(100);
+        let hash1 = store.save(ObjectType::Blob, base.as_bytes());
+        let hash2 = store.save(ObjectType::Blob, (base.clone() + "edited\n").as_bytes());
+        let tree = store.save(ObjectType::Tree, &[]);
+        assert_eq!(store.repack(&HashSet::new(), &HashSet::new()), (3, 1));
+
+        let store = Store::new();
+        assert!(Rc::ptr_eq(&store.packs(), &Store::new().packs())); // 进程内共享已加载的pack
+        assert!(store.list_loose().is_empty());
+        assert_eq!(store.list_objects().len(), 3);
+        assert_eq!(store.load(&hash1), (ObjectType::Blob, base.as_bytes().to_vec()));
+        assert_eq!(store.object_type(&hash2), ObjectType::Blob);
+        assert_eq!(store.object_type(&tree), ObjectType::Tree);
+        assert_eq!(store.search(&hash2[..8].to_string()), Some(hash2.clone()));
+        assert_eq!(store.save(ObjectType::Blob, base.as_bytes()), hash1); // 已打包，不再写入loose
+        assert!(store.list_loose().is_empty());
+
+        // 新的loose object与旧的pack合并为一个pack
+        store.save(ObjectType::Blob, "new".as_bytes());
+        assert_eq!(store.repack(&HashSet::new(), &HashSet::new()).0, 4);
+        let store = Store::new();
+        assert!(store.list_loose().is_empty());
+        assert_eq!(Pack::list(&store.pack_dir()).len(), 1);
+        assert_eq!(store.load(&hash2).1, (base + "edited\n").as_bytes());
+
+        // 被排除的object会被丢弃
+        assert_eq!(store.repack(&HashSet::from([tree.clone()]), &HashSet::new()).0, 3);
+        let store = Store::new();
+        assert!(!store.contains(&tree));
+        assert!(store.mtime(&hash1).is_some());
+        assert_eq!(store.repack(&store.list_objects().into_iter().collect(), &HashSet::new()).0, 0);
+        assert!(Store::new().list_objects().is_empty());
+    }
+
+    #[test]
+    fn test_object_type() {
+        test::setup_with_clean_mit();
+        let store = Store::new();
+        let hash = store.save(ObjectType::Tree, "{}".as_bytes());
+        assert_eq!(store.object_type(&hash), ObjectType::Tree);
+        assert_eq!(store.object_type(&"not_exist".to_string()), ObjectType::Invalid);
+    }
+
     #[test]
     fn test_search() {
         test::setup_with_clean_mit();
-        let hashs = vec!["1234567890".to_string(), "1235467891".to_string(), "4567892".to_string()];
+        let hashs = ["1234567890".to_string(), "1235467891".to_string(), "4567892".to_string()];
         for hash in hashs.iter() {
             let mut path = util::get_storage_path().unwrap();
             path.push("objects");
@@ -8,7 +8,7 @@ use std::{
 };
 
 use crate::models::Index;
-use crate::utils::PathExt;
+use crate::utils::{config::ObjectFormat, store::Store, PathExt};
 
 // 执行测试的储存库
 use super::util;
@@ -48,7 +48,11 @@ fn setup_env() {
 }
 
 pub fn init_mit() {
-    let _ = crate::commands::init();
+    init_mit_with_format(ObjectFormat::Mit);
+}
+
+pub fn init_mit_with_format(object_format: ObjectFormat) {
+    let _ = crate::commands::init(object_format);
     Index::reload(); // 重置index, 以防止其他测试修改了index单例
 }
 
@@ -58,6 +62,12 @@ pub fn setup_with_clean_mit() {
     init_mit();
 }
 
+/// with 初始化的干净的mit，使用Git兼容的object格式
+pub fn setup_with_clean_mit_git_format() {
+    setup_without_mit();
+    init_mit_with_format(ObjectFormat::Git);
+}
+
 pub fn setup_without_mit() {
     // 将执行目录切换到测试目录，并清除测试目录下的.mit目录
     setup_env();
@@ -66,6 +76,7 @@ pub fn setup_without_mit() {
     if path.exists() {
         fs::remove_dir_all(&path).unwrap();
     }
+    Store::reload_packs(); // 清除已加载的pack，以防止使用其他测试的仓库中的pack
 }
 
 pub fn ensure_files<T: AsRef<str>>(paths: &Vec<T>) {
@@ -4,7 +4,9 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::models::{commit::Commit, object::Hash, tree::Tree};
+use crate::models::{Hash, ObjectType};
+
+use super::store::Store;
 
 pub const ROOT_DIR: &str = ".mit";
 
@@ -203,11 +205,7 @@ pub fn cur_dir() -> PathBuf {
 
 /// 列出工作区所有文件(包括子文件夹)
 pub fn list_workdir_files() -> Vec<PathBuf> {
-    if let Ok(files) = list_files(&get_working_dir().unwrap()) {
-        files
-    } else {
-        Vec::new()
-    }
+    list_files(&get_working_dir().unwrap()).unwrap_or_default()
 }
 
 /// 获取相对于dir的 规范化 相对路径（不包含../ ./）
@@ -310,9 +308,7 @@ pub fn get_absolute_path_to_dir(path: &Path, dir: &Path) -> PathBuf {
         for component in path.components() {
             match component {
                 std::path::Component::ParentDir => {
-                    if !abs_path.pop() {
-                        panic!("relative path parse error");
-                    }
+                    assert!(abs_path.pop(), "relative path parse error");
                 }
                 std::path::Component::Normal(part) => abs_path.push(part),
                 std::path::Component::CurDir => {}
@@ -338,28 +334,9 @@ pub fn integrate_paths(paths: &Vec<PathBuf>) -> HashSet<PathBuf> {
     abs_paths
 }
 
-#[derive(Debug, PartialEq)]
-pub enum ObjectType {
-    Blob,
-    Tree,
-    Commit,
-    Invalid,
-}
+/// 根据object头部获取类型，不再通过反序列化猜测
 pub fn check_object_type(hash: Hash) -> ObjectType {
-    let path = get_storage_path().unwrap().join("objects").join(hash);
-    if path.exists() {
-        let data = fs::read_to_string(path).unwrap(); //TODO store::load?
-        let result: Result<Commit, serde_json::Error> = serde_json::from_str(&data);
-        if result.is_ok() {
-            return ObjectType::Commit;
-        }
-        let result: Result<Tree, serde_json::Error> = serde_json::from_str(&data);
-        if result.is_ok() {
-            return ObjectType::Tree;
-        }
-        return ObjectType::Blob;
-    }
-    ObjectType::Invalid
+    Store::new().object_type(&hash)
 }
 
 /// 判断hash对应的文件是否是commit
@@ -367,23 +344,23 @@ pub fn is_typeof_commit(hash: Hash) -> bool {
     check_object_type(hash) == ObjectType::Commit
 }
 
-/// 将内容对应的文件内容(主要是blob)还原到file
-pub fn write_workfile(content: String, file: &PathBuf) {
+/// 将内容对应的文件内容(主要是blob)还原到file，按原始字节写入
+pub fn write_workfile(content: Vec<u8>, file: &PathBuf) {
     let mut parent = file.clone();
     parent.pop();
     std::fs::create_dir_all(parent).unwrap();
     std::fs::write(file, content).unwrap();
 }
 
-/// 从工作区读取文件内容
-pub fn read_workfile(file: &Path) -> String {
-    std::fs::read_to_string(file).unwrap()
+/// 从工作区读取文件内容（原始字节，不要求UTF-8）
+pub fn read_workfile(file: &Path) -> Vec<u8> {
+    std::fs::read(file).unwrap()
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        models::{blob::Blob, index::Index},
+        models::{blob::Blob, commit::Commit, index::Index},
         utils::{
             test,
             util::{self, *},
@@ -489,6 +466,14 @@ mod tests {
         assert_eq!(check_object_type(commit.get_tree_hash()), ObjectType::Tree);
         commit.save();
         assert_eq!(check_object_type(commit.get_hash()), ObjectType::Commit);
+
+        // 内容恰好是合法的commit/tree JSON的blob，仍然是blob
+        let commit_json = Store::new().load(&commit.get_hash()).1;
+        let hash = Blob::new(commit_json).get_hash();
+        assert_eq!(check_object_type(hash), ObjectType::Blob);
+        let tree_json = Store::new().load(&commit.get_tree_hash()).1;
+        let hash = Blob::new(tree_json).get_hash();
+        assert_eq!(check_object_type(hash), ObjectType::Blob);
     }
 
     #[test]
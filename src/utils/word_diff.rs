@@ -0,0 +1,185 @@
+use regex::Regex;
+
+use super::diff::{edit_script, DiffAlgorithm, Edit};
+
+/// 默认的单词：连续的非空白字符（与Git相同）；`--word-diff-regex=.`则按字符比较
+pub const DEFAULT_WORD_REGEX: &str = r"\S+";
+
+// `--word-diff=color`使用的ANSI颜色
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[m";
+
+/// `--word-diff`的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WordDiffMode {
+    /// 删除的单词标红，新增的单词标绿
+    Color,
+    /// 删除的单词写作`[-word-]`，新增的单词写作`{+word+}`
+    Plain,
+    /// 面向脚本：每段单独一行，以` `、`-`、`+`开头，原文中的换行写作`~`
+    Porcelain,
+}
+
+/// 单词级diff的一段文本
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Equal(String),
+    Delete(String),
+    Insert(String),
+}
+
+/// 文本中所有单词的位置(start, end)，单词以外的部分（空白）不参与比较
+fn words(text: &str, regex: &Regex) -> Vec<(usize, usize)> {
+    regex
+        .find_iter(text)
+        .filter(|m| !m.as_str().is_empty())
+        .map(|m| (m.start(), m.end()))
+        .collect()
+}
+
+/** 按单词比较old与new，返回合并后的文本段；单词之间的空白取自new
+<br>连续的删除 & 新增合并为一段，删除在前
+ */
+pub fn word_diff(old: &str, new: &str, regex: &Regex, algorithm: DiffAlgorithm) -> Vec<Segment> {
+    let (old_words, new_words) = (words(old, regex), words(new, regex));
+    let old_tokens = old_words.iter().map(|&(s, e)| &old[s..e]).collect::<Vec<_>>();
+    let new_tokens = new_words.iter().map(|&(s, e)| &new[s..e]).collect::<Vec<_>>();
+    let script = edit_script(&algorithm.matches(&old_tokens, &new_tokens), old_tokens.len(), new_tokens.len());
+
+    let mut segments = Vec::new();
+    let mut push = |segment: Segment| match (segments.last_mut(), segment) {
+        (Some(Segment::Equal(last)), Segment::Equal(text)) => last.push_str(&text),
+        (_, Segment::Equal(text)) if text.is_empty() => {}
+        (_, segment) => segments.push(segment),
+    };
+    let mut new_pos = 0; // new中已输出的位置
+    let mut k = 0;
+    while k < script.len() {
+        if let Edit::Equal(_, j) = script[k] {
+            push(Segment::Equal(new[new_pos..new_words[j].1].to_string()));
+            new_pos = new_words[j].1;
+            k += 1;
+            continue;
+        }
+        // 一组连续的修改
+        let end = script[k..]
+            .iter()
+            .position(|edit| matches!(edit, Edit::Equal(..)))
+            .map_or(script.len(), |p| k + p);
+        let deleted = script[k..end]
+            .iter()
+            .filter_map(|e| if let Edit::Delete(i) = e { Some(*i) } else { None });
+        let inserted = script[k..end]
+            .iter()
+            .filter_map(|e| if let Edit::Insert(j) = e { Some(*j) } else { None });
+        let deleted = deleted.collect::<Vec<_>>();
+        let inserted = inserted.collect::<Vec<_>>();
+
+        // 修改之前的空白：有新增时到第一个新增的单词，否则到下一个未修改的单词
+        let next_new = inserted.first().copied().or_else(|| match script.get(end) {
+            Some(Edit::Equal(_, j)) => Some(*j),
+            _ => None,
+        });
+        if let Some(j) = next_new {
+            push(Segment::Equal(new[new_pos..new_words[j].0].to_string()));
+            new_pos = new_words[j].0;
+        }
+        if let (Some(first), Some(last)) = (deleted.first(), deleted.last()) {
+            push(Segment::Delete(old[old_words[*first].0..old_words[*last].1].to_string()));
+        }
+        if let (Some(first), Some(last)) = (inserted.first(), inserted.last()) {
+            push(Segment::Insert(new[new_words[*first].0..new_words[*last].1].to_string()));
+            new_pos = new_words[*last].1;
+        }
+        k = end;
+    }
+    push(Segment::Equal(new[new_pos..].to_string()));
+    segments
+}
+
+/// 将文本段按格式输出为行（不含换行符）
+pub fn format(segments: &[Segment], mode: WordDiffMode) -> Vec<String> {
+    if mode == WordDiffMode::Porcelain {
+        let mut lines = Vec::new();
+        for segment in segments {
+            let (prefix, text) = match segment {
+                Segment::Equal(text) => (' ', text),
+                Segment::Delete(text) => ('-', text),
+                Segment::Insert(text) => ('+', text),
+            };
+            for (i, piece) in text.split('\n').enumerate() {
+                if i > 0 {
+                    lines.push("~".to_string());
+                }
+                if !piece.is_empty() {
+                    lines.push(format!("{}{}", prefix, piece));
+                }
+            }
+        }
+        return lines;
+    }
+
+    // 每行单独标记，避免标记跨行（与Git相同）
+    let wrap = |s: &str, open: &str, close: &str| {
+        let lines = s.split('\n').map(|line| match line.is_empty() {
+            true => String::new(),
+            false => format!("{}{}{}", open, line, close),
+        });
+        lines.collect::<Vec<_>>().join("\n")
+    };
+    let mut text = String::new();
+    for segment in segments {
+        match (segment, mode) {
+            (Segment::Equal(s), _) => text.push_str(s),
+            (Segment::Delete(s), WordDiffMode::Plain) => text.push_str(&wrap(s, "[-", "-]")),
+            (Segment::Insert(s), WordDiffMode::Plain) => text.push_str(&wrap(s, "{+", "+}")),
+            // 与Git相同，不论输出是否为终端都使用ANSI颜色，否则输出到管道、文件时没有任何修改标记
+            (Segment::Delete(s), _) => text.push_str(&wrap(s, RED, RESET)),
+            (Segment::Insert(s), _) => text.push_str(&wrap(s, GREEN, RESET)),
+        }
+    }
+    let text = text.strip_suffix('\n').unwrap_or(&text);
+    text.split('\n').map(|line| line.to_string()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn plain(old: &str, new: &str, regex: &str) -> String {
+        let segments = word_diff(old, new, &Regex::new(regex).unwrap(), DiffAlgorithm::Myers);
+        format(&segments, WordDiffMode::Plain).join("\n")
+    }
+
+    #[test]
+    fn test_word_diff() {
+        assert_eq!(plain("a b c\n", "a b c\n", DEFAULT_WORD_REGEX), "a b c");
+        assert_eq!(plain("a b c\n", "a c\n", DEFAULT_WORD_REGEX), "a [-b-]c");
+        assert_eq!(plain("a c\n", "a b c\n", DEFAULT_WORD_REGEX), "a {+b+} c");
+        assert_eq!(plain("port = 80\n", "port = 8080\n", DEFAULT_WORD_REGEX), "port = [-80-]{+8080+}");
+        assert_eq!(plain("one two\nthree\n", "one 2\nthree\n", DEFAULT_WORD_REGEX), "one [-two-]{+2+}\nthree");
+        assert_eq!(plain("a b\n", "a\n", DEFAULT_WORD_REGEX), "a[-b-]");
+        // 跨行的修改每行单独标记
+        assert_eq!(
+            plain("keep\nalpha one\nbeta two\nend\n", "keep\ngamma\nend\n", DEFAULT_WORD_REGEX),
+            "keep\n[-alpha one-]\n[-beta two-]{+gamma+}\nend"
+        );
+        // 按字符比较
+        assert_eq!(plain("color\n", "colour\n", "."), "colo{+u+}r");
+    }
+
+    #[test]
+    fn test_color() {
+        let segments = word_diff("a b\n", "a x\n", &Regex::new(DEFAULT_WORD_REGEX).unwrap(), DiffAlgorithm::Myers);
+        // 测试的输出不是终端，仍然使用颜色
+        assert_eq!(format(&segments, WordDiffMode::Color), ["a \x1b[31mb\x1b[m\x1b[32mx\x1b[m"]);
+    }
+
+    #[test]
+    fn test_porcelain() {
+        let segments =
+            word_diff("a b\nc\n", "a x\nc\n", &Regex::new(DEFAULT_WORD_REGEX).unwrap(), DiffAlgorithm::Myers);
+        assert_eq!(format(&segments, WordDiffMode::Porcelain), [" a ", "-b", "+x", "~", " c", "~"]);
+    }
+}
@@ -0,0 +1,19 @@
+fn sum(values: &[u32]) -> u32 {
+    values.iter().sum()
+}
+
+fn parse(input: &str) -> Result<Vec<u32>, String> {
+    let mut result = Vec::new();
+    for line in input.lines() {
+        if line.is_empty() {
+            continue;
+        }
+        result.push(line.parse().map_err(|e| format!("{:?}", e))?);
+    }
+    Ok(result)
+}
+
+fn main() {
+    let values = parse("1\n2\n3\n").unwrap();
+    println!("{}", sum(&values));
+}
@@ -0,0 +1,23 @@
+fn parse(input: &str) -> Vec<u32> {
+    let mut result = Vec::new();
+    for line in input.lines() {
+        if line.is_empty() {
+            continue;
+        }
+        result.push(line.parse().unwrap());
+    }
+    result
+}
+
+fn sum(values: &[u32]) -> u32 {
+    let mut total = 0;
+    for value in values {
+        total += value;
+    }
+    total
+}
+
+fn main() {
+    let values = parse("1\n2\n3\n");
+    println!("{}", sum(&values));
+}